@@ -4,11 +4,11 @@
 mod assets;
 
 use fixed::types::I10F6;
-use vb_graphics as gfx;
-use vb_rt::{
-    println,
-    sys::{hardware, vip},
+use vb_graphics::{
+    self as gfx,
+    worlds::{WorldBuilder, WorldList},
 };
+use vb_rt::{println, sys::hardware};
 
 vb_rt::rom_header!("Hello World!", "SG", "HIYA");
 vb_rt::main!({ main() });
@@ -32,36 +32,19 @@ fn main() {
     let mut counter = 0;
     let mut smile_x = I10F6::from_num(184);
     let mut smile_y = I10F6::from_num(104);
+    let mut worlds = WorldList::new();
 
     loop {
-        let world = vip::WORLDS.index(31);
-        world.header().write(
-            vip::WorldHeader::new()
-                .with_lon(true)
-                .with_ron(true)
-                .with_bgm(vip::WorldMode::Normal)
-                .with_bg_map_base(0),
-        );
-        world.w().write(383);
-        world.h().write(223);
-
-        let world = vip::WORLDS.index(30);
-        world.header().write(
-            vip::WorldHeader::new()
-                .with_lon(true)
-                .with_ron(true)
-                .with_bgm(vip::WorldMode::Normal)
-                .with_bg_map_base(0),
-        );
-        world.gx().write(smile_x.round().to_num());
-        world.gy().write(smile_y.round().to_num());
-        world.mx().write(384);
-        world.my().write(0);
-        world.w().write(15);
-        world.h().write(15);
-
-        let world = vip::WORLDS.index(29);
-        world.header().write(vip::WorldHeader::new().with_end(true));
+        worlds.clear();
+        worlds.push(WorldBuilder::normal(0)).unwrap();
+        worlds
+            .push(
+                WorldBuilder::normal(0)
+                    .position(smile_x.round().to_num(), smile_y.round().to_num())
+                    .source(384, 0)
+                    .size(16, 16),
+            )
+            .unwrap();
 
         let buttons = hardware::read_controller();
         let mut xspeed = I10F6::ZERO;
@@ -87,6 +70,6 @@ fn main() {
         smile_x = (smile_x + xspeed).clamp(I10F6::ZERO, I10F6::from_num(368));
         smile_y = (smile_y + yspeed).clamp(I10F6::ZERO, I10F6::from_num(208));
 
-        FRAME.wait_for_new_frame();
+        worlds.wait_and_flush(&FRAME);
     }
 }
@@ -4,6 +4,7 @@
 mod assets;
 
 use vb_graphics as gfx;
+use vb_rt::input::{Button, InputState};
 use vb_sound as snd;
 
 vb_rt::rom_header!("Spooky Music", "SG", "EEEK");
@@ -29,34 +30,29 @@ fn main() {
 
     FRAME.enable_interrupts();
 
-    let mut was_a_pressed = false;
-    let mut was_sta_pressed = false;
+    let mut input = InputState::new();
     loop {
-        let pressed = vb_rt::sys::hardware::read_controller();
-        let a_pressed = pressed.a();
-        if a_pressed && !was_a_pressed {
+        input.update();
+        if input.pressed(Button::A) {
             if snd::CHANNELS[4].playing_overlay() {
                 snd::CHANNELS[2].play_overlay(&assets::HURT_4);
             } else {
                 snd::CHANNELS[4].play_overlay(&assets::HURT_4);
             }
         }
-        was_a_pressed = a_pressed;
-        let sta_pressed = pressed.sta();
         for ch in [
             &snd::CHANNELS[0],
             &snd::CHANNELS[1],
             &snd::CHANNELS[2],
             &snd::CHANNELS[5],
         ] {
-            if sta_pressed && !was_sta_pressed {
+            if input.pressed(Button::Start) {
                 ch.pause();
             }
-            if !sta_pressed && was_sta_pressed {
+            if input.released(Button::Start) {
                 ch.resume();
             }
         }
-        was_sta_pressed = sta_pressed;
         FRAME.wait_for_new_frame();
     }
 }
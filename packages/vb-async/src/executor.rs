@@ -0,0 +1,305 @@
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+    task::{Context, RawWaker, RawWakerVTable, Waker},
+};
+
+/// The most tasks an [`Executor`] can run.
+pub const MAX_TASKS: usize = 32;
+
+/// Set when a task has been woken, and cleared just before it's polled.
+/// These live in a static rather than the executor, so wakers can never dangle.
+static WOKEN: [AtomicBool; MAX_TASKS] = [const { AtomicBool::new(false) }; MAX_TASKS];
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone_waker, wake, wake, drop_waker);
+
+unsafe fn clone_waker(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &VTABLE)
+}
+
+unsafe fn wake(data: *const ()) {
+    WOKEN[data.addr()].store(true, Relaxed);
+}
+
+unsafe fn drop_waker(_data: *const ()) {}
+
+fn task_waker(index: usize) -> Waker {
+    let raw = RawWaker::new(core::ptr::without_provenance(index), &VTABLE);
+    // SAFETY: the vtable functions only use the data as an index into WOKEN
+    unsafe { Waker::from_raw(raw) }
+}
+
+/// Returns the index of the task which owns this waker, if it came from an [`Executor`].
+fn task_index(waker: &Waker) -> Option<usize> {
+    core::ptr::eq(waker.vtable(), &VTABLE).then(|| waker.data().addr())
+}
+
+type Task<'a> = Pin<&'a mut (dyn Future<Output = ()> + 'a)>;
+
+/// Runs up to [`MAX_TASKS`] tasks, polling each one only after it has been woken.
+///
+/// Only one executor should run at a time, because they all share the same wake flags.
+pub struct Executor<'a, const N: usize> {
+    tasks: [Option<Task<'a>>; N],
+}
+
+impl<'a, const N: usize> Executor<'a, N> {
+    pub fn new(tasks: [Task<'a>; N]) -> Self {
+        const { assert!(N <= MAX_TASKS, "too many tasks") };
+        // Every task gets polled once to start it off
+        for woken in &WOKEN[..N] {
+            woken.store(true, Relaxed);
+        }
+        Self {
+            tasks: tasks.map(Some),
+        }
+    }
+
+    /// Poll every unfinished task which has been woken since it was last polled.
+    /// Returns true if any tasks were polled.
+    pub fn poll(&mut self) -> bool {
+        let mut polled = false;
+        for (index, slot) in self.tasks.iter_mut().enumerate() {
+            let Some(task) = slot else {
+                continue;
+            };
+            if !WOKEN[index].load(Relaxed) {
+                continue;
+            }
+            // Clear the flag first, so that a wake during the poll isn't lost
+            WOKEN[index].store(false, Relaxed);
+            polled = true;
+
+            let waker = task_waker(index);
+            let mut cx = Context::from_waker(&waker);
+            if task.as_mut().poll(&mut cx).is_ready() {
+                *slot = None;
+            }
+        }
+        polled
+    }
+
+    /// Returns true once every task has finished.
+    pub fn is_finished(&self) -> bool {
+        self.tasks.iter().all(Option::is_none)
+    }
+
+    /// Run the tasks forever, halting the CPU until the next interrupt whenever none of them are awake.
+    /// A task woken by an interrupt which fires just before halting waits until the next interrupt,
+    /// so keep the VIP interrupt enabled to bound that to a frame.
+    #[cfg(target_arch = "v810")]
+    pub fn run(mut self) -> ! {
+        loop {
+            if !self.poll() {
+                vb_rt::sys::halt();
+            }
+        }
+    }
+}
+
+/// A set of tasks waiting for something to happen, usually in an interrupt handler.
+pub struct WaitList {
+    waiting: [AtomicBool; MAX_TASKS],
+}
+
+impl WaitList {
+    pub const fn new() -> Self {
+        Self {
+            waiting: [const { AtomicBool::new(false) }; MAX_TASKS],
+        }
+    }
+
+    /// Wake this task the next time [`WaitList::wake_all`] is called.
+    /// Wakers which didn't come from an [`Executor`] are woken right away instead,
+    /// so their futures will be polled again until they're ready.
+    pub fn register(&self, waker: &Waker) {
+        match task_index(waker) {
+            Some(index) => self.waiting[index].store(true, Relaxed),
+            None => waker.wake_by_ref(),
+        }
+    }
+
+    /// Wake every task which has registered since the last call.
+    pub fn wake_all(&self) {
+        for (waiting, woken) in self.waiting.iter().zip(&WOKEN) {
+            if waiting.load(Relaxed) {
+                waiting.store(false, Relaxed);
+                woken.store(true, Relaxed);
+            }
+        }
+    }
+}
+
+impl Default for WaitList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    extern crate std;
+
+    use core::cell::{Cell, RefCell};
+    use core::pin::pin;
+    use core::task::Poll;
+    use std::sync::{Mutex, MutexGuard};
+
+    use super::*;
+
+    /// Executors share their wake flags, so tests which run one take turns.
+    pub(crate) fn lock() -> MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+        LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Counts its polls, keeps its waker, and finishes when told to.
+    #[derive(Default)]
+    struct Probe {
+        polls: Cell<u32>,
+        waker: RefCell<Option<Waker>>,
+        done: Cell<bool>,
+    }
+
+    impl Probe {
+        async fn run(&self) {
+            core::future::poll_fn(|cx| {
+                self.polls.set(self.polls.get() + 1);
+                *self.waker.borrow_mut() = Some(cx.waker().clone());
+                if self.done.get() {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await
+        }
+
+        fn wake(&self) {
+            self.waker.borrow().as_ref().unwrap().wake_by_ref();
+        }
+    }
+
+    #[test]
+    fn every_task_is_polled_once_to_start() {
+        let _lock = lock();
+        let (a, b) = (Probe::default(), Probe::default());
+        let first = pin!(a.run());
+        let second = pin!(b.run());
+        let mut executor = Executor::new([first, second]);
+        assert!(executor.poll());
+        assert_eq!((a.polls.get(), b.polls.get()), (1, 1));
+        assert!(!executor.is_finished());
+    }
+
+    #[test]
+    fn pending_tasks_wait_until_theyre_woken() {
+        let _lock = lock();
+        let (a, b) = (Probe::default(), Probe::default());
+        let first = pin!(a.run());
+        let second = pin!(b.run());
+        let mut executor = Executor::new([first, second]);
+        executor.poll();
+        assert!(!executor.poll());
+
+        b.wake();
+        assert!(executor.poll());
+        assert_eq!((a.polls.get(), b.polls.get()), (1, 2));
+        assert!(!executor.poll());
+    }
+
+    #[test]
+    fn waking_twice_polls_once() {
+        let _lock = lock();
+        let a = Probe::default();
+        let task = pin!(a.run());
+        let mut executor = Executor::new([task]);
+        executor.poll();
+        a.wake();
+        a.wake();
+        executor.poll();
+        executor.poll();
+        assert_eq!(a.polls.get(), 2);
+    }
+
+    #[test]
+    fn a_task_which_wakes_itself_is_polled_again() {
+        let _lock = lock();
+        let polls = Cell::new(0);
+        let task = core::future::poll_fn(|cx| {
+            polls.set(polls.get() + 1);
+            if polls.get() == 3 {
+                return Poll::Ready(());
+            }
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        });
+        let task = pin!(task);
+        let mut executor = Executor::new([task]);
+        while executor.poll() {}
+        assert_eq!(polls.get(), 3);
+        assert!(executor.is_finished());
+    }
+
+    #[test]
+    fn finished_tasks_are_never_polled_again() {
+        let _lock = lock();
+        let (a, b) = (Probe::default(), Probe::default());
+        let first = pin!(a.run());
+        let second = pin!(b.run());
+        let mut executor = Executor::new([first, second]);
+        executor.poll();
+
+        a.done.set(true);
+        a.wake();
+        executor.poll();
+        assert_eq!(a.polls.get(), 2);
+        assert!(!executor.is_finished());
+
+        // A stale waker for a finished task does nothing
+        a.wake();
+        assert!(!executor.poll());
+        assert_eq!(a.polls.get(), 2);
+
+        b.done.set(true);
+        b.wake();
+        executor.poll();
+        assert!(executor.is_finished());
+    }
+
+    #[test]
+    fn wait_lists_wake_only_the_tasks_which_registered() {
+        let _lock = lock();
+        let list = WaitList::new();
+        let (a, b) = (Probe::default(), Probe::default());
+        let first = pin!(a.run());
+        let second = pin!(b.run());
+        let mut executor = Executor::new([first, second]);
+        executor.poll();
+
+        list.register(b.waker.borrow().as_ref().unwrap());
+        assert!(!executor.poll());
+        list.wake_all();
+        executor.poll();
+        assert_eq!((a.polls.get(), b.polls.get()), (1, 2));
+
+        // Registration only lasts until the next wake_all
+        list.wake_all();
+        assert!(!executor.poll());
+    }
+
+    #[test]
+    fn foreign_wakers_are_woken_right_away() {
+        static WOKEN: AtomicBool = AtomicBool::new(false);
+        static FOREIGN: RawWakerVTable = RawWakerVTable::new(
+            |data| RawWaker::new(data, &FOREIGN),
+            |_| WOKEN.store(true, Relaxed),
+            |_| WOKEN.store(true, Relaxed),
+            |_| {},
+        );
+        let waker = unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &FOREIGN)) };
+        WaitList::new().register(&waker);
+        assert!(WOKEN.load(Relaxed));
+    }
+}
@@ -0,0 +1,67 @@
+//! Wait for the player to press buttons.
+
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU32, Ordering::Relaxed},
+    task::{Context, Poll},
+};
+
+use vb_rt::{input::Button, sys::hardware};
+
+use crate::time::{frame_count, wake_on_next_frame};
+
+/// The frame when the game pad was last read. Starts out invalid.
+static READ_FRAME: AtomicU32 = AtomicU32::new(u32::MAX);
+static HELD: AtomicU32 = AtomicU32::new(0);
+
+/// Read the game pad at most once per frame, no matter how many tasks are waiting on it.
+fn held(button: Button) -> bool {
+    let frame = frame_count();
+    if READ_FRAME.load(Relaxed) != frame {
+        HELD.store(hardware::read_controller().into_bits() as u32, Relaxed);
+        READ_FRAME.store(frame, Relaxed);
+    }
+    HELD.load(Relaxed) & (1 << button as u8) != 0
+}
+
+/// Wait for the button to go down. If it's already down, it has to be released first.
+pub fn button_pressed(button: Button) -> ButtonChange {
+    ButtonChange {
+        button,
+        down: true,
+        was_down: None,
+    }
+}
+
+/// Wait for the button to go up. If it's already up, it has to be pressed first.
+pub fn button_released(button: Button) -> ButtonChange {
+    ButtonChange {
+        button,
+        down: false,
+        was_down: None,
+    }
+}
+
+/// A future which completes when a button changes state.
+pub struct ButtonChange {
+    button: Button,
+    down: bool,
+    was_down: Option<bool>,
+}
+
+impl Future for ButtonChange {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Check again on the next frame, whatever happens
+        wake_on_next_frame(cx.waker());
+        let down = held(self.button);
+        let was_down = self.was_down.replace(down);
+        if down == self.down && was_down == Some(!self.down) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! A tiny single-threaded async executor, for writing cutscenes, dialogue and enemy scripts
+//! as straight-line code.
+//!
+//! ```ignore
+//! vb_rt::vip_interrupt_handler!({
+//!     FRAME.acknowledge_interrupts();
+//!     vb_async::on_frame();
+//! });
+//!
+//! async fn cutscene() {
+//!     show_text("Where am I?");
+//!     vb_async::input::button_pressed(Button::A).await;
+//!     fade_out();
+//!     vb_async::sleep_frames(30).await;
+//! }
+//!
+//! fn main() {
+//!     let cutscene = pin!(cutscene());
+//!     let enemies = pin!(enemies());
+//!     vb_async::Executor::new([cutscene, enemies]).run();
+//! }
+//! ```
+//!
+//! Tasks live inside the [`Executor`], which lives on the stack of a function that never returns,
+//! so nothing needs to be allocated.
+#![no_std]
+
+mod executor;
+#[cfg(target_arch = "v810")]
+pub mod input;
+mod time;
+#[cfg(target_arch = "v810")]
+mod timer;
+
+pub use executor::{Executor, MAX_TASKS, WaitList};
+pub use time::{Counter, Sleep, frame_count, next_frame, on_frame, sleep_frames, sleep_ticks};
+#[cfg(target_arch = "v810")]
+pub use timer::{delay_micros, delay_millis, on_timer, start_timer};
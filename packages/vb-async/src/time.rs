@@ -0,0 +1,218 @@
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU32, Ordering::Relaxed},
+    task::{Context, Poll},
+};
+
+use crate::executor::WaitList;
+
+/// Counts events (like frames or timer ticks), and wakes tasks waiting for it to reach some value.
+pub struct Counter {
+    count: AtomicU32,
+    waiters: WaitList,
+}
+
+impl Counter {
+    pub const fn new() -> Self {
+        Self {
+            count: AtomicU32::new(0),
+            waiters: WaitList::new(),
+        }
+    }
+
+    pub fn now(&self) -> u32 {
+        self.count.load(Relaxed)
+    }
+
+    /// Count another event, and wake any tasks waiting on this counter.
+    /// Call this from only one place, usually an interrupt handler.
+    pub fn advance(&self) {
+        self.count.store(self.now().wrapping_add(1), Relaxed);
+        self.waiters.wake_all();
+    }
+
+    /// Wait until the counter has advanced `count` more times.
+    /// The wait starts when the future is first polled.
+    pub fn sleep(&self, count: u32) -> Sleep<'_> {
+        Sleep {
+            counter: self,
+            count,
+            until: None,
+        }
+    }
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A future which completes once a [`Counter`] reaches some value.
+pub struct Sleep<'a> {
+    counter: &'a Counter,
+    count: u32,
+    until: Option<u32>,
+}
+
+impl Future for Sleep<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Register before checking, so an advance between the two isn't missed
+        self.counter.waiters.register(cx.waker());
+        let now = self.counter.now();
+        let count = self.count;
+        let until = *self.until.get_or_insert(now.wrapping_add(count));
+        if now.wrapping_sub(until) as i32 >= 0 {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+static FRAMES: Counter = Counter::new();
+pub(crate) static TICKS: Counter = Counter::new();
+
+/// Call this once per frame from `vip_interrupt_handler!`.
+pub fn on_frame() {
+    FRAMES.advance();
+}
+
+/// The number of times [`on_frame`] has been called.
+pub fn frame_count() -> u32 {
+    FRAMES.now()
+}
+
+/// Wake this task on the next frame.
+#[cfg(target_arch = "v810")]
+pub(crate) fn wake_on_next_frame(waker: &core::task::Waker) {
+    FRAMES.waiters.register(waker);
+}
+
+/// Wait for the next frame.
+pub fn next_frame() -> Sleep<'static> {
+    FRAMES.sleep(1)
+}
+
+/// Wait for `frames` frames to pass.
+pub fn sleep_frames(frames: u32) -> Sleep<'static> {
+    FRAMES.sleep(frames)
+}
+
+/// Wait for the timer to fire `ticks` times. See `start_timer`.
+pub fn sleep_ticks(ticks: u32) -> Sleep<'static> {
+    TICKS.sleep(ticks)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::pin::pin;
+
+    use super::*;
+    use crate::executor::{Executor, tests::lock};
+
+    #[test]
+    fn sleeping_tasks_wake_when_the_counter_gets_there() {
+        let _lock = lock();
+        let counter = Counter::new();
+        let woke_at = Cell::new(None);
+        let task = async {
+            counter.sleep(3).await;
+            woke_at.set(Some(counter.now()));
+        };
+        let task = pin!(task);
+        let mut executor = Executor::new([task]);
+        executor.poll();
+        for _ in 0..2 {
+            counter.advance();
+            assert!(executor.poll());
+            assert_eq!(woke_at.get(), None);
+        }
+        counter.advance();
+        executor.poll();
+        assert_eq!(woke_at.get(), Some(3));
+        assert!(executor.is_finished());
+    }
+
+    #[test]
+    fn the_wait_starts_at_the_first_poll() {
+        let _lock = lock();
+        let counter = Counter::new();
+        let sleep = counter.sleep(2);
+        counter.advance();
+        counter.advance();
+        let task = pin!(sleep);
+        let mut executor = Executor::new([task]);
+        executor.poll();
+        counter.advance();
+        executor.poll();
+        assert!(!executor.is_finished());
+        counter.advance();
+        executor.poll();
+        assert!(executor.is_finished());
+    }
+
+    #[test]
+    fn sleeping_for_nothing_finishes_right_away() {
+        let _lock = lock();
+        let counter = Counter::new();
+        let task = pin!(counter.sleep(0));
+        let mut executor = Executor::new([task]);
+        executor.poll();
+        assert!(executor.is_finished());
+    }
+
+    #[test]
+    fn sleeps_which_span_the_counter_wrapping_still_finish() {
+        let _lock = lock();
+        let counter = Counter {
+            count: AtomicU32::new(u32::MAX - 1),
+            waiters: WaitList::new(),
+        };
+        let task = pin!(counter.sleep(4));
+        let mut executor = Executor::new([task]);
+        executor.poll();
+        for _ in 0..3 {
+            counter.advance();
+            executor.poll();
+        }
+        assert!(!executor.is_finished());
+        counter.advance();
+        executor.poll();
+        assert!(executor.is_finished());
+    }
+
+    #[test]
+    fn timer_sleeps_expire_after_enough_ticks() {
+        let _lock = lock();
+        let (short, long) = (Cell::new(false), Cell::new(false));
+        let short_task = async {
+            sleep_ticks(1).await;
+            short.set(true);
+        };
+        let long_task = async {
+            sleep_ticks(3).await;
+            long.set(true);
+        };
+        let first = pin!(short_task);
+        let second = pin!(long_task);
+        let mut executor = Executor::new([first, second]);
+        executor.poll();
+
+        // This is all on_timer does, besides acknowledging the interrupt
+        TICKS.advance();
+        executor.poll();
+        assert_eq!((short.get(), long.get()), (true, false));
+        TICKS.advance();
+        executor.poll();
+        assert!(!long.get());
+        TICKS.advance();
+        executor.poll();
+        assert!(long.get());
+        assert!(executor.is_finished());
+    }
+}
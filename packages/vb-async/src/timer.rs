@@ -0,0 +1,34 @@
+use core::sync::atomic::{AtomicU32, Ordering::Relaxed};
+
+use crate::time::{Sleep, TICKS};
+
+static TICK_MICROS: AtomicU32 = AtomicU32::new(0);
+
+/// Start the hardware timer firing about every `tick_micros` microseconds, to drive
+/// [`delay_micros`], [`delay_millis`] and [`sleep_ticks`](crate::sleep_ticks).
+/// Call [`on_timer`] from `timer_interrupt_handler!`, and make sure interrupts are enabled.
+pub fn start_timer(tick_micros: u32) {
+    // The timer can't fire at every interval, so delays are measured in the one it actually uses
+    let tick_micros = vb_rt::timer::configure_interval(tick_micros);
+    TICK_MICROS.store(tick_micros, Relaxed);
+    vb_rt::timer::enable_interrupts();
+    vb_rt::timer::start();
+}
+
+/// Call this from `timer_interrupt_handler!`. Acknowledges the interrupt for you.
+pub fn on_timer() {
+    vb_rt::timer::acknowledge_interrupts();
+    TICKS.advance();
+}
+
+/// Wait for at least `micros` microseconds, rounded up to a whole number of timer ticks.
+pub fn delay_micros(micros: u32) -> Sleep<'static> {
+    let tick_micros = TICK_MICROS.load(Relaxed);
+    assert!(tick_micros != 0, "call start_timer before delaying");
+    TICKS.sleep(micros.div_ceil(tick_micros))
+}
+
+/// Wait for at least `millis` milliseconds, rounded up to a whole number of timer ticks.
+pub fn delay_millis(millis: u32) -> Sleep<'static> {
+    delay_micros(millis.saturating_mul(1000))
+}
@@ -1,4 +1,5 @@
 mod font;
+mod obj;
 mod packer;
 mod png;
 
@@ -10,15 +11,16 @@ use std::{
 use crate::{
     assets::{
         font::FontAtlas,
+        obj::load_obj_contents,
         packer::{InputRegion, Packer},
         png::{PngContents, PngView},
     },
     config::{
-        ImageEffects, RawAnimation, RawAssets, RawBgSprite, RawBgSpriteMap, RawFont, RawImage,
-        RawImageData, RawImageRegion, RawMask,
+        ImageEffects, RawAnimation, RawAssets, RawBgSprite, RawBgSpriteMap, RawColumnTable,
+        RawFont, RawImage, RawImageData, RawImageRegion, RawMask, RawMesh, RawMetasprite,
     },
 };
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 use bitfield_struct::bitfield;
 use png::PngAtlas;
 
@@ -79,6 +81,9 @@ struct AssetProcessor {
     maskdata: BTreeMap<String, MaskData>,
     texturedata: BTreeMap<String, TextureData>,
     fontdata: BTreeMap<String, FontData>,
+    columntabledata: BTreeMap<String, ColumnTableData>,
+    meshdata: BTreeMap<String, MeshData>,
+    metaspritedata: BTreeMap<String, MetaspriteData>,
 }
 
 impl AssetProcessor {
@@ -94,6 +99,9 @@ impl AssetProcessor {
             maskdata: BTreeMap::new(),
             texturedata: BTreeMap::new(),
             fontdata: BTreeMap::new(),
+            columntabledata: BTreeMap::new(),
+            meshdata: BTreeMap::new(),
+            metaspritedata: BTreeMap::new(),
         }
     }
 
@@ -135,12 +143,21 @@ impl AssetProcessor {
         for (name, animation) in assets.animations {
             self.process_animation(name, animation)?;
         }
+        for (name, metasprite) in assets.metasprites {
+            self.process_metasprite(name, metasprite)?;
+        }
         for (name, mask) in assets.masks {
             self.process_mask(name, mask)?;
         }
         for (name, font) in assets.fonts {
             self.process_font(name, font)?;
         }
+        for (name, column_table) in assets.column_tables {
+            self.process_column_table(name, column_table)?;
+        }
+        for (name, mesh) in assets.meshes {
+            self.process_mesh(name, mesh)?;
+        }
         while let Some((name, sprite_map)) = assets.bg_sprite_maps.pop_first() {
             let mut current_base = sprite_map.base.clone();
             let mut sprite_map_queue = vec![];
@@ -163,6 +180,9 @@ impl AssetProcessor {
             masks: self.maskdata.into_values().collect(),
             textures: self.texturedata.into_values().collect(),
             fonts: self.fontdata.into_values().collect(),
+            column_tables: self.columntabledata.into_values().collect(),
+            meshes: self.meshdata.into_values().collect(),
+            metasprites: self.metaspritedata.into_values().collect(),
         })
     }
 
@@ -379,30 +399,23 @@ impl AssetProcessor {
             }
         }
 
-        let chardata = self
-            .chardata
-            .entry(chardata)
-            .or_insert_with_key(|name| CharData {
-                name: name.clone(),
-                chars: vec![[0; 8]],
-            });
-
+        let chardata = self.chardata_entry(chardata);
         let mut cells = vec![];
         for shade in shades {
-            let (char, palette) = shades_to_chardata(shade)?;
-            let (index, hflip, vflip) = chardata.add_deduped(char);
-            cells.push(
-                Cell::new()
-                    .with_character(index)
-                    .with_hflip(hflip)
-                    .with_vflip(vflip)
-                    .with_palette(palette)
-                    .into_bits(),
-            );
+            cells.push(chardata.add_cell(shade)?);
         }
         Ok((width, height, cells))
     }
 
+    fn chardata_entry(&mut self, name: String) -> &mut CharData {
+        self.chardata
+            .entry(name)
+            .or_insert_with_key(|name| CharData {
+                name: name.clone(),
+                chars: vec![[0; 8]],
+            })
+    }
+
     fn extract_region_shades(
         &mut self,
         palette: Option<[u8; 3]>,
@@ -583,6 +596,72 @@ impl AssetProcessor {
         Ok(())
     }
 
+    fn process_metasprite(&mut self, name: String, metasprite: RawMetasprite) -> Result<()> {
+        let mut pieces = vec![];
+        for layer in metasprite.layers {
+            if !layer.region.effects.is_empty() {
+                bail!("metasprites cannot use effects yet");
+            }
+            let png = self.pngs.open(layer.region.file.to_path_buf())?;
+            let view = extract_region_view(png, &layer.region, metasprite.palette)?;
+
+            // Trim the empty space around the layer, and start the cells where its pixels do.
+            let (width, height) = view.size();
+            let opaque = |x: usize, y: usize| view.get_shade(x, y) != Shade::Transparent;
+            let Some(left) = (0..width).find(|&x| (0..height).any(|y| opaque(x, y))) else {
+                continue;
+            };
+            let right = (0..width)
+                .rfind(|&x| (0..height).any(|y| opaque(x, y)))
+                .unwrap()
+                + 1;
+            let top = (0..height)
+                .find(|&y| (left..right).any(|x| opaque(x, y)))
+                .unwrap();
+            let bottom = (0..height)
+                .rfind(|&y| (left..right).any(|x| opaque(x, y)))
+                .unwrap()
+                + 1;
+
+            let chardata = self.chardata_entry(metasprite.chardata.clone());
+            for cell_y in (top..bottom).step_by(8) {
+                for cell_x in (left..right).step_by(8) {
+                    let mut shades = [[Shade::Transparent; 8]; 8];
+                    for (y, shade_row) in shades.iter_mut().enumerate() {
+                        for (x, shade) in shade_row.iter_mut().enumerate() {
+                            *shade = view.get_shade(x + cell_x, y + cell_y);
+                        }
+                    }
+                    if shades
+                        .as_flattened()
+                        .iter()
+                        .all(|s| *s == Shade::Transparent)
+                    {
+                        continue;
+                    }
+                    let dx = cell_x as isize + layer.offset.0 - metasprite.origin.0;
+                    let dy = cell_y as isize + layer.offset.1 - metasprite.origin.1;
+                    let (Ok(dx), Ok(dy)) = (i16::try_from(dx), i16::try_from(dy)) else {
+                        bail!("metasprite {name} has a piece too far from its origin");
+                    };
+                    pieces.push(MetaspritePieceData {
+                        dx,
+                        dy,
+                        parallax: layer.parallax,
+                        cell: chardata.add_cell(shades)?,
+                    });
+                }
+            }
+        }
+        if pieces.is_empty() {
+            bail!("metasprite {name} has no visible pixels");
+        }
+
+        self.metaspritedata
+            .insert(name.clone(), MetaspriteData { name, pieces });
+        Ok(())
+    }
+
     fn process_mask(&mut self, name: String, mask: RawMask) -> Result<()> {
         let png = self.pngs.open(mask.region.file.to_path_buf())?;
         let view = extract_region_view(png, &mask.region, None)?;
@@ -616,6 +695,79 @@ impl AssetProcessor {
         Ok(())
     }
 
+    fn process_column_table(&mut self, name: String, column_table: RawColumnTable) -> Result<()> {
+        let points = column_table.brightness;
+        if points.is_empty() {
+            bail!("column table {name} has no brightness points");
+        }
+        for pair in points.windows(2) {
+            if pair[0].0 >= pair[1].0 {
+                bail!("column table {name} has brightness points out of order");
+            }
+        }
+        for &(entry, brightness) in &points {
+            if !(1.0..=16.0).contains(&brightness) {
+                bail!(
+                    "column table {name} has brightness {brightness} at entry {entry}, but it must be between 1 and 16"
+                );
+            }
+        }
+
+        let repeats = (0..256)
+            .map(|entry| {
+                let brightness = interpolate_brightness(&points, entry as f64);
+                // A column with repeat N is lit N + 1 times.
+                (brightness.round() as u8 - 1).min(15)
+            })
+            .collect();
+        self.columntabledata
+            .insert(name.clone(), ColumnTableData { name, repeats });
+        Ok(())
+    }
+
+    fn process_mesh(&mut self, name: String, mesh: RawMesh) -> Result<()> {
+        let contents = load_obj_contents(&mesh.file)?;
+        if contents.edges.is_empty() {
+            bail!("mesh {name} has no edges");
+        }
+
+        // Only keep vertices which are part of an edge.
+        let mut new_indices = vec![None; contents.vertices.len()];
+        let mut vertices = vec![];
+        let mut edges = vec![];
+        for &(a, b) in &contents.edges {
+            let mut edge = [0; 2];
+            for (index, old_index) in edge.iter_mut().zip([a, b]) {
+                if new_indices[old_index].is_none() {
+                    // Edges store vertices as u16 indices
+                    if vertices.len() > u16::MAX as usize {
+                        bail!("mesh {name} has too many vertices");
+                    }
+                    let vertex = convert_vertex(contents.vertices[old_index], mesh.scale)
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "mesh {name} has a vertex which doesn't fit in 16 bits after scaling"
+                            )
+                        })?;
+                    new_indices[old_index] = Some(vertices.len() as u16);
+                    vertices.push(vertex);
+                }
+                *index = new_indices[old_index].unwrap();
+            }
+            edges.push(edge);
+        }
+
+        self.meshdata.insert(
+            name.clone(),
+            MeshData {
+                name,
+                vertices,
+                edges,
+            },
+        );
+        Ok(())
+    }
+
     fn process_font(&mut self, name: String, font: RawFont) -> Result<()> {
         let contents = self.fonts.open(font.file.to_path_buf())?;
         let mut chars = vec![];
@@ -795,6 +947,34 @@ fn shades_to_chardata(shades: [[Shade; 8]; 8]) -> Result<([u16; 8], u8)> {
     Ok((char, black_shade as u8))
 }
 
+/// OBJ files point +y up and +z towards the viewer, but the screen points +y down and +z away.
+fn convert_vertex(vertex: [f64; 3], scale: f64) -> Option<[i16; 3]> {
+    let [x, y, z] = vertex.map(|coord| coord * scale);
+    let mut result = [0; 3];
+    for (dst, coord) in result.iter_mut().zip([x, -y, -z]) {
+        let rounded = coord.round();
+        if !(i16::MIN as f64..=i16::MAX as f64).contains(&rounded) {
+            return None;
+        }
+        *dst = rounded as i16;
+    }
+    Some(result)
+}
+
+fn interpolate_brightness(points: &[(u8, f64)], entry: f64) -> f64 {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    if entry <= first.0 as f64 {
+        return first.1;
+    }
+    if entry >= last.0 as f64 {
+        return last.1;
+    }
+    let next = points.iter().position(|p| p.0 as f64 > entry).unwrap();
+    let (start, end) = (points[next - 1], points[next]);
+    let t = (entry - start.0 as f64) / (end.0 - start.0) as f64;
+    start.1 + (end.1 - start.1) * t
+}
+
 pub struct Assets {
     pub chardata: Vec<CharData>,
     pub images: Vec<ImageData>,
@@ -803,6 +983,43 @@ pub struct Assets {
     pub masks: Vec<MaskData>,
     pub textures: Vec<TextureData>,
     pub fonts: Vec<FontData>,
+    pub column_tables: Vec<ColumnTableData>,
+    pub meshes: Vec<MeshData>,
+    pub metasprites: Vec<MetaspriteData>,
+}
+
+pub struct ColumnTableData {
+    pub name: String,
+    pub repeats: Vec<u8>,
+}
+
+pub struct MeshData {
+    pub name: String,
+    pub vertices: Vec<[i16; 3]>,
+    pub edges: Vec<[u16; 2]>,
+}
+
+pub struct MetaspriteData {
+    pub name: String,
+    /// Back to front.
+    pub pieces: Vec<MetaspritePieceData>,
+}
+
+pub struct MetaspritePieceData {
+    pub dx: i16,
+    pub dy: i16,
+    pub parallax: i16,
+    pub cell: u16,
+}
+impl MetaspritePieceData {
+    pub fn as_bytes(&self) -> [u8; 8] {
+        let mut result = [0; 8];
+        result[0..2].copy_from_slice(&self.dx.to_le_bytes());
+        result[2..4].copy_from_slice(&self.dy.to_le_bytes());
+        result[4..6].copy_from_slice(&self.parallax.to_le_bytes());
+        result[6..8].copy_from_slice(&self.cell.to_le_bytes());
+        result
+    }
 }
 
 pub struct CharData {
@@ -823,6 +1040,18 @@ impl CharData {
         self.chars.push(char);
         (index as u16, false, false)
     }
+
+    /// Add the character for these shades, and return a cell which draws it.
+    fn add_cell(&mut self, shades: RawCell) -> Result<u16> {
+        let (char, palette) = shades_to_chardata(shades)?;
+        let (index, hflip, vflip) = self.add_deduped(char);
+        Ok(Cell::new()
+            .with_character(index)
+            .with_hflip(hflip)
+            .with_vflip(vflip)
+            .with_palette(palette)
+            .into_bits())
+    }
 }
 
 pub struct ImageData {
@@ -993,3 +1222,137 @@ fn animation_layout(frame_size: (usize, usize), frames: usize, stereo: bool) ->
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+    use crate::config::RawMetaspriteLayer;
+
+    /// Write a greyscale PNG where `pixels` are opaque white and everything else is transparent.
+    fn write_png(name: &str, size: (u32, u32), pixels: &[(u32, u32)]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "vb-graphics-build-{}-{name}.png",
+            std::process::id()
+        ));
+        let mut data = vec![0u8; (size.0 * size.1 * 2) as usize];
+        for &(x, y) in pixels {
+            let index = ((y * size.0 + x) * 2) as usize;
+            data[index..index + 2].copy_from_slice(&[255, 255]);
+        }
+        let file = std::fs::File::create(&path).unwrap();
+        let mut encoder = ::png::Encoder::new(file, size.0, size.1);
+        encoder.set_color(::png::ColorType::GrayscaleAlpha);
+        encoder.set_depth(::png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&data).unwrap();
+        path
+    }
+
+    fn region(file: &Path) -> RawImageRegion {
+        RawImageRegion {
+            file: file.to_path_buf(),
+            hflip: false,
+            vflip: false,
+            transpose: false,
+            rotate: 0,
+            scale: 1.0,
+            position: None,
+            size: None,
+            effects: ImageEffects::default(),
+        }
+    }
+
+    fn layer(region: RawImageRegion, offset: (isize, isize), parallax: i16) -> RawMetaspriteLayer {
+        RawMetaspriteLayer {
+            region,
+            offset,
+            parallax,
+        }
+    }
+
+    fn metasprite(origin: (isize, isize), layers: Vec<RawMetaspriteLayer>) -> RawMetasprite {
+        RawMetasprite {
+            chardata: "chars".into(),
+            palette: None,
+            origin,
+            layers,
+        }
+    }
+
+    fn pieces(processor: &AssetProcessor) -> Vec<(i16, i16, i16)> {
+        processor.metaspritedata["sprite"]
+            .pieces
+            .iter()
+            .map(|piece| (piece.dx, piece.dy, piece.parallax))
+            .collect()
+    }
+
+    #[test]
+    fn metasprite_layers_are_trimmed_to_their_pixels() {
+        // Opaque pixels span (10, 5) to (19, 14), so the cells start at (10, 5).
+        // Of the 2x2 cells that covers, only the top left and bottom right have any pixels.
+        let file = write_png("trimmed", (24, 24), &[(10, 5), (17, 12), (19, 14)]);
+        let mut processor = AssetProcessor::new();
+        processor
+            .process_metasprite(
+                "sprite".into(),
+                metasprite((0, 0), vec![layer(region(&file), (0, 0), 0)]),
+            )
+            .unwrap();
+        assert_eq!(pieces(&processor), [(10, 5, 0), (18, 13, 0)]);
+        // The blank character, then one for each piece
+        assert_eq!(processor.chardata["chars"].chars.len(), 3);
+    }
+
+    #[test]
+    fn metasprite_pieces_are_placed_relative_to_the_origin() {
+        let file = write_png("placed", (24, 24), &[(10, 5), (17, 12), (19, 14)]);
+        let mut blank = region(&file);
+        blank.size = Some((8, 4));
+        let mut processor = AssetProcessor::new();
+        processor
+            .process_metasprite(
+                "sprite".into(),
+                metasprite(
+                    (16, 16),
+                    vec![
+                        layer(region(&file), (3, -4), 2),
+                        // Layers without any pixels add no pieces
+                        layer(blank, (0, 0), 5),
+                        layer(region(&file), (0, 0), -1),
+                    ],
+                ),
+            )
+            .unwrap();
+        assert_eq!(
+            pieces(&processor),
+            [(-3, -15, 2), (5, -7, 2), (-6, -11, -1), (2, -3, -1)]
+        );
+        // Both layers share the same characters
+        assert_eq!(processor.chardata["chars"].chars.len(), 3);
+    }
+
+    #[test]
+    fn metasprites_need_visible_pixels() {
+        let file = write_png("empty", (8, 8), &[]);
+        let mut processor = AssetProcessor::new();
+        let result = processor.process_metasprite(
+            "sprite".into(),
+            metasprite((0, 0), vec![layer(region(&file), (0, 0), 0)]),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn metasprite_pieces_must_fit_in_16_bits() {
+        let file = write_png("far", (8, 8), &[(0, 0)]);
+        let mut processor = AssetProcessor::new();
+        let result = processor.process_metasprite(
+            "sprite".into(),
+            metasprite((0, 0), vec![layer(region(&file), (40000, 0), 0)]),
+        );
+        assert!(result.is_err());
+    }
+}
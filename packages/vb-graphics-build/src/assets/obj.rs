@@ -0,0 +1,169 @@
+use std::{collections::BTreeSet, fs, path::Path};
+
+use anyhow::{Context, Result, anyhow, bail};
+
+/// The vertices and edges of a Wavefront OBJ file. Faces become the edges around them.
+pub struct ObjContents {
+    pub vertices: Vec<[f64; 3]>,
+    /// Pairs of indices into `vertices`, each stored lowest first and only once.
+    pub edges: BTreeSet<(usize, usize)>,
+}
+
+pub fn load_obj_contents(path: &Path) -> Result<ObjContents> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow!("could not read obj from {}: {}", path.display(), e))?;
+    let mut contents = ObjContents {
+        vertices: vec![],
+        edges: BTreeSet::new(),
+    };
+    for (number, line) in text.lines().enumerate() {
+        parse_line(&mut contents, line)
+            .with_context(|| format!("{}, line {}", path.display(), number + 1))?;
+    }
+    Ok(contents)
+}
+
+fn parse_line(contents: &mut ObjContents, line: &str) -> Result<()> {
+    let line = line.split('#').next().unwrap_or_default();
+    let mut words = line.split_whitespace();
+    match words.next() {
+        Some("v") => {
+            let mut vertex = [0.0; 3];
+            for coord in &mut vertex {
+                let Some(word) = words.next() else {
+                    bail!("vertex has fewer than 3 coordinates");
+                };
+                *coord = word.parse()?;
+            }
+            contents.vertices.push(vertex);
+        }
+        Some(kind @ ("l" | "f")) => {
+            let indices = words
+                .map(|word| vertex_index(word, contents.vertices.len()))
+                .collect::<Result<Vec<_>>>()?;
+            if indices.len() < 2 {
+                bail!("{kind} element needs at least 2 vertices");
+            }
+            for pair in indices.windows(2) {
+                add_edge(contents, pair[0], pair[1]);
+            }
+            if kind == "f" {
+                // Faces are closed loops
+                add_edge(contents, indices[indices.len() - 1], indices[0]);
+            }
+        }
+        // Normals, texture coordinates, groups and materials don't matter for a wireframe
+        _ => {}
+    }
+    Ok(())
+}
+
+/// OBJ indices start at 1, and negative indices count back from the latest vertex.
+/// Faces can also name texture coordinates and normals, like `1/2/3`.
+fn vertex_index(word: &str, vertex_count: usize) -> Result<usize> {
+    let index: isize = word.split('/').next().unwrap_or_default().parse()?;
+    let resolved = if index < 0 {
+        vertex_count as isize + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved as usize >= vertex_count {
+        bail!("vertex index {index} is out of range");
+    }
+    Ok(resolved as usize)
+}
+
+fn add_edge(contents: &mut ObjContents, a: usize, b: usize) {
+    if a != b {
+        contents.edges.insert((a.min(b), a.max(b)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::convert_vertex;
+
+    fn parse(text: &str) -> Result<ObjContents> {
+        let mut contents = ObjContents {
+            vertices: vec![],
+            edges: BTreeSet::new(),
+        };
+        for line in text.lines() {
+            parse_line(&mut contents, line)?;
+        }
+        Ok(contents)
+    }
+
+    fn edges(text: &str) -> Vec<(usize, usize)> {
+        parse(text).unwrap().edges.into_iter().collect()
+    }
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    #[test]
+    fn vertices_are_parsed() {
+        let contents = parse("v 1.5 -2 3 # comment\nvn 0 0 1\nv 4 5 6 1.0\n").unwrap();
+        assert_eq!(contents.vertices, [[1.5, -2.0, 3.0], [4.0, 5.0, 6.0]]);
+    }
+
+    #[test]
+    fn faces_are_closed_loops() {
+        let edges = edges(&format!("{SQUARE}f 1 2 3 4\n"));
+        assert_eq!(edges, [(0, 1), (0, 3), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn lines_are_open_polylines() {
+        let edges = edges(&format!("{SQUARE}l 1 2 3 4\n"));
+        assert_eq!(edges, [(0, 1), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn texture_and_normal_indices_are_ignored() {
+        let edges = edges(&format!("{SQUARE}f 1/5/2 2//3 3/1\n"));
+        assert_eq!(edges, [(0, 1), (0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_latest_vertex() {
+        let edges = edges("v 0 0 0\nv 1 0 0\nv 1 1 0\nl -1 -3\nv 0 1 0\nl -1 -2\n");
+        assert_eq!(edges, [(0, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn shared_edges_are_only_stored_once() {
+        // Two triangles sharing the 1-3 edge, once in each direction
+        let edges = edges(&format!("{SQUARE}f 1 2 3\nf 3 4 1\nl 2 1\n"));
+        assert_eq!(edges, [(0, 1), (0, 2), (0, 3), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn out_of_range_indices_are_errors() {
+        assert!(parse(&format!("{SQUARE}f 1 2 5\n")).is_err());
+        assert!(parse(&format!("{SQUARE}f 0 1 2\n")).is_err());
+        assert!(parse(&format!("{SQUARE}l 1 -5\n")).is_err());
+        // Indices can only refer to vertices which came before them
+        assert!(parse("v 0 0 0\nl 1 2\nv 1 0 0\n").is_err());
+    }
+
+    #[test]
+    fn malformed_elements_are_errors() {
+        assert!(parse("v 1 2\n").is_err());
+        assert!(parse("v 1 x 3\n").is_err());
+        assert!(parse(&format!("{SQUARE}l 1\n")).is_err());
+        assert!(parse(&format!("{SQUARE}f 1 a 3\n")).is_err());
+    }
+
+    #[test]
+    fn vertices_are_flipped_into_screen_space() {
+        // OBJ is y-up and z towards the viewer, the screen is y-down and z into it
+        assert_eq!(convert_vertex([1.0, 2.0, 3.0], 10.0), Some([10, -20, -30]));
+        assert_eq!(convert_vertex([-0.26, 0.24, -0.5], 10.0), Some([-3, -2, 5]));
+        assert_eq!(convert_vertex([3276.8, 0.0, 0.0], 10.0), None);
+        assert_eq!(
+            convert_vertex([0.0, 0.0, -3276.7], 10.0),
+            Some([0, 0, 32767])
+        );
+    }
+}
@@ -116,6 +116,36 @@ pub fn generate(opts: &Options, assets: Assets) -> Result<()> {
         writeln!(file)?;
     }
 
+    for metasprite in assets.metasprites {
+        let metaspritedata_filename = format!("metasprite.{}.bin", metasprite.name);
+        let mut metaspritedata_file = opts.output_file(&metaspritedata_filename)?;
+        for piece in &metasprite.pieces {
+            metaspritedata_file.write_all(&piece.as_bytes())?;
+        }
+        metaspritedata_file.flush()?;
+
+        writeln!(
+            file,
+            "static {}_PIECES: [vb_graphics::MetaspritePiece; {}] = vb_graphics::include_metaspritedata!(\"{}\");",
+            rust_identifier(&metasprite.name),
+            metasprite.pieces.len(),
+            metaspritedata_filename,
+        )?;
+        writeln!(file, "#[allow(dead_code)]")?;
+        writeln!(
+            file,
+            "pub const {}: vb_graphics::Metasprite = vb_graphics::Metasprite {{",
+            rust_identifier(&metasprite.name),
+        )?;
+        writeln!(
+            file,
+            "    pieces: &{}_PIECES,",
+            rust_identifier(&metasprite.name)
+        )?;
+        writeln!(file, "}};")?;
+        writeln!(file)?;
+    }
+
     for bg_sprite_map in assets.bg_sprite_maps {
         writeln!(file, "pub mod {} {{", bg_sprite_map.name.replace("-", "_"))?;
         for sprite in &bg_sprite_map.sprites {
@@ -271,6 +301,62 @@ pub fn generate(opts: &Options, assets: Assets) -> Result<()> {
         writeln!(file)?;
     }
 
+    for column_table in assets.column_tables {
+        writeln!(file, "#[allow(dead_code)]")?;
+        writeln!(
+            file,
+            "pub const {}: vb_rt::sys::vip::ColumnTable = vb_rt::sys::vip::ColumnTable::DEFAULT.with_repeats(&{:?});",
+            rust_identifier(&column_table.name),
+            column_table.repeats,
+        )?;
+        writeln!(file)?;
+    }
+
+    for mesh in assets.meshes {
+        let vertices_filename = format!("mesh.{}.vertices.bin", mesh.name);
+        let mut vertices_file = opts.output_file(&vertices_filename)?;
+        for coord in mesh.vertices.as_flattened() {
+            vertices_file.write_all(&coord.to_le_bytes())?;
+        }
+        vertices_file.flush()?;
+
+        let edges_filename = format!("mesh.{}.edges.bin", mesh.name);
+        let mut edges_file = opts.output_file(&edges_filename)?;
+        for index in mesh.edges.as_flattened() {
+            edges_file.write_all(&index.to_le_bytes())?;
+        }
+        edges_file.flush()?;
+
+        writeln!(
+            file,
+            "static {}_VERTICES: [[i16; 3]; {}] = vb_graphics::include_meshdata!(\"{}\");",
+            rust_identifier(&mesh.name),
+            mesh.vertices.len(),
+            vertices_filename,
+        )?;
+        writeln!(
+            file,
+            "static {}_EDGES: [[u16; 2]; {}] = vb_graphics::include_meshdata!(\"{}\");",
+            rust_identifier(&mesh.name),
+            mesh.edges.len(),
+            edges_filename,
+        )?;
+        writeln!(file, "#[allow(dead_code)]")?;
+        writeln!(
+            file,
+            "pub const {}: vb_graphics::wire3d::Mesh = vb_graphics::wire3d::Mesh {{",
+            rust_identifier(&mesh.name),
+        )?;
+        writeln!(
+            file,
+            "    vertices: &{}_VERTICES,",
+            rust_identifier(&mesh.name)
+        )?;
+        writeln!(file, "    edges: &{}_EDGES,", rust_identifier(&mesh.name))?;
+        writeln!(file, "}};")?;
+        writeln!(file)?;
+    }
+
     file.flush()?;
     Ok(())
 }
@@ -89,6 +89,10 @@ struct RawAssetFile {
     pub fonts: BTreeMap<String, RawFont>,
     #[serde(rename = "bgspritemap", default)]
     pub bg_sprite_maps: BTreeMap<String, RawBgSpriteMap>,
+    #[serde(rename = "column_table", default)]
+    pub column_tables: BTreeMap<String, RawColumnTable>,
+    #[serde(rename = "mesh", default)]
+    pub meshes: BTreeMap<String, RawMesh>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -106,6 +110,8 @@ struct RawSpritesheet {
     sprites: BTreeMap<String, RawSprite>,
     #[serde(rename = "animation", default)]
     animations: BTreeMap<String, Vec<RawSprite>>,
+    #[serde(rename = "metasprite", default)]
+    metasprites: BTreeMap<String, RawSpritesheetMetasprite>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -120,6 +126,36 @@ enum RawSprite {
     },
 }
 
+/// A sprite drawn with objects. Either one sprite from the sheet, or several layered back to front.
+/// `origin` is the pixel within the sprite which is drawn at the metasprite's position.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum RawSpritesheetMetasprite {
+    Layered {
+        #[serde(default)]
+        origin: (isize, isize),
+        layers: Vec<RawSpritesheetMetaspriteLayer>,
+    },
+    Single {
+        #[serde(default)]
+        origin: (isize, isize),
+        #[serde(flatten)]
+        layer: RawSpritesheetMetaspriteLayer,
+    },
+}
+
+#[derive(Deserialize, Debug)]
+struct RawSpritesheetMetaspriteLayer {
+    /// How many pixels to move this layer from the top left corner of the sprite.
+    #[serde(default)]
+    offset: (isize, isize),
+    /// Added to the metasprite's parallax for every piece in this layer.
+    #[serde(default)]
+    parallax: i16,
+    #[serde(flatten)]
+    sprite: RawSpriteData,
+}
+
 #[derive(Deserialize, Debug)]
 struct RawSpriteData {
     #[serde(default)]
@@ -186,6 +222,9 @@ pub struct RawAssets {
     pub bg_sprite_maps: BTreeMap<String, RawBgSpriteMap>,
     pub masks: BTreeMap<String, RawMask>,
     pub fonts: BTreeMap<String, RawFont>,
+    pub column_tables: BTreeMap<String, RawColumnTable>,
+    pub meshes: BTreeMap<String, RawMesh>,
+    pub metasprites: BTreeMap<String, RawMetasprite>,
 }
 
 #[derive(Debug)]
@@ -208,6 +247,37 @@ impl RawAnimation {
     }
 }
 
+#[derive(Debug)]
+pub struct RawMetasprite {
+    pub chardata: String,
+    pub palette: Option<[u8; 3]>,
+    pub origin: (isize, isize),
+    pub layers: Vec<RawMetaspriteLayer>,
+}
+impl RawMetasprite {
+    fn fix(self, opts: &mut Options, dir: &Path, palette: Option<[u8; 3]>) -> Self {
+        Self {
+            palette: self.palette.or(palette),
+            layers: self
+                .layers
+                .into_iter()
+                .map(|layer| RawMetaspriteLayer {
+                    region: layer.region.fix_files(opts, dir),
+                    ..layer
+                })
+                .collect(),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RawMetaspriteLayer {
+    pub region: RawImageRegion,
+    pub offset: (isize, isize),
+    pub parallax: i16,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct RawImage {
     pub chardata: String,
@@ -323,6 +393,31 @@ impl RawFont {
     }
 }
 
+/// A wireframe model, loaded from the vertices, lines and faces of a Wavefront OBJ file.
+/// Coordinates are multiplied by `scale` and rounded to whole units.
+#[derive(Deserialize, Debug)]
+pub struct RawMesh {
+    pub file: PathBuf,
+    #[serde(default = "no_zoom")]
+    pub scale: f64,
+}
+impl RawMesh {
+    fn fix_files(self, opts: &mut Options, dir: &Path) -> Self {
+        Self {
+            file: opts.input_path(&dir.join(self.file)),
+            ..self
+        }
+    }
+}
+
+/// A brightness curve across the columns of the display.
+/// Each point is a column table entry and how many times that column is lit, from 1 to 16.
+/// Entries between points are interpolated, and entries outside them match the nearest point.
+#[derive(Deserialize, Debug)]
+pub struct RawColumnTable {
+    pub brightness: Vec<(u8, f64)>,
+}
+
 pub fn parse(opts: &mut Options) -> Result<RawAssets> {
     let mut assets = RawAssets {
         animations: BTreeMap::new(),
@@ -330,6 +425,9 @@ pub fn parse(opts: &mut Options) -> Result<RawAssets> {
         bg_sprite_maps: BTreeMap::new(),
         masks: BTreeMap::new(),
         fonts: BTreeMap::new(),
+        column_tables: BTreeMap::new(),
+        meshes: BTreeMap::new(),
+        metasprites: BTreeMap::new(),
     };
     let mut files = vec![(opts.config_file_path(), None)];
     let mut spritesheet_sprites = BTreeMap::new();
@@ -362,6 +460,11 @@ pub fn parse(opts: &mut Options) -> Result<RawAssets> {
                     .animations
                     .insert(name, animation.fix(opts, dir, palette));
             }
+            for (name, metasprite) in parsed.metasprites {
+                assets
+                    .metasprites
+                    .insert(name, metasprite.fix(opts, dir, palette));
+            }
             spritesheet_sprites.insert(path, sprites);
         }
 
@@ -385,6 +488,10 @@ pub fn parse(opts: &mut Options) -> Result<RawAssets> {
                 .bg_sprite_maps
                 .insert(name, bg_sprite_map.fix_files(opts, dir));
         }
+        assets.column_tables.extend(file.column_tables);
+        for (name, mesh) in file.meshes {
+            assets.meshes.insert(name, mesh.fix_files(opts, dir));
+        }
     }
     for (name, bg_sprite_map) in &mut assets.bg_sprite_maps {
         for spritesheet in &bg_sprite_map.spritesheets {
@@ -411,6 +518,7 @@ pub fn parse(opts: &mut Options) -> Result<RawAssets> {
 struct ParsedSpritesheet {
     images: Vec<(String, RawImage)>,
     animations: Vec<(String, RawAnimation)>,
+    metasprites: Vec<(String, RawMetasprite)>,
 }
 
 fn parse_spritesheet(path: &Path) -> Result<ParsedSpritesheet> {
@@ -474,8 +582,35 @@ fn parse_spritesheet(path: &Path) -> Result<ParsedSpritesheet> {
             },
         ));
     }
+    let mut metasprites = vec![];
+    for (name, metasprite) in file.metasprites {
+        let (origin, layers) = match metasprite {
+            RawSpritesheetMetasprite::Layered { origin, layers } => (origin, layers),
+            RawSpritesheetMetasprite::Single { origin, layer } => (origin, vec![layer]),
+        };
+        if layers.is_empty() {
+            bail!("metasprite {name} has no layers");
+        }
+        metasprites.push((
+            name,
+            RawMetasprite {
+                chardata: file.chardata.clone(),
+                palette,
+                origin,
+                layers: layers
+                    .into_iter()
+                    .map(|layer| RawMetaspriteLayer {
+                        region: data_to_region(layer.sprite),
+                        offset: layer.offset,
+                        parallax: layer.parallax,
+                    })
+                    .collect(),
+            },
+        ));
+    }
     Ok(ParsedSpritesheet {
         images: sprites,
         animations,
+        metasprites,
     })
 }
@@ -1,5 +1,7 @@
 use vb_rt::sys::vip;
 
+use crate::objects::ObjectPool;
+
 #[derive(Clone, Copy, Debug)]
 pub struct Image {
     pub width_cells: u8,
@@ -100,41 +102,161 @@ impl ImageRenderer<'_> {
     }
 
     pub fn into_objects(self, mut used: u16, dst: (i16, i16), stereo: vip::ObjectStereo) -> u16 {
+        for object in self.objects(dst, stereo) {
+            used -= 1;
+            vip::OBJS.index(used as usize).write(object);
+        }
+        used
+    }
+
+    /// Draw the image with objects from one group of the pool.
+    /// Returns false if the group ran out of objects partway through.
+    pub fn into_object_pool(
+        self,
+        pool: &mut ObjectPool,
+        group: usize,
+        dst: (i16, i16),
+        stereo: vip::ObjectStereo,
+    ) -> bool {
+        self.objects(dst, stereo)
+            .all(|object| pool.push(group, object).is_some())
+    }
+
+    /// One object for each visible, non-empty cell of the image.
+    fn objects(
+        &self,
+        dst: (i16, i16),
+        stereo: vip::ObjectStereo,
+    ) -> impl Iterator<Item = vip::Object> {
         let min_x = -8 - stereo.jp().abs();
         let max_x = 384 + stereo.jp().abs();
-        for y in 0..self.image.height_cells {
-            let dy = dst.1 + (y as i16) * 8;
-            if dy <= -8 {
+        let image = self.image;
+        let offset = self.offset;
+        (0..image.height_cells)
+            .map(move |y| (y, dst.1 + (y as i16) * 8))
+            .skip_while(|&(_, dy)| dy <= -8)
+            .take_while(|&(_, dy)| dy < 224)
+            .flat_map(move |(y, dy)| {
+                (0..image.width_cells)
+                    .map(move |x| (x, dst.0 + (x as i16) * 8))
+                    .skip_while(move |&(_, dx)| dx <= min_x)
+                    .take_while(move |&(_, dx)| dx <= max_x)
+                    .filter_map(move |(x, dx)| {
+                        let cell = image.data[y as usize * image.width_cells as usize + x as usize];
+                        let character = cell.character();
+                        if character == 0 {
+                            return None;
+                        }
+                        Some(vip::Object {
+                            jx: dx,
+                            stereo,
+                            jy: dy,
+                            cell: cell.with_character(character + offset),
+                        })
+                    })
+            })
+    }
+}
+
+/// A sprite drawn with objects, made of 8x8 pieces placed around its origin.
+/// Unlike an [`Image`], the pieces don't have to line up in a grid, and empty space takes no objects.
+#[derive(Clone, Copy, Debug)]
+pub struct Metasprite {
+    /// Back to front.
+    pub pieces: &'static [MetaspritePiece],
+}
+
+impl Metasprite {
+    pub fn render(&self) -> MetaspriteRenderer<'_> {
+        MetaspriteRenderer {
+            sprite: self,
+            hflip: false,
+            vflip: false,
+            parallax: 0,
+            offset: 0,
+        }
+    }
+
+    pub fn render_to_object_pool(
+        &self,
+        pool: &mut ObjectPool,
+        group: usize,
+        dst: (i16, i16),
+    ) -> bool {
+        self.render().into_object_pool(pool, group, dst)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MetaspritePiece {
+    /// How far the piece's top left corner is from the sprite's origin.
+    pub dx: i16,
+    pub dy: i16,
+    /// Added to the parallax of the whole sprite, so pieces can stand in front of or behind each other.
+    pub parallax: i16,
+    pub cell: vip::Cell,
+}
+
+pub struct MetaspriteRenderer<'a> {
+    sprite: &'a Metasprite,
+    hflip: bool,
+    vflip: bool,
+    parallax: i16,
+    offset: u16,
+}
+impl MetaspriteRenderer<'_> {
+    /// Mirror the whole sprite left to right, around its origin.
+    pub fn hflip(self, hflip: bool) -> Self {
+        Self { hflip, ..self }
+    }
+    /// Mirror the whole sprite top to bottom, around its origin.
+    pub fn vflip(self, vflip: bool) -> Self {
+        Self { vflip, ..self }
+    }
+    /// How far the sprite is moved left for the left eye, and right for the right eye.
+    pub fn parallax(self, parallax: i16) -> Self {
+        Self { parallax, ..self }
+    }
+    pub fn char_offset(self, offset: u16) -> Self {
+        Self { offset, ..self }
+    }
+
+    /// Draw the sprite with objects from one group of the pool, with its origin at `dst`.
+    /// Pieces which are off screen are skipped.
+    /// Returns false if the group ran out of objects partway through.
+    pub fn into_object_pool(self, pool: &mut ObjectPool, group: usize, dst: (i16, i16)) -> bool {
+        for piece in self.sprite.pieces {
+            let dx = if self.hflip { -8 - piece.dx } else { piece.dx };
+            let dy = if self.vflip { -8 - piece.dy } else { piece.dy };
+            let (jx, jy) = (dst.0.wrapping_add(dx), dst.1.wrapping_add(dy));
+            // JP only has 10 bits
+            let parallax = self
+                .parallax
+                .saturating_add(piece.parallax)
+                .clamp(-512, 511);
+            if jy <= -8 || jy >= 224 || jx <= -8 - parallax.abs() || jx > 384 + parallax.abs() {
                 continue;
             }
-            if dy >= 224 {
-                break;
-            }
-            for x in 0..self.image.width_cells {
-                let dx = dst.0 + (x as i16) * 8;
-                if dx <= min_x {
-                    continue;
-                }
-                if dx > max_x {
-                    break;
-                }
-                let cell =
-                    self.image.data[y as usize * self.image.width_cells as usize + x as usize];
-                let character = cell.character();
-                if character == 0 {
-                    continue;
-                }
-                let index = used - 1;
-                let obj = vip::OBJS.index(index as usize);
-                obj.jx().write(dx);
-                obj.stereo().write(stereo);
-                obj.jy().write(dy);
-                obj.cell()
-                    .write(cell.with_character(character + self.offset));
-                used = index;
+            let cell = piece
+                .cell
+                .with_character(piece.cell.character() + self.offset)
+                .with_h_flip(piece.cell.h_flip() != self.hflip)
+                .with_v_flip(piece.cell.v_flip() != self.vflip);
+            let object = vip::Object {
+                jx,
+                stereo: vip::ObjectStereo::new()
+                    .with_jp(parallax)
+                    .with_jlon(true)
+                    .with_jron(true),
+                jy,
+                cell,
+            };
+            if pool.push(group, object).is_none() {
+                return false;
             }
         }
-        used
+        true
     }
 }
 
@@ -281,12 +403,16 @@ impl Mask {
             .saturating_add_signed(offset.1)
             .min(self.height) as usize;
 
+        if right <= left {
+            return false;
+        }
+
         let lhs_width_cells = self.width.div_ceil(8) as usize;
         let rhs_width_cells = other.width.div_ceil(8) as usize;
         let length = right - left;
         for y in top..bottom {
-            let other_left = left.saturating_add_signed(-offset.0 as isize);
-            let other_y = y.saturating_add_signed(-offset.1 as isize);
+            let other_left = left.saturating_add_signed(-(offset.0 as isize));
+            let other_y = y.saturating_add_signed(-(offset.1 as isize));
 
             let lhs_bytes = &self.data[(y * lhs_width_cells + left / 8)..];
             let lhs_offset = left % 8;
@@ -308,8 +434,10 @@ impl Mask {
     ) -> bool {
         while length > 0 {
             let to_consume = length.min(8 - lhs_offset).min(8 - rhs_offset);
-            let lhs = lhs_bytes[0] << to_consume;
-            let rhs = rhs_bytes[0] << to_consume;
+            // The leftmost pixel is in the lowest bit
+            let mask = u8::MAX >> (8 - to_consume);
+            let lhs = (lhs_bytes[0] >> lhs_offset) & mask;
+            let rhs = (rhs_bytes[0] >> rhs_offset) & mask;
             if lhs & rhs != 0 {
                 return true;
             }
@@ -337,28 +465,30 @@ pub struct Texture {
 }
 
 impl Texture {
-    pub fn render_row_to_chardata(&self, index: u16, dst: (u8, u8), src: (u16, u16), size: u16) {
-        let mut dst_addr = (index as usize * 8) + dst.1 as usize;
-        let src_addr = self.width.div_ceil(4) as usize * src.1 as usize + (src.0 as usize / 4);
-        let src_offset = (src.0 % 4) as u8;
-        let dst_offset = dst.0;
-
-        let row_iter = TextureRowIter {
-            data: &self.data[src_addr..],
-            remaining: size as usize,
-            src_offset,
-            dst_offset,
-        };
+    vb_rt::ram_fn! {
+        pub fn render_row_to_chardata(&self, index: u16, dst: (u8, u8), src: (u16, u16), size: u16) {
+            let mut dst_addr = (index as usize * 8) + dst.1 as usize;
+            let src_addr = self.width.div_ceil(4) as usize * src.1 as usize + (src.0 as usize / 4);
+            let src_offset = (src.0 % 4) as u8;
+            let dst_offset = dst.0;
 
-        for TextureCell { data, mask } in row_iter {
-            let dst = vip::CHARACTER_HWS.index(dst_addr);
-            let value = if mask != 0 {
-                (dst.read() & mask) | data
-            } else {
-                data
+            let row_iter = TextureRowIter {
+                data: &self.data[src_addr..],
+                remaining: size as usize,
+                src_offset,
+                dst_offset,
             };
-            dst.write(value);
-            dst_addr += 8;
+
+            for TextureCell { data, mask } in row_iter {
+                let dst = vip::CHARACTER_HWS.index(dst_addr);
+                let value = if mask != 0 {
+                    (dst.read() & mask) | data
+                } else {
+                    data
+                };
+                dst.write(value);
+                dst_addr += 8;
+            }
         }
     }
 }
@@ -373,6 +503,8 @@ struct TextureRowIter<'a> {
 impl<'a> Iterator for TextureRowIter<'a> {
     type Item = TextureCell;
 
+    // Inlined into `render_row_to_chardata`, so it runs from WRAM along with it
+    #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
         if self.remaining == 0 {
             return None;
@@ -494,6 +626,20 @@ macro_rules! include_fontdata {
     };
 }
 
+#[macro_export]
+macro_rules! include_meshdata {
+    ($path:expr) => {
+        $crate::resource_value_impl!(4, include_bytes!($crate::out_path!($path)))
+    };
+}
+
+#[macro_export]
+macro_rules! include_metaspritedata {
+    ($path:expr) => {
+        $crate::resource_value_impl!(4, include_bytes!($crate::out_path!($path)))
+    };
+}
+
 #[macro_export]
 macro_rules! resource_value_impl {
     ($align:expr, $contents:expr) => {{
@@ -504,3 +650,405 @@ macro_rules! resource_value_impl {
         unsafe { core::mem::transmute(ALIGNED.0) }
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use vb_rt::sys::mock;
+
+    use super::*;
+
+    /// A 2x2 image with a blank cell in the top right.
+    static IMAGE: Image = Image {
+        width_cells: 2,
+        height_cells: 2,
+        data: &[
+            vip::Cell::new().with_character(1),
+            vip::Cell::new(),
+            vip::Cell::new().with_character(3).with_h_flip(true),
+            vip::Cell::new().with_character(4).with_palette(2),
+        ],
+    };
+
+    fn object_bytes(index: usize) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        mock::read_bytes(0x3e000 + index * 8, &mut bytes);
+        bytes
+    }
+
+    fn expected_object(jx: i16, stereo: vip::ObjectStereo, jy: i16, cell: vip::Cell) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0..2].copy_from_slice(&jx.to_le_bytes());
+        bytes[2..4].copy_from_slice(&stereo.into_bits().to_le_bytes());
+        bytes[4..6].copy_from_slice(&jy.to_le_bytes());
+        bytes[6..8].copy_from_slice(&cell.into_bits().to_le_bytes());
+        bytes
+    }
+
+    fn both_eyes(parallax: i16) -> vip::ObjectStereo {
+        vip::ObjectStereo::new()
+            .with_jlon(true)
+            .with_jron(true)
+            .with_jp(parallax)
+    }
+
+    #[test]
+    fn images_become_one_object_per_non_empty_cell() {
+        mock::reset();
+        let stereo = both_eyes(2);
+        assert_eq!(IMAGE.render().into_objects(10, (16, 20), stereo), 7);
+        assert_eq!(
+            object_bytes(9),
+            expected_object(16, stereo, 20, IMAGE.data[0])
+        );
+        assert_eq!(
+            object_bytes(8),
+            expected_object(16, stereo, 28, IMAGE.data[2])
+        );
+        assert_eq!(
+            object_bytes(7),
+            expected_object(24, stereo, 28, IMAGE.data[3])
+        );
+        assert_eq!(object_bytes(6), [0; 8]);
+        assert_eq!(object_bytes(10), [0; 8]);
+    }
+
+    #[test]
+    fn objects_can_use_characters_loaded_elsewhere() {
+        mock::reset();
+        let stereo = both_eyes(0);
+        IMAGE
+            .render()
+            .char_offset(100)
+            .into_objects(3, (0, 0), stereo);
+        assert_eq!(
+            object_bytes(2),
+            expected_object(0, stereo, 0, vip::Cell::new().with_character(101))
+        );
+        assert_eq!(
+            object_bytes(0),
+            expected_object(8, stereo, 8, IMAGE.data[3].with_character(104))
+        );
+    }
+
+    #[test]
+    fn offscreen_cells_dont_use_objects() {
+        mock::reset();
+        let stereo = both_eyes(2);
+        // The left column is too far off screen to show even with parallax
+        assert_eq!(IMAGE.render().into_objects(10, (-12, 0), stereo), 9);
+        assert_eq!(
+            object_bytes(9),
+            expected_object(-4, stereo, 8, IMAGE.data[3])
+        );
+
+        // The top row is above the screen
+        assert_eq!(IMAGE.render().into_objects(10, (0, -10), stereo), 8);
+        assert_eq!(
+            object_bytes(9),
+            expected_object(0, stereo, -2, IMAGE.data[2])
+        );
+
+        assert_eq!(IMAGE.render().into_objects(10, (0, 224), stereo), 10);
+        assert_eq!(IMAGE.render().into_objects(10, (400, 0), stereo), 10);
+    }
+
+    #[test]
+    fn objects_drawn_with_parallax_can_start_further_off_screen() {
+        mock::reset();
+        let stereo = both_eyes(5);
+        assert_eq!(IMAGE.render().into_objects(10, (-12, 0), stereo), 7);
+        assert_eq!(
+            object_bytes(9),
+            expected_object(-12, stereo, 0, IMAGE.data[0])
+        );
+    }
+
+    #[test]
+    fn metasprite_parallax_is_clamped_to_what_jp_can_hold() {
+        mock::reset();
+        static SPRITE: Metasprite = Metasprite {
+            pieces: &[
+                MetaspritePiece {
+                    dx: 0,
+                    dy: 0,
+                    parallax: i16::MAX,
+                    cell: vip::Cell::new().with_character(1),
+                },
+                MetaspritePiece {
+                    dx: 8,
+                    dy: 0,
+                    parallax: i16::MIN,
+                    cell: vip::Cell::new().with_character(2),
+                },
+                MetaspritePiece {
+                    dx: 16,
+                    dy: 0,
+                    parallax: -3,
+                    cell: vip::Cell::new().with_character(3),
+                },
+            ],
+        };
+        let mut pool = ObjectPool::even();
+        assert!(
+            SPRITE
+                .render()
+                .parallax(10)
+                .into_object_pool(&mut pool, 0, (100, 50))
+        );
+        let cells = SPRITE.pieces.iter().map(|piece| piece.cell);
+        for ((index, (jx, jp)), cell) in [(255, (100, 511)), (254, (108, -512)), (253, (116, 7))]
+            .into_iter()
+            .zip(cells)
+        {
+            assert_eq!(
+                object_bytes(index),
+                expected_object(jx, both_eyes(jp), 50, cell)
+            );
+        }
+    }
+
+    /// Three pieces around the origin, with different flips.
+    static FLIPPY: Metasprite = Metasprite {
+        pieces: &[
+            MetaspritePiece {
+                dx: -8,
+                dy: -8,
+                parallax: 0,
+                cell: vip::Cell::new().with_character(1),
+            },
+            MetaspritePiece {
+                dx: 0,
+                dy: -8,
+                parallax: 0,
+                cell: vip::Cell::new().with_character(2).with_h_flip(true),
+            },
+            MetaspritePiece {
+                dx: 4,
+                dy: 2,
+                parallax: 0,
+                cell: vip::Cell::new()
+                    .with_character(3)
+                    .with_v_flip(true)
+                    .with_palette(1),
+            },
+        ],
+    };
+
+    /// Where each piece of FLIPPY ends up, and its cell.
+    fn flippy_objects(hflip: bool, vflip: bool) -> [[u8; 8]; 3] {
+        mock::reset();
+        let mut pool = ObjectPool::even();
+        assert!(
+            FLIPPY
+                .render()
+                .hflip(hflip)
+                .vflip(vflip)
+                .char_offset(10)
+                .into_object_pool(&mut pool, 0, (100, 50))
+        );
+        [object_bytes(255), object_bytes(254), object_bytes(253)]
+    }
+
+    fn cell(character: u16, h_flip: bool, v_flip: bool, palette: u8) -> vip::Cell {
+        vip::Cell::new()
+            .with_character(character)
+            .with_h_flip(h_flip)
+            .with_v_flip(v_flip)
+            .with_palette(palette)
+    }
+
+    #[test]
+    fn metasprites_are_placed_around_their_origin() {
+        assert_eq!(
+            flippy_objects(false, false),
+            [
+                expected_object(92, both_eyes(0), 42, cell(11, false, false, 0)),
+                expected_object(100, both_eyes(0), 42, cell(12, true, false, 0)),
+                expected_object(104, both_eyes(0), 52, cell(13, false, true, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn hflip_mirrors_metasprites_around_their_origin() {
+        // A piece at dx ends up at -8 - dx, so its right edge is where its left edge was
+        assert_eq!(
+            flippy_objects(true, false),
+            [
+                expected_object(100, both_eyes(0), 42, cell(11, true, false, 0)),
+                expected_object(92, both_eyes(0), 42, cell(12, false, false, 0)),
+                expected_object(88, both_eyes(0), 52, cell(13, true, true, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn vflip_mirrors_metasprites_around_their_origin() {
+        assert_eq!(
+            flippy_objects(false, true),
+            [
+                expected_object(92, both_eyes(0), 50, cell(11, false, true, 0)),
+                expected_object(100, both_eyes(0), 50, cell(12, true, true, 0)),
+                expected_object(104, both_eyes(0), 40, cell(13, false, false, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn both_flips_turn_metasprites_around() {
+        assert_eq!(
+            flippy_objects(true, true),
+            [
+                expected_object(100, both_eyes(0), 50, cell(11, true, true, 0)),
+                expected_object(92, both_eyes(0), 50, cell(12, false, true, 0)),
+                expected_object(88, both_eyes(0), 40, cell(13, true, false, 1)),
+            ]
+        );
+    }
+
+    const fn piece(dx: i16, dy: i16, character: u16) -> MetaspritePiece {
+        MetaspritePiece {
+            dx,
+            dy,
+            parallax: 0,
+            cell: vip::Cell::new().with_character(character),
+        }
+    }
+
+    #[test]
+    fn off_screen_metasprite_pieces_are_skipped() {
+        static SPRITE: Metasprite = Metasprite {
+            pieces: &[
+                piece(-8, 0, 1),
+                piece(-7, 0, 2),
+                piece(384, 0, 3),
+                piece(385, 0, 4),
+                piece(0, -8, 5),
+                piece(0, -7, 6),
+                piece(0, 223, 7),
+                piece(0, 224, 8),
+            ],
+        };
+        mock::reset();
+        let mut pool = ObjectPool::even();
+        assert!(SPRITE.render_to_object_pool(&mut pool, 0, (0, 0)));
+        let drawn: [u16; 4] = core::array::from_fn(|n| {
+            vip::Cell::from_bits(u16::from_le_bytes(
+                object_bytes(255 - n)[6..8].try_into().unwrap(),
+            ))
+            .character()
+        });
+        assert_eq!(drawn, [2, 3, 6, 7]);
+        assert_eq!(pool.used(0), 4);
+
+        // Parallax moves each eye's copy further out, so more of the edge counts as on screen
+        static EDGES: Metasprite = Metasprite {
+            pieces: &[
+                piece(-12, 0, 1),
+                piece(-11, 0, 2),
+                piece(388, 0, 3),
+                piece(389, 0, 4),
+            ],
+        };
+        pool.clear();
+        assert!(
+            EDGES
+                .render()
+                .parallax(-4)
+                .into_object_pool(&mut pool, 0, (0, 0))
+        );
+        assert_eq!(pool.used(0), 2);
+        assert_eq!(
+            object_bytes(255),
+            expected_object(-11, both_eyes(-4), 0, EDGES.pieces[1].cell)
+        );
+        assert_eq!(
+            object_bytes(254),
+            expected_object(388, both_eyes(-4), 0, EDGES.pieces[2].cell)
+        );
+    }
+
+    #[test]
+    fn metasprites_report_a_full_pool() {
+        static SPRITE: Metasprite = Metasprite {
+            pieces: &[piece(0, 0, 1), piece(8, 0, 2), piece(16, 0, 3)],
+        };
+        mock::reset();
+        let mut pool = ObjectPool::new([2, 1, 1, 1]);
+        assert!(!SPRITE.render_to_object_pool(&mut pool, 0, (50, 50)));
+        // The pieces which fit were still drawn
+        assert_eq!(
+            object_bytes(1),
+            expected_object(50, both_eyes(0), 50, SPRITE.pieces[0].cell)
+        );
+        assert_eq!(
+            object_bytes(0),
+            expected_object(58, both_eyes(0), 50, SPRITE.pieces[1].cell)
+        );
+        assert_eq!(object_bytes(2), [0; 8]);
+
+        let mut pool = ObjectPool::new([3, 1, 1, 1]);
+        assert!(SPRITE.render_to_object_pool(&mut pool, 0, (50, 50)));
+    }
+
+    /// Masks store one bit per pixel, with each row padded to a whole byte and the leftmost pixel in the lowest bit.
+    const SOLID: Mask = Mask {
+        width: 8,
+        height: 2,
+        data: &[0xff, 0xff],
+    };
+    const TOP_LEFT: Mask = Mask {
+        width: 8,
+        height: 2,
+        data: &[0b0000_0001, 0],
+    };
+    const TOP_RIGHT: Mask = Mask {
+        width: 8,
+        height: 1,
+        data: &[0b1000_0000],
+    };
+    const WIDE: Mask = Mask {
+        width: 12,
+        height: 1,
+        data: &[0, 0b0000_0010],
+    };
+
+    #[test]
+    fn masks_intersect_where_pixels_overlap() {
+        assert!(SOLID.intersects(&TOP_LEFT, (0, 0)));
+        assert!(SOLID.intersects(&TOP_LEFT, (7, 1)));
+        assert!(TOP_LEFT.intersects(&SOLID, (-7, -1)));
+        assert!(!TOP_LEFT.intersects(&SOLID, (1, 0)));
+    }
+
+    #[test]
+    fn masks_which_dont_overlap_dont_intersect() {
+        assert!(!SOLID.intersects(&SOLID, (8, 0)));
+        assert!(!SOLID.intersects(&SOLID, (-8, 0)));
+        assert!(!SOLID.intersects(&SOLID, (0, 2)));
+        assert!(!SOLID.intersects(&SOLID, (0, -2)));
+        assert!(!SOLID.intersects(&SOLID, (i16::MAX, i16::MIN)));
+        assert!(!SOLID.intersects(&SOLID, (i16::MIN, i16::MAX)));
+    }
+
+    #[test]
+    fn transparent_pixels_dont_intersect() {
+        assert!(TOP_LEFT.intersects(&TOP_RIGHT, (-7, 0)));
+        assert!(!TOP_LEFT.intersects(&TOP_RIGHT, (-6, 0)));
+        assert!(!TOP_LEFT.intersects(&TOP_RIGHT, (-7, 1)));
+        assert!(TOP_RIGHT.intersects(&TOP_LEFT, (7, 0)));
+        assert!(!TOP_RIGHT.intersects(&TOP_LEFT, (6, 0)));
+    }
+
+    #[test]
+    fn intersections_are_found_across_byte_boundaries() {
+        // WIDE's only pixel is at x = 9
+        assert!(WIDE.intersects(&TOP_LEFT, (9, 0)));
+        assert!(!WIDE.intersects(&TOP_LEFT, (8, 0)));
+        assert!(WIDE.intersects(&TOP_RIGHT, (2, 0)));
+        assert!(!WIDE.intersects(&TOP_RIGHT, (3, 0)));
+        assert!(TOP_RIGHT.intersects(&WIDE, (-2, 0)));
+        assert!(TOP_LEFT.intersects(&WIDE, (-9, 0)));
+        assert!(!TOP_LEFT.intersects(&WIDE, (-8, 0)));
+    }
+}
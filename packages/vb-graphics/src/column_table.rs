@@ -0,0 +1,70 @@
+//! Rewrite the column tables while the game is running.
+//!
+//! The display reads one column table entry per column as it draws, and the CTA register tracks which one.
+//! Setting the `lock` bit in `DPCTRL` freezes CTA, so every write here sets it for as long as it takes,
+//! unless the game has already set it.
+//! ```ignore
+//! column_table::write_both(&ColumnTable::DEFAULT.with_repeat(brightness));
+//! ```
+
+pub use vb_rt::sys::vip::{ColumnTable, ColumnTableEntry};
+use vb_rt::sys::{VolatilePointer, vip};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+pub fn read(eye: Eye) -> ColumnTable {
+    ColumnTable(table(eye).read_array(0))
+}
+
+pub fn write(eye: Eye, table: &ColumnTable) {
+    with_lock(|| self::table(eye).write_slice(&table.0, 0));
+}
+
+pub fn write_both(table: &ColumnTable) {
+    with_lock(|| {
+        vip::LEFT_COLUMN_TABLE.write_slice(&table.0, 0);
+        vip::RIGHT_COLUMN_TABLE.write_slice(&table.0, 0);
+    });
+}
+
+/// Freeze or unfreeze CTA. Hold the lock across several writes to make them all take effect together.
+pub fn set_locked(locked: bool) {
+    vip::DPCTRL.write(display_flags().with_lock(locked));
+}
+
+pub fn is_locked() -> bool {
+    vip::DPSTTS.read().lock()
+}
+
+/// The indexes of the left and right entries which the display is reading.
+pub fn position() -> (u8, u8) {
+    let cta = vip::CTA.read();
+    (cta.cta_l(), cta.cta_r())
+}
+
+fn table(eye: Eye) -> VolatilePointer<[ColumnTableEntry; 256]> {
+    match eye {
+        Eye::Left => vip::LEFT_COLUMN_TABLE,
+        Eye::Right => vip::RIGHT_COLUMN_TABLE,
+    }
+}
+
+fn with_lock(write: impl FnOnce()) {
+    let flags = display_flags();
+    if flags.lock() {
+        write();
+        return;
+    }
+    vip::DPCTRL.write(flags.with_lock(true));
+    write();
+    vip::DPCTRL.write(flags);
+}
+
+/// The current display flags, safe to write back to `DPCTRL` without resetting the display.
+fn display_flags() -> vip::DisplayFlags {
+    vip::DPSTTS.read().with_dprst(false)
+}
@@ -0,0 +1,535 @@
+//! Draw pixels straight into the frame buffers, for wireframes and other effects which characters can't do.
+//!
+//! The display shows one pair of frame buffers while the other pair is drawn, and they swap every game frame.
+//! [`Canvas::back`] picks the pair which isn't being shown. Either turn off VIP drawing with
+//! [`set_vip_drawing`] and draw the whole frame yourself, or draw after the VIP has finished (on `xpend`)
+//! to add to what it drew.
+//!
+//! Colors are frame buffer pixel values: 0 is black, and 1 to 3 use the brightness in BRTA, BRTB and BRTC.
+//! ```ignore
+//! let canvas = Canvas::back();
+//! canvas.clear(Target::BOTH, 0);
+//! canvas.line(Target::Both(4), (10, 10), (200, 120), 3);
+//! ```
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use vb_rt::sys::{VolatilePointer, vip};
+
+use crate::Texture;
+
+pub const WIDTH: i16 = 384;
+pub const HEIGHT: i16 = 224;
+
+const WORDS_PER_COLUMN: usize = 16;
+const WORDS: usize = 384 * WORDS_PER_COLUMN;
+type Words = VolatilePointer<[u32; WORDS]>;
+
+const BUFFERS: [[Words; 2]; 2] = [
+    [
+        vip::LEFT_FRAME_BUFFER_0.cast(),
+        vip::RIGHT_FRAME_BUFFER_0.cast(),
+    ],
+    [
+        vip::LEFT_FRAME_BUFFER_1.cast(),
+        vip::RIGHT_FRAME_BUFFER_1.cast(),
+    ],
+];
+
+/// Which eye to draw to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Left,
+    Right,
+    /// Both eyes. The left eye sees everything this many pixels further left, and the right eye further right.
+    Both(i16),
+}
+
+impl Target {
+    /// Both eyes, at the depth of the screen.
+    pub const BOTH: Self = Self::Both(0);
+
+    fn eyes(self) -> [Option<(usize, i16)>; 2] {
+        match self {
+            Self::Left => [Some((0, 0)), None],
+            Self::Right => [None, Some((1, 0))],
+            Self::Both(parallax) => [Some((0, -parallax)), Some((1, parallax))],
+        }
+    }
+}
+
+static BACK_BUFFER: AtomicU8 = AtomicU8::new(0);
+
+/// The index of the frame buffer pair which won't be displayed until the next game frame.
+pub fn back_buffer() -> usize {
+    let display = vip::DPSTTS.read();
+    let drawing = vip::XPSTTS.read();
+    // The display shows the front buffer, and the VIP draws into the back one.
+    let back = if display.l0bsy() || display.r0bsy() || drawing.f1bsy() {
+        1
+    } else if display.l1bsy() || display.r1bsy() || drawing.f0bsy() {
+        0
+    } else {
+        // Between eyes, with the VIP idle. Nothing has swapped since we last knew.
+        return BACK_BUFFER.load(Ordering::Relaxed) as usize;
+    };
+    BACK_BUFFER.store(back, Ordering::Relaxed);
+    back as usize
+}
+
+/// Turn VIP drawing on or off. While it's off, the VIP leaves the frame buffers alone,
+/// and anything drawn to them stays there.
+pub fn set_vip_drawing(enabled: bool) {
+    vip::XPCTRL.write(vip::XPSTTS.read().with_xprst(false).with_xpen(enabled));
+}
+
+/// One pair of frame buffers, which can be drawn to pixel by pixel.
+/// Everything is clipped to the screen.
+#[derive(Clone, Copy, Debug)]
+pub struct Canvas {
+    buffers: [Words; 2],
+}
+
+impl Canvas {
+    /// The frame buffer pair which isn't being displayed.
+    pub fn back() -> Self {
+        Self::new(back_buffer())
+    }
+
+    /// Frame buffer pair 0 or 1.
+    pub const fn new(buffer: usize) -> Self {
+        assert!(buffer < 2, "There are only two frame buffers");
+        Self {
+            buffers: BUFFERS[buffer],
+        }
+    }
+
+    /// Fill the whole screen with one color. Ignores parallax.
+    pub fn clear(&self, target: Target, color: u8) {
+        let pattern = pattern(color);
+        self.each_eye(target, |words, _| {
+            for index in 0..WORDS {
+                words.index(index).write(pattern);
+            }
+        });
+    }
+
+    pub fn pixel(&self, target: Target, x: i16, y: i16, color: u8) {
+        self.fill_rect(target, (x, y), (1, 1), color);
+    }
+
+    /// A horizontal line, `width` pixels long, starting at (x, y) and going right.
+    pub fn hspan(&self, target: Target, x: i16, y: i16, width: i16, color: u8) {
+        self.fill_rect(target, (x, y), (width, 1), color);
+    }
+
+    /// A vertical line, `height` pixels long, starting at (x, y) and going down.
+    pub fn vspan(&self, target: Target, x: i16, y: i16, height: i16, color: u8) {
+        self.fill_rect(target, (x, y), (1, height), color);
+    }
+
+    pub fn fill_rect(&self, target: Target, position: (i16, i16), size: (i16, i16), color: u8) {
+        let (top, bottom) = clip(position.1, size.1, HEIGHT);
+        if top >= bottom {
+            return;
+        }
+        self.each_eye(target, |words, dx| {
+            let (left, right) = clip(position.0.saturating_add(dx), size.0, WIDTH);
+            for x in left..right {
+                fill_column(words, x, top, bottom, color);
+            }
+        });
+    }
+
+    /// A line from one point to another, including both ends.
+    pub fn line(&self, target: Target, from: (i16, i16), to: (i16, i16), color: u8) {
+        self.each_eye(target, |words, dx| {
+            let from = (from.0.saturating_add(dx), from.1);
+            let to = (to.0.saturating_add(dx), to.1);
+            draw_line(words, from, to, color);
+        });
+    }
+
+    /// Draw a whole texture with its top left corner at `dst`. Pixels with value 0 are transparent.
+    pub fn blit(&self, target: Target, texture: &Texture, dst: (i16, i16)) {
+        self.blit_region(
+            target,
+            texture,
+            dst,
+            (0, 0),
+            (texture.width, texture.height),
+        );
+    }
+
+    /// Draw part of a texture, `size` pixels starting at `src`, with its top left corner at `dst`.
+    /// Pixels with value 0 are transparent.
+    pub fn blit_region(
+        &self,
+        target: Target,
+        texture: &Texture,
+        dst: (i16, i16),
+        src: (u16, u16),
+        size: (u16, u16),
+    ) {
+        let width = size.0.min(texture.width.saturating_sub(src.0)) as i16;
+        let height = size.1.min(texture.height.saturating_sub(src.1)) as i16;
+        let (top, bottom) = clip(dst.1, height, HEIGHT);
+        if top >= bottom {
+            return;
+        }
+        let src_y = (src.1 as i16 + top - dst.1) as u16;
+        self.each_eye(target, |words, dx| {
+            let x = dst.0.saturating_add(dx);
+            let (left, right) = clip(x, width, WIDTH);
+            for column in left..right {
+                let src_x = src.0 + (column - x) as u16;
+                blit_column(words, column, top, bottom, texture, (src_x, src_y));
+            }
+        });
+    }
+
+    fn each_eye(&self, target: Target, mut draw: impl FnMut(Words, i16)) {
+        for (eye, dx) in target.eyes().into_iter().flatten() {
+            draw(self.buffers[eye], dx);
+        }
+    }
+}
+
+/// A word full of pixels of one color.
+const fn pattern(color: u8) -> u32 {
+    (color as u32 & 3) * 0x5555_5555
+}
+
+/// The bits in a word for rows `start` up to (but not including) `end`.
+const fn row_mask(start: i16, end: i16) -> u32 {
+    let below_end = if end >= 16 {
+        u32::MAX
+    } else {
+        (1 << (end * 2)) - 1
+    };
+    below_end & !((1 << (start * 2)) - 1)
+}
+
+/// The part of the span starting at `start`, `length` long, which lies between 0 and `limit`.
+fn clip(start: i16, length: i16, limit: i16) -> (i16, i16) {
+    let end = start.saturating_add(length.max(0)).min(limit);
+    (start.max(0), end)
+}
+
+fn column_word(words: Words, x: i16, y: i16) -> VolatilePointer<u32> {
+    words.index(x as usize * WORDS_PER_COLUMN + (y / 16) as usize)
+}
+
+vb_rt::ram_fn! {
+    fn fill_column(words: Words, x: i16, top: i16, bottom: i16, color: u8) {
+        let pattern = pattern(color);
+        let mut y = top;
+        while y < bottom {
+            let word_end = (y & !15) + 16;
+            let end = word_end.min(bottom);
+            let mask = row_mask(y & 15, end - (y & !15));
+            let word = column_word(words, x, y);
+            if mask == u32::MAX {
+                word.write(pattern);
+            } else {
+                word.write((word.read() & !mask) | (pattern & mask));
+            }
+            y = end;
+        }
+    }
+}
+
+vb_rt::ram_fn! {
+    fn blit_column(
+        words: Words,
+        x: i16,
+        top: i16,
+        bottom: i16,
+        texture: &Texture,
+        src: (u16, u16),
+    ) {
+        let stride = texture.width.div_ceil(4) as usize;
+        let mut src_index = src.1 as usize * stride + src.0 as usize / 4;
+        let shift = (src.0 % 4) * 2;
+        let (mut data, mut mask) = (0u32, 0u32);
+        for y in top..bottom {
+            let pixel = (texture.data[src_index] >> shift) & 3;
+            src_index += stride;
+            if pixel != 0 {
+                let bit = (y & 15) * 2;
+                data |= (pixel as u32) << bit;
+                mask |= 3 << bit;
+            }
+            if (y & 15) == 15 || y + 1 == bottom {
+                if mask != 0 {
+                    let word = column_word(words, x, y);
+                    word.write((word.read() & !mask) | data);
+                }
+                (data, mask) = (0, 0);
+            }
+        }
+    }
+}
+
+vb_rt::ram_fn! {
+    fn draw_line(words: Words, from: (i16, i16), to: (i16, i16), color: u8) {
+        let (min_x, max_x) = (from.0.min(to.0), from.0.max(to.0));
+        let (min_y, max_y) = (from.1.min(to.1), from.1.max(to.1));
+        if max_x < 0 || min_x >= WIDTH || max_y < 0 || min_y >= HEIGHT {
+            return;
+        }
+        let pattern = pattern(color);
+        let (mut x, mut y) = (from.0 as i32, from.1 as i32);
+        let (end_x, end_y) = (to.0 as i32, to.1 as i32);
+        let dx = (end_x - x).abs();
+        let dy = -(end_y - y).abs();
+        let step_x = if x < end_x { 1 } else { -1 };
+        let step_y = if y < end_y { 1 } else { -1 };
+        let mut error = dx + dy;
+        loop {
+            if (0..WIDTH as i32).contains(&x) && (0..HEIGHT as i32).contains(&y) {
+                let word = column_word(words, x as i16, y as i16);
+                let mask = 3 << ((y & 15) * 2);
+                word.write((word.read() & !mask) | (pattern & mask));
+            }
+            if x == end_x && y == end_y {
+                break;
+            }
+            let doubled = error * 2;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vb_rt::sys::mock;
+
+    use super::*;
+
+    const LEFT: usize = 0x00000;
+    const RIGHT: usize = 0x10000;
+
+    /// Word `index` of column `x` in the frame buffer at `buffer`.
+    fn word(buffer: usize, x: usize, index: usize) -> u32 {
+        let mut bytes = [0; 4];
+        mock::read_bytes(buffer + (x * WORDS_PER_COLUMN + index) * 4, &mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn pixel(buffer: usize, x: i16, y: i16) -> u8 {
+        let word = word(buffer, x as usize, y as usize / 16);
+        ((word >> ((y % 16) * 2)) & 3) as u8
+    }
+
+    /// Every lit pixel in the frame buffer at `buffer`, in column order.
+    fn lit_pixels(buffer: usize) -> impl Iterator<Item = (i16, i16)> {
+        (0..WIDTH)
+            .flat_map(|x| (0..HEIGHT).map(move |y| (x, y)))
+            .filter(move |&(x, y)| pixel(buffer, x, y) != 0)
+    }
+
+    #[test]
+    fn row_masks_cover_two_bits_per_row() {
+        assert_eq!(row_mask(0, 1), 0x0000_0003);
+        assert_eq!(row_mask(3, 5), 0x0000_03c0);
+        assert_eq!(row_mask(15, 16), 0xc000_0000);
+        assert_eq!(row_mask(0, 16), u32::MAX);
+        assert_eq!(row_mask(4, 4), 0);
+    }
+
+    #[test]
+    fn pixels_land_on_either_side_of_a_word_boundary() {
+        mock::reset();
+        let canvas = Canvas::new(0);
+        canvas.pixel(Target::BOTH, 10, 15, 2);
+        canvas.pixel(Target::BOTH, 10, 16, 3);
+        for buffer in [LEFT, RIGHT] {
+            assert_eq!(word(buffer, 10, 0), 0x8000_0000);
+            assert_eq!(word(buffer, 10, 1), 0x0000_0003);
+            assert_eq!(word(buffer, 9, 0), 0);
+            assert_eq!(word(buffer, 11, 1), 0);
+        }
+    }
+
+    #[test]
+    fn fill_rect_keeps_the_rest_of_the_word() {
+        mock::reset();
+        let canvas = Canvas::new(0);
+        canvas.fill_rect(Target::Left, (0, 0), (1, 16), 3);
+        canvas.fill_rect(Target::Left, (0, 3), (1, 2), 1);
+        assert_eq!(word(LEFT, 0, 0), 0xffff_fd7f);
+    }
+
+    #[test]
+    fn fill_rect_is_clipped_at_every_edge() {
+        mock::reset();
+        let canvas = Canvas::new(0);
+        // Top left corner
+        canvas.fill_rect(Target::Left, (-5, -5), (7, 7), 3);
+        assert_eq!(word(LEFT, 0, 0), 0x0000_000f);
+        assert_eq!(word(LEFT, 1, 0), 0x0000_000f);
+        assert_eq!(word(LEFT, 2, 0), 0);
+
+        // Bottom right corner
+        canvas.fill_rect(Target::Left, (380, 220), (10, 10), 1);
+        assert_eq!(word(LEFT, 379, 13), 0);
+        for x in 380..384 {
+            assert_eq!(word(LEFT, x, 13), 0x5500_0000);
+            assert_eq!(word(LEFT, x, 14), 0);
+        }
+
+        // Bigger than the screen in every direction
+        mock::reset();
+        canvas.fill_rect(Target::Right, (-10, -10), (500, 300), 2);
+        assert_eq!(word(RIGHT, 0, 0), 0xaaaa_aaaa);
+        assert_eq!(word(RIGHT, 383, 13), 0xaaaa_aaaa);
+        assert_eq!(word(RIGHT, 383, 14), 0);
+        assert_eq!(lit_pixels(LEFT).count(), 0);
+    }
+
+    #[test]
+    fn parallax_moves_each_eye_apart() {
+        mock::reset();
+        let canvas = Canvas::new(0);
+        canvas.pixel(Target::Both(3), 100, 20, 1);
+        assert!(lit_pixels(LEFT).eq([(97, 20)]));
+        assert!(lit_pixels(RIGHT).eq([(103, 20)]));
+
+        mock::reset();
+        canvas.pixel(Target::Both(-3), 100, 20, 1);
+        assert!(lit_pixels(LEFT).eq([(103, 20)]));
+        assert!(lit_pixels(RIGHT).eq([(97, 20)]));
+    }
+
+    #[test]
+    fn single_eye_targets_leave_the_other_eye_alone() {
+        mock::reset();
+        let canvas = Canvas::new(0);
+        canvas.pixel(Target::Left, 5, 6, 1);
+        canvas.pixel(Target::Right, 7, 8, 2);
+        assert!(lit_pixels(LEFT).eq([(5, 6)]));
+        assert!(lit_pixels(RIGHT).eq([(7, 8)]));
+    }
+
+    #[test]
+    fn blits_treat_zero_as_transparent() {
+        // Two rows of four pixels: 1 0 2 0, then 0 3 0 1
+        static TEXTURE: Texture = Texture {
+            width: 4,
+            height: 2,
+            data: &[0b00_10_00_01, 0b01_00_11_00],
+        };
+        mock::reset();
+        let canvas = Canvas::new(0);
+        canvas.fill_rect(Target::Left, (20, 14), (4, 2), 3);
+        canvas.blit(Target::Left, &TEXTURE, (20, 15));
+        let rows = |y| core::array::from_fn::<_, 4, _>(|x| pixel(LEFT, 20 + x as i16, y));
+        assert_eq!(rows(14), [3, 3, 3, 3]);
+        assert_eq!(rows(15), [1, 3, 2, 3]);
+        assert_eq!(rows(16), [0, 3, 0, 1]);
+    }
+
+    #[test]
+    fn blits_are_clipped_to_the_screen() {
+        static TEXTURE: Texture = Texture {
+            width: 4,
+            height: 2,
+            data: &[0b11_10_01_11, 0b01_10_11_01],
+        };
+        mock::reset();
+        let canvas = Canvas::new(0);
+        canvas.blit(Target::Left, &TEXTURE, (-2, 223));
+        assert!(lit_pixels(LEFT).eq([(0, 223), (1, 223)]));
+        assert_eq!((pixel(LEFT, 0, 223), pixel(LEFT, 1, 223)), (2, 3));
+    }
+
+    #[test]
+    fn shallow_lines_light_one_pixel_per_column() {
+        mock::reset();
+        let canvas = Canvas::new(0);
+        canvas.line(Target::Left, (10, 3), (0, 0), 1);
+        let pixels: [(i16, i16); 11] = {
+            let mut lit = lit_pixels(LEFT);
+            core::array::from_fn(|_| lit.next().unwrap())
+        };
+        assert_eq!(lit_pixels(LEFT).count(), 11);
+        assert_eq!(pixels[0], (0, 0));
+        assert_eq!(pixels[10], (10, 3));
+        for (x, &(px, py)) in pixels.iter().enumerate() {
+            assert_eq!(px, x as i16);
+            // Never more than half a pixel from the ideal line
+            assert!((py * 10 - x as i16 * 3).abs() <= 5);
+        }
+    }
+
+    #[test]
+    fn steep_lines_light_one_pixel_per_row() {
+        mock::reset();
+        let canvas = Canvas::new(0);
+        canvas.line(Target::Right, (5, 0), (8, 20), 2);
+        assert_eq!(lit_pixels(RIGHT).count(), 21);
+        assert_eq!(pixel(RIGHT, 5, 0), 2);
+        assert_eq!(pixel(RIGHT, 8, 20), 2);
+        for y in 0..=20 {
+            let row: i16 = (0..WIDTH).filter(|&x| pixel(RIGHT, x, y) != 0).count() as i16;
+            assert_eq!(row, 1, "row {y}");
+        }
+    }
+
+    #[test]
+    fn lines_are_clipped_to_the_screen() {
+        mock::reset();
+        let canvas = Canvas::new(0);
+        canvas.line(Target::Left, (-2, 5), (2, 5), 1);
+        canvas.line(Target::Left, (100, 220), (100, 230), 1);
+        canvas.line(Target::Left, (-10, -10), (-1, -1), 1);
+        assert!(lit_pixels(LEFT).eq([
+            (0, 5),
+            (1, 5),
+            (2, 5),
+            (100, 220),
+            (100, 221),
+            (100, 222),
+            (100, 223)
+        ]));
+    }
+
+    fn set_status(l0bsy: bool, f1bsy: bool, l1bsy: bool, f0bsy: bool) {
+        let display = vip::DisplayFlags::new().with_l0bsy(l0bsy).with_l1bsy(l1bsy);
+        let drawing = vip::DrawingFlags::new().with_f0bsy(f0bsy).with_f1bsy(f1bsy);
+        mock::write_bytes(0x5f820, &display.into_bits().to_le_bytes());
+        mock::write_bytes(0x5f840, &drawing.into_bits().to_le_bytes());
+    }
+
+    #[test]
+    fn the_back_buffer_is_whichever_isnt_busy() {
+        mock::reset();
+        // Showing or drawing buffer 0, so buffer 1 is free
+        set_status(true, false, false, false);
+        assert_eq!(back_buffer(), 1);
+        // Showing or drawing buffer 1, so buffer 0 is free
+        set_status(false, false, true, false);
+        assert_eq!(back_buffer(), 0);
+        set_status(false, false, false, true);
+        assert_eq!(back_buffer(), 0);
+        // Nothing busy, so it's whatever it was last time
+        set_status(false, false, false, false);
+        assert_eq!(back_buffer(), 0);
+
+        // The VIP drawing into buffer 1 means it's the back buffer too
+        set_status(false, true, false, false);
+        assert_eq!(back_buffer(), 1);
+        set_status(true, true, false, false);
+        assert_eq!(back_buffer(), 1);
+        set_status(false, false, false, false);
+        assert_eq!(back_buffer(), 1);
+    }
+}
@@ -1,13 +1,24 @@
 #![no_std]
-#![cfg(target_arch = "v810")]
+#![cfg(any(target_arch = "v810", feature = "host-mock"))]
 
 mod assets;
+pub mod column_table;
+pub mod framebuffer;
+pub mod objects;
 pub mod text;
+pub mod wire3d;
+pub mod worlds;
 
 use core::sync::atomic::AtomicBool;
 
-pub use assets::{BgAnimation, BgSprite, Font, FontCharacter, Image, Mask, StereoImage, Texture};
-use vb_rt::sys::{halt, vip};
+pub use assets::{
+    BgAnimation, BgSprite, Font, FontCharacter, Image, Mask, Metasprite, MetaspritePiece,
+    StereoImage, Texture,
+};
+use vb_rt::{
+    interrupt,
+    sys::{halt, vip},
+};
 
 const PALETTES: [vip::Palette; 4] = [
     vip::Palette::new().with_c1(1).with_c2(2).with_c3(3),
@@ -67,8 +78,7 @@ impl FrameMonitor {
 
     pub fn enable_interrupts(&self) {
         vip::INTENB.write(vip::InterruptFlags::new().with_xpend(true));
-        // clear tne NP flag in PSW so interrupts can fire
-        unsafe { core::arch::asm!("ldsr r0, psw", options(nomem)) };
+        interrupt::enable();
     }
 
     pub fn acknowledge_interrupts(&self) {
@@ -0,0 +1,267 @@
+//! Share the 1024 objects between the four object groups, and hide whatever wasn't drawn this frame.
+//!
+//! Each object world draws one group: the first object world in the world list draws group 3,
+//! and the ones in front of it draw groups 2, 1 and 0. [`WorldList::push_objects`] returns the group
+//! its world draws. Within a group, objects pushed later are drawn on top of objects pushed earlier.
+//!
+//! Unlike [`WorldList`], the pool doesn't keep a copy of the objects in RAM, since all 1024 would take 8 KiB.
+//! Objects go straight to `vip::OBJS` as they're pushed, and the VIP reads them while it draws.
+//! So push objects after the VIP has finished drawing a frame, or the frame may tear,
+//! with some rows showing the old objects and some the new ones.
+//! ```ignore
+//! let mut pool = ObjectPool::new([64, 64, 128, 256]);
+//! loop {
+//!     game.update();
+//!     // The VIP is idle until the next frame starts, so nothing changes while it draws
+//!     FRAME.wait_for_new_frame();
+//!     worlds.clear();
+//!     pool.clear();
+//!     worlds.push(WorldBuilder::normal(0)).unwrap();
+//!     let group = worlds.push_objects().unwrap();
+//!     assets::PLAYER.render().into_object_pool(&mut pool, group, (x, y), stereo);
+//!     worlds.flush();
+//!     pool.flush();
+//! }
+//! ```
+//!
+//! [`WorldList`]: crate::worlds::WorldList
+//! [`WorldList::push_objects`]: crate::worlds::WorldList::push_objects
+
+use vb_rt::sys::vip;
+
+const OBJECT_COUNT: u16 = 1024;
+
+/// Objects for each of the four groups, and how many of them are in use.
+pub struct ObjectPool {
+    /// The highest index in each group. Objects are handed out from there downward.
+    tops: [u16; 4],
+    used: [u16; 4],
+    /// How many objects each group used when it was last flushed.
+    flushed: [u16; 4],
+}
+
+impl ObjectPool {
+    /// A pool which gives `budgets[n]` objects to group n.
+    /// Panics if any group has no objects, or if there are more than 1024 altogether.
+    pub const fn new(budgets: [u16; 4]) -> Self {
+        let mut tops = [0; 4];
+        let mut total = 0;
+        let mut group = 0;
+        while group < 4 {
+            assert!(
+                budgets[group] > 0,
+                "Every object group needs at least one object"
+            );
+            total += budgets[group];
+            assert!(total <= OBJECT_COUNT, "There are only 1024 objects");
+            tops[group] = total - 1;
+            group += 1;
+        }
+        Self {
+            tops,
+            used: [0; 4],
+            flushed: [OBJECT_COUNT; 4],
+        }
+    }
+
+    /// A pool which gives 256 objects to each group.
+    pub const fn even() -> Self {
+        Self::new([256; 4])
+    }
+
+    /// How many objects group `group` has.
+    pub const fn budget(&self, group: usize) -> u16 {
+        self.tops[group] - self.bottom(group) + 1
+    }
+
+    /// How many objects group `group` has used since the last clear.
+    pub const fn used(&self, group: usize) -> u16 {
+        self.used[group]
+    }
+
+    pub const fn remaining(&self, group: usize) -> u16 {
+        self.budget(group) - self.used[group]
+    }
+
+    /// Start a new frame, with every object available again.
+    /// Objects from the last frame stay on screen until they're pushed over, or hidden by the next flush.
+    pub fn clear(&mut self) {
+        self.used = [0; 4];
+    }
+
+    /// Write an object into group `group`, in front of every object already in it.
+    /// It's written to `vip::OBJS` right away rather than at the next flush,
+    /// so it may tear if the VIP is drawing. Returns its index, or `None` if the group is full.
+    pub fn push(&mut self, group: usize, object: vip::Object) -> Option<u16> {
+        if self.remaining(group) == 0 {
+            return None;
+        }
+        let index = self.tops[group] - self.used[group];
+        self.used[group] += 1;
+        vip::OBJS.index(index as usize).write(object);
+        Some(index)
+    }
+
+    /// Point the SPT registers at each group, and hide any objects which were used last time but not this time.
+    /// Do this between frames, while the VIP isn't drawing.
+    pub fn flush(&mut self) {
+        for group in 0..4 {
+            vip::SPT.index(group).write(self.tops[group]);
+            // Everything from the top of the group down to `used` is this frame's.
+            let stale_end = self.tops[group] + 1 - self.used[group];
+            let stale_start = self.tops[group] + 1 - self.flushed[group].min(self.budget(group));
+            for index in stale_start..stale_end {
+                // With neither jlon nor jron set, the object isn't drawn to either eye.
+                let stereo = vip::OBJS.index(index as usize).stereo();
+                stereo.write(stereo.read().with_jlon(false).with_jron(false));
+            }
+            self.flushed[group] = self.used[group];
+        }
+    }
+
+    const fn bottom(&self, group: usize) -> u16 {
+        if group == 0 {
+            0
+        } else {
+            self.tops[group - 1] + 1
+        }
+    }
+}
+
+impl Default for ObjectPool {
+    fn default() -> Self {
+        Self::even()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vb_rt::sys::mock;
+
+    use super::*;
+
+    const OBJS: usize = 0x3e000;
+
+    fn object_bytes(index: usize) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        mock::read_bytes(OBJS + index * 8, &mut bytes);
+        bytes
+    }
+
+    /// Fill objects `range` with set bits, so we can see which bits a flush clears.
+    fn scribble(range: core::ops::Range<usize>) {
+        for index in range {
+            mock::write_bytes(OBJS + index * 8, &[0xff; 8]);
+        }
+    }
+
+    /// What a scribbled object looks like once it's been hidden.
+    fn hidden() -> [u8; 8] {
+        let stereo = vip::ObjectStereo::from_bits(0xffff)
+            .with_jlon(false)
+            .with_jron(false)
+            .into_bits();
+        let [lo, hi] = stereo.to_le_bytes();
+        [0xff, 0xff, lo, hi, 0xff, 0xff, 0xff, 0xff]
+    }
+
+    fn object(jx: i16) -> vip::Object {
+        vip::Object {
+            jx,
+            stereo: vip::ObjectStereo::new().with_jlon(true).with_jron(true),
+            jy: 8,
+            cell: vip::Cell::new().with_character(1),
+        }
+    }
+
+    #[test]
+    fn spt_points_at_the_top_of_each_group() {
+        mock::reset();
+        let mut pool = ObjectPool::new([64, 64, 128, 256]);
+        pool.flush();
+        let spt: [u16; 4] = core::array::from_fn(|group| vip::SPT.index(group).read());
+        assert_eq!(spt, [63, 127, 255, 511]);
+        let budgets: [u16; 4] = core::array::from_fn(|group| pool.budget(group));
+        assert_eq!(budgets, [64, 64, 128, 256]);
+    }
+
+    #[test]
+    fn objects_are_handed_out_from_the_top_down() {
+        mock::reset();
+        let mut pool = ObjectPool::new([64, 64, 128, 256]);
+        assert_eq!(pool.push(1, object(1)), Some(127));
+        assert_eq!(pool.push(1, object(2)), Some(126));
+        assert_eq!(pool.push(0, object(3)), Some(63));
+        assert_eq!(pool.push(3, object(4)), Some(511));
+        assert_eq!((pool.used(1), pool.remaining(1)), (2, 62));
+        // Written right away, without waiting for a flush
+        assert_eq!(object_bytes(126)[..2], 2i16.to_le_bytes());
+        assert_eq!(object_bytes(511)[..2], 4i16.to_le_bytes());
+
+        pool.clear();
+        assert_eq!(pool.push(1, object(5)), Some(127));
+    }
+
+    #[test]
+    fn push_fails_once_the_group_is_full() {
+        mock::reset();
+        let mut pool = ObjectPool::new([2, 1, 1, 1]);
+        assert_eq!(pool.push(0, object(0)), Some(1));
+        assert_eq!(pool.push(0, object(0)), Some(0));
+        assert_eq!(pool.push(0, object(0)), None);
+        assert_eq!(pool.remaining(0), 0);
+        // Other groups are unaffected
+        assert_eq!(pool.push(1, object(0)), Some(2));
+        assert_eq!(pool.push(1, object(0)), None);
+    }
+
+    #[test]
+    fn the_first_flush_hides_every_unused_object() {
+        mock::reset();
+        scribble(0..1024);
+        let mut pool = ObjectPool::new([64, 64, 128, 256]);
+        pool.push(0, object(7)).unwrap();
+        pool.flush();
+        assert_eq!(object_bytes(63)[..2], 7i16.to_le_bytes());
+        for index in (0..63).chain(64..512) {
+            assert_eq!(object_bytes(index), hidden(), "object {index}");
+        }
+        // Objects outside of every group are left alone
+        for index in 512..1024 {
+            assert_eq!(object_bytes(index), [0xff; 8], "object {index}");
+        }
+    }
+
+    #[test]
+    fn objects_which_werent_reused_are_hidden() {
+        mock::reset();
+        let mut pool = ObjectPool::new([64, 64, 128, 256]);
+        for jx in 0..3 {
+            pool.push(3, object(jx)).unwrap();
+        }
+        pool.flush();
+
+        scribble(256..512);
+        pool.clear();
+        pool.push(3, object(10)).unwrap();
+        pool.flush();
+        assert_eq!(object_bytes(511)[..2], 10i16.to_le_bytes());
+        // Used last frame but not this one
+        assert_eq!(object_bytes(510), hidden());
+        assert_eq!(object_bytes(509), hidden());
+        // Hidden by the first flush, so there's nothing to do
+        for index in 256..509 {
+            assert_eq!(object_bytes(index), [0xff; 8], "object {index}");
+        }
+
+        // A frame which uses more objects than the last one has nothing to hide
+        scribble(256..511);
+        pool.clear();
+        pool.push(3, object(0)).unwrap();
+        pool.push(3, object(0)).unwrap();
+        pool.flush();
+        for index in 256..510 {
+            assert_eq!(object_bytes(index), [0xff; 8], "object {index}");
+        }
+    }
+}
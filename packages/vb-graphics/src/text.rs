@@ -233,3 +233,151 @@ impl<const N: usize> core::fmt::Write for BufferedTextRenderer<N> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use vb_rt::sys::mock;
+
+    use super::*;
+    use crate::{FontCharacter, Texture};
+
+    /// Two glyphs: a 3x2 one which sits a row down, and a 2x1 one.
+    static TEXTURE: Texture = Texture {
+        width: 8,
+        height: 2,
+        // 2 bits per pixel, leftmost pixel in the low bits
+        data: &[0b00_10_01_11, 0b00_00_10_10, 0b00_01_01_01, 0b00_00_00_00],
+    };
+    static FONT: Font = Font {
+        texture: &TEXTURE,
+        chars: &[
+            FontCharacter {
+                x: 0,
+                y_offset: 1,
+                width: 3,
+                height: 2,
+            },
+            FontCharacter {
+                x: 4,
+                y_offset: 0,
+                width: 2,
+                height: 1,
+            },
+        ],
+        line_height: 4,
+    };
+
+    const CHARDATA_START: u16 = 10;
+
+    /// The 8 rows of a character, 2 bits per pixel with the leftmost pixel in the low bits.
+    fn rows(character: u16) -> [u16; 8] {
+        let bytes = mock::bytes(0x78000 + character as usize * 16, 16);
+        core::array::from_fn(|row| u16::from_le_bytes([bytes[row * 2], bytes[row * 2 + 1]]))
+    }
+
+    #[test]
+    fn characters_are_drawn_into_chardata() {
+        mock::reset();
+        let mut text = TextRenderer::new(&FONT, CHARDATA_START, (2, 1));
+        assert!(text.draw_char(0));
+        assert_eq!(rows(10), [0, 0b10_01_11, 0b01_01_01, 0, 0, 0, 0, 0]);
+        assert_eq!(text.width(), 4);
+        assert!(!text.is_empty());
+    }
+
+    #[test]
+    fn characters_are_drawn_after_each_other() {
+        mock::reset();
+        let mut text = TextRenderer::new(&FONT, CHARDATA_START, (2, 1));
+        assert!(text.draw_char(0));
+        assert!(text.draw_char(1));
+        assert_eq!(rows(10)[0], 0b10_10 << 8);
+        assert_eq!(text.width(), 7);
+    }
+
+    #[test]
+    fn characters_straddle_chardata_boundaries() {
+        mock::reset();
+        let mut text = TextRenderer::new(&FONT, CHARDATA_START, (2, 1));
+        text.draw_text(&[0, 1, 0]);
+        // The last glyph starts at the 7th pixel, so its first column lands in the last column of character 10
+        assert_eq!(rows(10)[1], 0b11 << 14 | 0b10_01_11);
+        assert_eq!(rows(10)[2], 0b01 << 14 | 0b01_01_01);
+        assert_eq!(rows(11)[1], 0b10_01);
+        assert_eq!(rows(11)[2], 0b01_01);
+        assert_eq!(text.width(), 11);
+    }
+
+    #[test]
+    fn drawing_keeps_pixels_left_of_the_glyph_and_outside_its_rows() {
+        mock::reset();
+        let mut text = TextRenderer::new(&FONT, CHARDATA_START, (2, 1));
+        mock::write_bytes(0x78000 + 10 * 16, &[0xff; 16]);
+        text.draw_char(0);
+        assert_eq!(
+            rows(10),
+            [
+                0xffff, 0b10_01_11, 0b01_01_01, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff
+            ]
+        );
+        // Everything to the right of the glyph is expected to be blank already, so it's overwritten
+        text.draw_char(1);
+        assert_eq!(rows(10)[0], 0b10_10 << 8 | 0xff);
+    }
+
+    #[test]
+    fn running_out_of_room_stops_drawing() {
+        mock::reset();
+        let mut text = TextRenderer::new(&FONT, CHARDATA_START, (2, 1));
+        assert!(text.draw_text(&[0, 0, 0]));
+        assert!(!text.draw_char(0));
+        assert_eq!(rows(12), [0; 8]);
+    }
+
+    #[test]
+    fn newlines_move_down_a_line() {
+        mock::reset();
+        let mut text = TextRenderer::new(&FONT, CHARDATA_START, (2, 2));
+        assert!(text.draw_char(0));
+        assert!(text.draw_char(b'\n'));
+        assert!(text.draw_char(1));
+        // Four rows down is still inside the first row of characters
+        assert_eq!(rows(10)[4], 0b10_10);
+        assert_eq!(text.width(), 3);
+        assert!(text.draw_char(b'\n'));
+        assert!(text.draw_char(1));
+        // Another four rows down is the next row of characters
+        assert_eq!(rows(12)[0], 0b10_10);
+        assert!(text.draw_char(b'\n'));
+        assert!(!text.draw_char(b'\n'));
+    }
+
+    #[test]
+    fn clearing_erases_the_chardata() {
+        mock::reset();
+        let mut text = TextRenderer::new(&FONT, CHARDATA_START, (2, 1));
+        mock::write_bytes(0x78000 + 9 * 16, &[0xff; 64]);
+        text.clear();
+        assert_eq!(rows(9), [0xffff; 8]);
+        assert_eq!(rows(10), [0; 8]);
+        assert_eq!(rows(11), [0; 8]);
+        assert_eq!(rows(12), [0xffff; 8]);
+        assert!(text.is_empty());
+    }
+
+    #[test]
+    fn bgmaps_point_at_the_chardata() {
+        mock::reset();
+        let text = TextRenderer::new(&FONT, CHARDATA_START, (2, 2));
+        assert_eq!(text.render_to_bgmap(1, (3, 4)), (24, 32));
+        let cell = |x: usize, y: usize| {
+            let bytes = mock::bytes(0x20000 + 0x2000 + (y * 64 + x) * 2, 2);
+            u16::from_le_bytes([bytes[0], bytes[1]])
+        };
+        assert_eq!(
+            [cell(3, 4), cell(4, 4), cell(3, 5), cell(4, 5)],
+            [10, 11, 12, 13]
+        );
+        assert_eq!(cell(5, 4), 0);
+    }
+}
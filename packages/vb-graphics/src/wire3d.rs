@@ -0,0 +1,412 @@
+//! Stereo wireframe 3D, drawn straight into the frame buffers.
+//!
+//! A [`Mesh`] is a list of vertices and the edges between them, usually generated by vb-graphics-build
+//! from a `[mesh.name]` section in `assets.toml`. A [`Scene`] moves each mesh into place with a [`Transform`],
+//! looks at it through its [`Camera`], and projects it for each eye with a [`Projection`].
+//! Edges which pass behind the camera or off the screen are clipped.
+//! ```ignore
+//! let mut scene = Scene::<64>::new(Projection::new(I16F16::from_num(256), I16F16::from_num(8)));
+//! scene.camera.position = Vec3::from_ints(0, 0, -200);
+//! let canvas = Canvas::back();
+//! canvas.clear(Target::BOTH, 0);
+//! scene.draw(&canvas, &assets::SHIP, &Transform::rotation(Mat3::rotation_y(angle)), 3);
+//! ```
+
+use arrayvec::ArrayVec;
+use vb_rt::math::{I16F16, Mat3, Projected, Projection, Vec3, recip};
+
+use crate::framebuffer::{Canvas, HEIGHT, Target, WIDTH};
+
+/// A wireframe model. Coordinates are whole units in model space.
+#[derive(Clone, Copy, Debug)]
+pub struct Mesh {
+    pub vertices: &'static [[i16; 3]],
+    /// Pairs of indices into `vertices`.
+    pub edges: &'static [[u16; 2]],
+}
+
+/// Where a mesh sits in the world: rotated first, then moved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transform {
+    pub rotation: Mat3,
+    pub translation: Vec3,
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self::new(Mat3::IDENTITY, Vec3::ZERO);
+
+    pub const fn new(rotation: Mat3, translation: Vec3) -> Self {
+        Self {
+            rotation,
+            translation,
+        }
+    }
+
+    pub const fn rotation(rotation: Mat3) -> Self {
+        Self::new(rotation, Vec3::ZERO)
+    }
+
+    pub const fn translation(translation: Vec3) -> Self {
+        Self::new(Mat3::IDENTITY, translation)
+    }
+
+    pub fn apply(&self, point: Vec3) -> Vec3 {
+        self.rotation * point + self.translation
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// The viewpoint of a [`Scene`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Camera {
+    pub position: Vec3,
+    /// Turns world directions into camera directions, where +z is straight ahead.
+    /// To turn the camera one way, rotate the world the other way.
+    pub rotation: Mat3,
+}
+
+impl Camera {
+    /// A camera at the origin, looking down +z.
+    pub const fn new() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            rotation: Mat3::IDENTITY,
+        }
+    }
+
+    /// The transform from world space into camera space.
+    pub fn view(&self) -> Transform {
+        let rotation = self.rotation;
+        Transform::new(rotation, -(rotation * self.position))
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Draws meshes in stereo. Holds room for `VERTICES` transformed vertices,
+/// which is the most a mesh can have.
+pub struct Scene<const VERTICES: usize> {
+    pub projection: Projection,
+    pub camera: Camera,
+    /// Each vertex in camera space, and where it lands on screen if it's in front of the near plane.
+    view_vertices: ArrayVec<(Vec3, Option<Projected>), VERTICES>,
+}
+
+impl<const VERTICES: usize> Scene<VERTICES> {
+    pub const fn new(projection: Projection) -> Self {
+        Self {
+            projection,
+            camera: Camera::new(),
+            view_vertices: ArrayVec::new_const(),
+        }
+    }
+
+    /// Draw every edge of a mesh into both eyes of the canvas.
+    /// Panics if the mesh has more than `VERTICES` vertices.
+    pub fn draw(&mut self, canvas: &Canvas, mesh: &Mesh, transform: &Transform, color: u8) {
+        assert!(
+            mesh.vertices.len() <= VERTICES,
+            "Mesh has more vertices than the scene has room for"
+        );
+        let view = self.camera.view();
+        let model_view = Transform::new(
+            view.rotation * transform.rotation,
+            view.apply(transform.translation),
+        );
+        // Vertices are shared between edges, so project each of them once up front
+        self.view_vertices.clear();
+        for &[x, y, z] in mesh.vertices {
+            let vertex = model_view.apply(Vec3::from_ints(x, y, z));
+            self.view_vertices
+                .push((vertex, self.projection.project(vertex)));
+        }
+        let near = self.projection.near;
+        for &[a, b] in mesh.edges {
+            let (a, b) = (
+                self.view_vertices[a as usize],
+                self.view_vertices[b as usize],
+            );
+            // Only the end of an edge which crosses the near plane needs projecting again
+            let projected = match (a, b) {
+                ((_, Some(a)), (_, Some(b))) => Some((a, b)),
+                ((_, None), (_, None)) => None,
+                ((inside, Some(a)), (outside, None)) => self
+                    .projection
+                    .project(intersect_near(inside, outside, near))
+                    .map(|b| (a, b)),
+                ((outside, None), (inside, Some(b))) => self
+                    .projection
+                    .project(intersect_near(inside, outside, near))
+                    .map(|a| (a, b)),
+            };
+            if let Some((a, b)) = projected {
+                draw_projected(canvas, a, b, color);
+            }
+        }
+    }
+
+    /// Draw one line between two points in camera space, into both eyes of the canvas.
+    pub fn draw_line(&self, canvas: &Canvas, from: Vec3, to: Vec3, color: u8) {
+        let Some((a, b)) = clip_near(from, to, self.projection.near) else {
+            return;
+        };
+        let (Some(a), Some(b)) = (self.projection.project(a), self.projection.project(b)) else {
+            return;
+        };
+        draw_projected(canvas, a, b, color);
+    }
+}
+
+/// Draw a line between two projected points, into both eyes of the canvas.
+fn draw_projected(canvas: &Canvas, a: Projected, b: Projected, color: u8) {
+    for (target, sign) in [(Target::Left, -1), (Target::Right, 1)] {
+        let from = eye_point(a, sign);
+        let to = eye_point(b, sign);
+        if let Some((from, to)) = clip_to_screen(from, to) {
+            canvas.line(target, from, to, color);
+        }
+    }
+}
+
+/// Cut off the part of an edge which is closer to the camera than `near`.
+fn clip_near(a: Vec3, b: Vec3, near: I16F16) -> Option<(Vec3, Vec3)> {
+    match (a.z >= near, b.z >= near) {
+        (true, true) => Some((a, b)),
+        (false, false) => None,
+        (true, false) => Some((a, intersect_near(a, b, near))),
+        (false, true) => Some((intersect_near(b, a, near), b)),
+    }
+}
+
+/// The point between `inside` and `outside` which lies on the near plane.
+fn intersect_near(inside: Vec3, outside: Vec3, near: I16F16) -> Vec3 {
+    // Saturate like `Projection` does, so edges spanning most of the fixed-point range don't overflow
+    let t = inside
+        .z
+        .saturating_sub(near)
+        .saturating_mul(recip(inside.z.saturating_sub(outside.z)));
+    let lerp =
+        |from: I16F16, to: I16F16| from.saturating_add(to.saturating_sub(from).saturating_mul(t));
+    Vec3::new(lerp(inside.x, outside.x), lerp(inside.y, outside.y), near)
+}
+
+fn eye_point(point: Projected, sign: i32) -> (i32, i32) {
+    (
+        point.x as i32 + sign * point.parallax as i32,
+        point.y as i32,
+    )
+}
+
+const LEFT: u8 = 1;
+const RIGHT: u8 = 2;
+const TOP: u8 = 4;
+const BOTTOM: u8 = 8;
+
+fn outcode((x, y): (i32, i32)) -> u8 {
+    let mut code = 0;
+    if x < 0 {
+        code |= LEFT;
+    } else if x >= WIDTH as i32 {
+        code |= RIGHT;
+    }
+    if y < 0 {
+        code |= TOP;
+    } else if y >= HEIGHT as i32 {
+        code |= BOTTOM;
+    }
+    code
+}
+
+/// Cohen-Sutherland clipping, so that long lines which are mostly off screen are cheap to draw.
+fn clip_to_screen(mut from: (i32, i32), mut to: (i32, i32)) -> Option<((i16, i16), (i16, i16))> {
+    let (max_x, max_y) = (WIDTH as i32 - 1, HEIGHT as i32 - 1);
+    let mut from_code = outcode(from);
+    let mut to_code = outcode(to);
+    loop {
+        if from_code | to_code == 0 {
+            return Some(((from.0 as i16, from.1 as i16), (to.0 as i16, to.1 as i16)));
+        }
+        if from_code & to_code != 0 {
+            return None;
+        }
+        let (code, start, end) = if from_code != 0 {
+            (from_code, to, from)
+        } else {
+            (to_code, from, to)
+        };
+        let (dx, dy) = ((end.0 - start.0) as i64, (end.1 - start.1) as i64);
+        // Move the outside point along the line to the edge it's past.
+        let clipped = if code & TOP != 0 {
+            (start.0 + (dx * -start.1 as i64 / dy) as i32, 0)
+        } else if code & BOTTOM != 0 {
+            (start.0 + (dx * (max_y - start.1) as i64 / dy) as i32, max_y)
+        } else if code & LEFT != 0 {
+            (0, start.1 + (dy * -start.0 as i64 / dx) as i32)
+        } else {
+            (max_x, start.1 + (dy * (max_x - start.0) as i64 / dx) as i32)
+        };
+        if from_code != 0 {
+            from = clipped;
+            from_code = outcode(from);
+        } else {
+            to = clipped;
+            to_code = outcode(to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use vb_rt::sys::mock;
+
+    use super::*;
+
+    /// Both eyes of frame buffer pair 0.
+    fn frame_buffers() -> (Vec<u8>, Vec<u8>) {
+        (mock::bytes(0x00000, 0x6000), mock::bytes(0x10000, 0x6000))
+    }
+
+    #[test]
+    fn meshes_draw_the_same_as_their_edges() {
+        // One edge in front of the camera, one crossing the near plane each way, and one behind it
+        static MESH: Mesh = Mesh {
+            vertices: &[
+                [-40, -20, 100],
+                [40, 30, 150],
+                [10, -10, -50],
+                [-30, 20, -80],
+            ],
+            edges: &[[0, 1], [1, 2], [3, 0], [2, 3]],
+        };
+        let mut scene =
+            Scene::<4>::new(Projection::new(I16F16::from_num(128), I16F16::from_num(8)));
+        scene.camera.position = Vec3::from_ints(0, 0, -20);
+        let transform = Transform::translation(Vec3::from_ints(5, 0, 0));
+        let canvas = Canvas::new(0);
+
+        mock::reset();
+        scene.draw(&canvas, &MESH, &transform, 3);
+        let drawn = frame_buffers();
+        assert!(drawn.0.iter().any(|byte| *byte != 0));
+        assert!(drawn.1.iter().any(|byte| *byte != 0));
+
+        mock::reset();
+        let view = scene.camera.view();
+        let vertex = |index: usize| {
+            let [x, y, z] = MESH.vertices[index];
+            view.apply(transform.apply(Vec3::from_ints(x, y, z)))
+        };
+        for &[a, b] in MESH.edges {
+            scene.draw_line(&canvas, vertex(a as usize), vertex(b as usize), 3);
+        }
+        assert!(drawn == frame_buffers());
+    }
+
+    #[test]
+    fn edges_behind_the_camera_arent_drawn() {
+        static MESH: Mesh = Mesh {
+            vertices: &[[-40, -20, -100], [40, 30, -150]],
+            edges: &[[0, 1]],
+        };
+        let mut scene =
+            Scene::<2>::new(Projection::new(I16F16::from_num(128), I16F16::from_num(8)));
+        mock::reset();
+        scene.draw(&Canvas::new(0), &MESH, &Transform::IDENTITY, 3);
+        let (left, right) = frame_buffers();
+        assert!(left.iter().chain(&right).all(|byte| *byte == 0));
+    }
+
+    /// The color of pixel (x, y) in one eye of frame buffer pair 0.
+    fn pixel(buffer: &[u8], x: usize, y: usize) -> u8 {
+        let byte = buffer[x * 64 + y / 4];
+        (byte >> ((y % 4) * 2)) & 3
+    }
+
+    /// Every lit pixel in one eye, in column order.
+    fn lit_pixels(buffer: &[u8]) -> Vec<(usize, usize)> {
+        (0..WIDTH as usize)
+            .flat_map(|x| (0..HEIGHT as usize).map(move |y| (x, y)))
+            .filter(|&(x, y)| pixel(buffer, x, y) != 0)
+            .collect()
+    }
+
+    #[test]
+    fn each_eye_sees_edges_shifted_by_their_parallax() {
+        // Twice as far away as the screen, so everything is half size with a parallax of 4
+        let scene = Scene::<2>::new(Projection::new(I16F16::from_num(128), I16F16::from_num(16)));
+        mock::reset();
+        scene.draw_line(
+            &Canvas::new(0),
+            Vec3::from_ints(-16, 0, 256),
+            Vec3::from_ints(16, 0, 256),
+            2,
+        );
+        let (left, right) = frame_buffers();
+        // The left eye sees it further left, and the right eye further right
+        let expected_left: Vec<_> = (180..=196).map(|x| (x, 112)).collect();
+        let expected_right: Vec<_> = (188..=204).map(|x| (x, 112)).collect();
+        assert_eq!(lit_pixels(&left), expected_left);
+        assert_eq!(lit_pixels(&right), expected_right);
+        assert_eq!(pixel(&left, 180, 112), 2);
+    }
+
+    #[test]
+    fn edges_popping_out_of_the_screen_cross_over() {
+        // Closer than the screen, so the left eye sees it to the right
+        let scene = Scene::<2>::new(Projection::new(I16F16::from_num(128), I16F16::from_num(16)));
+        mock::reset();
+        scene.draw_line(
+            &Canvas::new(0),
+            Vec3::from_ints(0, -8, 64),
+            Vec3::from_ints(0, 8, 64),
+            1,
+        );
+        let (left, right) = frame_buffers();
+        let expected_left: Vec<_> = (96..=128).map(|y| (200, y)).collect();
+        let expected_right: Vec<_> = (96..=128).map(|y| (184, y)).collect();
+        assert_eq!(lit_pixels(&left), expected_left);
+        assert_eq!(lit_pixels(&right), expected_right);
+    }
+
+    #[test]
+    fn huge_edges_crossing_the_near_plane_dont_overflow() {
+        let near = I16F16::ONE;
+        let point = intersect_near(
+            Vec3::from_ints(100, -50, 20000),
+            Vec3::from_ints(-100, 50, -20000),
+            near,
+        );
+        assert_eq!(point.z, near);
+        assert!((-100..=100).contains(&point.x.to_num::<i32>()));
+        assert!((-50..=50).contains(&point.y.to_num::<i32>()));
+
+        let point = intersect_near(
+            Vec3::from_ints(30000, 30000, 30000),
+            Vec3::from_ints(-30000, -30000, -30000),
+            near,
+        );
+        assert_eq!(point.z, near);
+
+        let scene = Scene::<2>::new(Projection::new(I16F16::from_num(128), I16F16::from_num(16)));
+        mock::reset();
+        scene.draw_line(
+            &Canvas::new(0),
+            Vec3::from_ints(10, 10, 20000),
+            Vec3::from_ints(-10, -10, -20000),
+            3,
+        );
+    }
+}
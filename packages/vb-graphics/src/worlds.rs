@@ -0,0 +1,506 @@
+//! Build the list of worlds each frame, instead of writing `vip::WORLDS` by hand.
+//!
+//! Push worlds from back to front. The first world goes in index 31 and is drawn first,
+//! each world after it is drawn on top, and the END world comes right after the last one.
+//! The list is kept in RAM, and only the worlds which changed since the last flush are written to the VIP.
+//! ```ignore
+//! static FRAME: FrameMonitor = FrameMonitor::new();
+//! let mut worlds = WorldList::new();
+//! loop {
+//!     worlds.clear();
+//!     worlds.push(WorldBuilder::normal(0)).unwrap();
+//!     worlds.push(WorldBuilder::bg_sprite(assets::PLAYER).position(x, y)).unwrap();
+//!     worlds.wait_and_flush(&FRAME);
+//! }
+//! ```
+
+use arrayvec::ArrayVec;
+use vb_rt::sys::vip::{self, World, WorldHeader, WorldMode};
+
+use crate::{BgSprite, FrameMonitor};
+
+/// Worlds can't be wider or taller than this.
+pub const MAX_SIZE: i16 = 0x1000;
+
+/// How many halfwords of world parameter memory there are, starting at `vip::WORLD_PARAMS`.
+const PARAM_MEMORY: u32 = 0xec00;
+
+/// One world, built up from its mode and then adjusted with the other methods.
+/// Worlds are shown to both eyes unless [`WorldBuilder::eyes`] says otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorldBuilder(World);
+
+impl WorldBuilder {
+    /// A plain background world, the size of the screen until [`WorldBuilder::size`] changes it.
+    pub const fn normal(bg_map_base: u8) -> Self {
+        Self::new(WorldMode::Normal, bg_map_base).size(384, 224)
+    }
+
+    /// A background world which shifts each row sideways, with one pair of offsets per row
+    /// starting at `param_base` halfwords into world parameter memory.
+    pub const fn hbias(bg_map_base: u8, param_base: u16) -> Self {
+        Self::new(WorldMode::HBias, bg_map_base)
+            .size(384, 224)
+            .param_base(param_base)
+    }
+
+    /// A background world which is rotated and scaled row by row, with one `AffineElement` per row
+    /// starting at `param_base` halfwords into world parameter memory.
+    pub const fn affine(bg_map_base: u8, param_base: u16) -> Self {
+        Self::new(WorldMode::Affine, bg_map_base)
+            .size(384, 224)
+            .param_base(param_base)
+    }
+
+    /// A world which draws the next group of objects. The first object world pushed draws group 3,
+    /// the next draws group 2, and so on. [`WorldList::push_objects`] says which group it gets.
+    pub const fn object() -> Self {
+        Self::new(WorldMode::Object, 0)
+    }
+
+    /// A normal world showing a sprite which has been loaded into a background map.
+    pub const fn bg_sprite(sprite: BgSprite) -> Self {
+        Self::normal(sprite.bgmap)
+            .source(sprite.x, sprite.y)
+            .size(sprite.width, sprite.height)
+    }
+
+    /// Use a world which has already been filled in.
+    pub const fn from_world(world: World) -> Self {
+        Self(world)
+    }
+
+    pub const fn world(self) -> World {
+        self.0
+    }
+
+    const fn new(mode: WorldMode, bg_map_base: u8) -> Self {
+        let header = WorldHeader::new()
+            .with_lon(true)
+            .with_ron(true)
+            .with_bgm(mode)
+            .with_bg_map_base(bg_map_base);
+        Self(World::new(header))
+    }
+
+    /// Where the top left corner of the world goes on screen.
+    pub const fn position(mut self, gx: i16, gy: i16) -> Self {
+        self.0.gx = gx;
+        self.0.gy = gy;
+        self
+    }
+
+    /// How far the world is moved left for the left eye, and right for the right eye.
+    pub const fn parallax(mut self, gp: i16) -> Self {
+        self.0.gp = gp;
+        self
+    }
+
+    /// Which pixel of the background appears in the top left corner of the world.
+    pub const fn source(mut self, mx: i16, my: i16) -> Self {
+        self.0.mx = mx;
+        self.0.my = my;
+        self
+    }
+
+    /// How far the background is moved within the world, left for the left eye and right for the right eye.
+    pub const fn source_parallax(mut self, mp: i16) -> Self {
+        self.0.mp = mp;
+        self
+    }
+
+    /// The world's size in pixels.
+    pub const fn size(mut self, width: i16, height: i16) -> Self {
+        self.0.w = width - 1;
+        self.0.h = height - 1;
+        self
+    }
+
+    pub const fn eyes(mut self, left: bool, right: bool) -> Self {
+        self.0.header = self.0.header.with_lon(left).with_ron(right);
+        self
+    }
+
+    /// How many background maps make up the background, as powers of 2.
+    /// For example, `bg_maps(1, 0)` is a background two maps wide and one map tall.
+    pub const fn bg_maps(mut self, scx: u8, scy: u8) -> Self {
+        self.0.header = self.0.header.with_scx(scx).with_scy(scy);
+        self
+    }
+
+    /// Instead of repeating the background forever, show this cell outside of it.
+    pub const fn overplane(mut self, cell: u16) -> Self {
+        self.0.header = self.0.header.with_over(true);
+        self.0.overplane_character = cell;
+        self
+    }
+
+    pub const fn param_base(mut self, param_base: u16) -> Self {
+        self.0.param_base = param_base;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorldError {
+    /// There are already 32 worlds in the list.
+    Full,
+    /// There are already 4 object worlds in the list, one for each object group.
+    TooManyObjectWorlds,
+    /// The world's width or height is less than 1 or more than [`MAX_SIZE`].
+    InvalidSize,
+    /// The world's parameters run past the end of world parameter memory.
+    ParamsOutOfRange,
+    /// The world's parameters overlap those of the world at this index.
+    ParamsOverlap(u8),
+}
+
+/// The worlds to draw this frame, and what the VIP was last given.
+pub struct WorldList {
+    worlds: ArrayVec<World, 32>,
+    object_worlds: u8,
+    shadow: [World; 32],
+    /// One bit for each index where `shadow` is known to match the VIP.
+    known: u32,
+}
+
+impl WorldList {
+    pub const fn new() -> Self {
+        Self {
+            worlds: ArrayVec::new_const(),
+            object_worlds: 0,
+            shadow: [World::new(WorldHeader::new()); 32],
+            known: 0,
+        }
+    }
+
+    /// Start a new list. Nothing changes on screen until the next flush.
+    pub fn clear(&mut self) {
+        self.worlds.clear();
+        self.object_worlds = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.worlds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.worlds.is_empty()
+    }
+
+    /// Add a world in front of every world already in the list.
+    /// Returns the index it will be written to.
+    pub fn push(&mut self, world: WorldBuilder) -> Result<u8, WorldError> {
+        let world = world.0;
+        if self.worlds.is_full() {
+            return Err(WorldError::Full);
+        }
+        match world.header.bgm() {
+            WorldMode::Object => {
+                if self.object_worlds == 4 {
+                    return Err(WorldError::TooManyObjectWorlds);
+                }
+                self.object_worlds += 1;
+            }
+            mode => {
+                let valid = 0..MAX_SIZE;
+                if !valid.contains(&world.w) || !valid.contains(&world.h) {
+                    return Err(WorldError::InvalidSize);
+                }
+                if let Some(params) = param_range(mode, &world) {
+                    if params.end > PARAM_MEMORY {
+                        return Err(WorldError::ParamsOutOfRange);
+                    }
+                    if let Some(index) = self.overlapping_params(&params) {
+                        return Err(WorldError::ParamsOverlap(index));
+                    }
+                }
+            }
+        }
+        let index = 31 - self.worlds.len() as u8;
+        self.worlds.push(world);
+        Ok(index)
+    }
+
+    /// Add an object world in front of every world already in the list.
+    /// Returns the object group it draws, for use with [`ObjectPool`](crate::objects::ObjectPool).
+    pub fn push_objects(&mut self) -> Result<usize, WorldError> {
+        self.push(WorldBuilder::object())?;
+        Ok(4 - self.object_worlds as usize)
+    }
+
+    /// Write every world which changed since the last flush, and the END world after them.
+    /// Do this between frames, while the VIP isn't drawing.
+    pub fn flush(&mut self) {
+        for position in 0..self.worlds.len() {
+            self.write(31 - position, self.worlds[position]);
+        }
+        if !self.worlds.is_full() {
+            let end = World::new(WorldHeader::new().with_end(true));
+            self.write(31 - self.worlds.len(), end);
+        }
+    }
+
+    /// Wait for the VIP to finish drawing the current frame, and then flush.
+    pub fn wait_and_flush(&mut self, frame: &FrameMonitor) {
+        frame.wait_for_new_frame();
+        self.flush();
+    }
+
+    /// Forget what the VIP holds, so that the next flush writes every world.
+    /// Use this after writing to `vip::WORLDS` directly.
+    pub fn invalidate(&mut self) {
+        self.known = 0;
+    }
+
+    fn write(&mut self, index: usize, world: World) {
+        let bit = 1 << index;
+        if self.known & bit != 0 && self.shadow[index] == world {
+            return;
+        }
+        vip::WORLDS.index(index).write(world);
+        self.shadow[index] = world;
+        self.known |= bit;
+    }
+
+    fn overlapping_params(&self, params: &core::ops::Range<u32>) -> Option<u8> {
+        self.worlds
+            .iter()
+            .enumerate()
+            .find_map(|(position, other)| {
+                let other = param_range(other.header.bgm(), other)?;
+                let overlaps = params.start < other.end && other.start < params.end;
+                overlaps.then_some(31 - position as u8)
+            })
+    }
+}
+
+impl Default for WorldList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The halfwords of world parameter memory which a world reads, if it reads any.
+fn param_range(mode: WorldMode, world: &World) -> Option<core::ops::Range<u32>> {
+    let per_row = match mode {
+        WorldMode::HBias => 2,
+        WorldMode::Affine => 8,
+        WorldMode::Normal | WorldMode::Object => return None,
+    };
+    let start = world.param_base as u32;
+    Some(start..start + (world.h as u32 + 1) * per_row)
+}
+
+#[cfg(test)]
+mod tests {
+    use vb_rt::sys::mock;
+
+    use super::*;
+
+    const WORLDS: usize = 0x3d800;
+    const WORLD_BYTES: usize = 32;
+
+    fn world_bytes(index: usize) -> [u8; WORLD_BYTES] {
+        let mut bytes = [0; WORLD_BYTES];
+        mock::read_bytes(WORLDS + index * WORLD_BYTES, &mut bytes);
+        bytes
+    }
+
+    fn is_end(index: usize) -> bool {
+        vip::WORLDS.index(index).read().header.end()
+    }
+
+    #[test]
+    fn indices_count_down_from_31() {
+        let mut worlds = WorldList::new();
+        assert_eq!(worlds.push(WorldBuilder::normal(0)), Ok(31));
+        assert_eq!(worlds.push(WorldBuilder::normal(1)), Ok(30));
+        assert_eq!(worlds.push(WorldBuilder::object()), Ok(29));
+        assert_eq!(worlds.len(), 3);
+
+        worlds.clear();
+        assert!(worlds.is_empty());
+        assert_eq!(worlds.push(WorldBuilder::normal(2)), Ok(31));
+    }
+
+    #[test]
+    fn the_list_holds_32_worlds() {
+        let mut worlds = WorldList::new();
+        for index in (0..32).rev() {
+            assert_eq!(worlds.push(WorldBuilder::normal(0)), Ok(index));
+        }
+        assert_eq!(worlds.push(WorldBuilder::normal(0)), Err(WorldError::Full));
+    }
+
+    #[test]
+    fn the_end_world_follows_the_last_world() {
+        mock::reset();
+        let mut worlds = WorldList::new();
+        worlds.push(WorldBuilder::normal(0)).unwrap();
+        worlds
+            .push(WorldBuilder::normal(1).position(10, 20))
+            .unwrap();
+        worlds.flush();
+        assert_eq!(
+            vip::WORLDS.index(31).read(),
+            WorldBuilder::normal(0).world()
+        );
+        assert_eq!(
+            vip::WORLDS.index(30).read(),
+            WorldBuilder::normal(1).position(10, 20).world()
+        );
+        assert!(is_end(29));
+        assert_eq!(world_bytes(28), [0; WORLD_BYTES]);
+
+        // A shorter list moves the END world up
+        worlds.clear();
+        worlds.push(WorldBuilder::normal(0)).unwrap();
+        worlds.flush();
+        assert!(!is_end(31));
+        assert!(is_end(30));
+    }
+
+    #[test]
+    fn full_lists_have_no_end_world() {
+        mock::reset();
+        let mut worlds = WorldList::new();
+        for _ in 0..32 {
+            worlds.push(WorldBuilder::normal(0)).unwrap();
+        }
+        worlds.flush();
+        assert!((0..32).all(|index| !is_end(index)));
+    }
+
+    #[test]
+    fn sizes_must_be_between_1_and_max_size() {
+        let mut worlds = WorldList::new();
+        let invalid = [
+            (0, 224),
+            (384, 0),
+            (MAX_SIZE + 1, 224),
+            (384, MAX_SIZE + 1),
+            (-5, 10),
+        ];
+        for (width, height) in invalid {
+            assert_eq!(
+                worlds.push(WorldBuilder::normal(0).size(width, height)),
+                Err(WorldError::InvalidSize),
+                "{width}x{height}"
+            );
+        }
+        assert_eq!(worlds.push(WorldBuilder::normal(0).size(1, 1)), Ok(31));
+        assert_eq!(
+            worlds.push(WorldBuilder::normal(0).size(MAX_SIZE, MAX_SIZE)),
+            Ok(30)
+        );
+        // Object worlds don't have a size
+        assert_eq!(worlds.push(WorldBuilder::object().size(0, 0)), Ok(29));
+    }
+
+    #[test]
+    fn params_must_fit_in_param_memory() {
+        // 224 rows of 2 halfwords for H-bias, and 8 halfwords for affine
+        let last_hbias = (PARAM_MEMORY - 224 * 2) as u16;
+        let last_affine = (PARAM_MEMORY - 224 * 8) as u16;
+        let mut worlds = WorldList::new();
+        assert_eq!(
+            worlds.push(WorldBuilder::hbias(0, last_hbias + 1)),
+            Err(WorldError::ParamsOutOfRange)
+        );
+        assert_eq!(
+            worlds.push(WorldBuilder::affine(0, last_affine + 1)),
+            Err(WorldError::ParamsOutOfRange)
+        );
+        assert_eq!(worlds.push(WorldBuilder::hbias(0, last_hbias)), Ok(31));
+
+        worlds.clear();
+        assert_eq!(worlds.push(WorldBuilder::affine(0, last_affine)), Ok(31));
+        // Normal worlds don't read any params
+        assert_eq!(
+            worlds.push(WorldBuilder::normal(0).param_base(0xffff)),
+            Ok(30)
+        );
+    }
+
+    #[test]
+    fn overlapping_params_name_the_other_world() {
+        let mut worlds = WorldList::new();
+        worlds.push(WorldBuilder::normal(0).param_base(0)).unwrap();
+        // H-bias reads 0..448, affine reads 1000..2792
+        assert_eq!(worlds.push(WorldBuilder::hbias(0, 0)), Ok(30));
+        assert_eq!(worlds.push(WorldBuilder::affine(0, 1000)), Ok(29));
+
+        assert_eq!(
+            worlds.push(WorldBuilder::hbias(0, 2790)),
+            Err(WorldError::ParamsOverlap(29))
+        );
+        assert_eq!(
+            worlds.push(WorldBuilder::affine(0, 447)),
+            Err(WorldError::ParamsOverlap(30))
+        );
+        assert_eq!(
+            worlds.push(WorldBuilder::hbias(0, 900).size(384, 100)),
+            Err(WorldError::ParamsOverlap(29))
+        );
+        // Touching isn't overlapping
+        assert_eq!(
+            worlds.push(WorldBuilder::hbias(0, 448).size(384, 276)),
+            Ok(28)
+        );
+        assert_eq!(worlds.push(WorldBuilder::hbias(0, 2792)), Ok(27));
+    }
+
+    #[test]
+    fn there_are_four_object_worlds() {
+        let mut worlds = WorldList::new();
+        assert_eq!(worlds.push_objects(), Ok(3));
+        worlds.push(WorldBuilder::normal(0)).unwrap();
+        assert_eq!(worlds.push_objects(), Ok(2));
+        assert_eq!(worlds.push_objects(), Ok(1));
+        assert_eq!(worlds.push_objects(), Ok(0));
+        assert_eq!(worlds.push_objects(), Err(WorldError::TooManyObjectWorlds));
+        assert_eq!(
+            worlds.push(WorldBuilder::object()),
+            Err(WorldError::TooManyObjectWorlds)
+        );
+
+        // Clearing the list frees up the groups again
+        worlds.clear();
+        assert_eq!(worlds.push_objects(), Ok(3));
+    }
+
+    #[test]
+    fn flush_only_writes_worlds_which_changed() {
+        mock::reset();
+        let mut worlds = WorldList::new();
+        worlds.push(WorldBuilder::normal(0)).unwrap();
+        worlds.push(WorldBuilder::normal(1)).unwrap();
+        worlds.flush();
+
+        // Scribble over the VIP's copy, so we can see what the next flush writes
+        let scribble = [0xa5; WORLD_BYTES];
+        for index in 29..32 {
+            mock::write_bytes(WORLDS + index * WORLD_BYTES, &scribble);
+        }
+        worlds.clear();
+        worlds.push(WorldBuilder::normal(0)).unwrap();
+        worlds.push(WorldBuilder::normal(1).position(4, 0)).unwrap();
+        worlds.flush();
+        assert_eq!(world_bytes(31), scribble);
+        assert_eq!(
+            vip::WORLDS.index(30).read(),
+            WorldBuilder::normal(1).position(4, 0).world()
+        );
+        assert_eq!(world_bytes(29), scribble);
+
+        // After invalidating, everything is written again
+        worlds.invalidate();
+        worlds.flush();
+        assert_eq!(
+            vip::WORLDS.index(31).read(),
+            WorldBuilder::normal(0).world()
+        );
+        assert!(is_end(29));
+    }
+}
@@ -0,0 +1,144 @@
+/* Generated by `vb_rt_build::Config`. In the template, placeholders look like @NAME@. */
+
+MEMORY
+{
+  EXPANSION (!r): ORIGIN = 0x04000000, LENGTH = 0
+  RAM (!r): ORIGIN = 0x05000000, LENGTH = 64k
+  SRAM (!r): ORIGIN = 0x06000000, LENGTH = 16384
+  ROM (rx): ORIGIN = 0x07000000, LENGTH = 16M
+}
+
+ENTRY(__handle__vb_rt_reset);
+
+PROVIDE(_vb_rt_game_pad_handler = default_handler);
+PROVIDE(_vb_rt_timer_handler = default_handler);
+PROVIDE(_vb_rt_game_pak_handler = default_handler);
+PROVIDE(_vb_rt_communication_handler = default_handler);
+PROVIDE(_vb_rt_vip_handler = default_handler);
+PROVIDE(_vb_rt_fp_exception_handler = default_handler);
+PROVIDE(_vb_rt_divide_by_zero_handler = default_handler);
+PROVIDE(_vb_rt_illegal_opcode_handler = default_handler);
+PROVIDE(_vb_rt_lo_trap_handler = default_handler);
+PROVIDE(_vb_rt_hi_trap_handler = default_handler);
+PROVIDE(_vb_rt_address_trap_handler = default_handler);
+PROVIDE(_vb_rt_duplexed_exception_handler = default_handler);
+PROVIDE(_vb_rt_stack_overflow_handler = default_stack_overflow_handler);
+
+/* Use the hardware's default wait states unless the game overrides them with `wait_states!` */
+PROVIDE(_vb_rt_wcr = 0);
+
+/* Load the standard column tables unless the game supplies its own with `column_table!` */
+PROVIDE(_vb_rt_column_table = _vb_rt_default_column_table);
+
+/* Don't reserve a heap unless the game asks for one with `heap_size!` or `Config::heap_size` */
+PROVIDE(_vb_rt_heap_size = 0);
+
+SECTIONS
+{
+  /* Code which runs from RAM. Stored at the start of ROM, and copied at reset.
+     This comes before .text so that its patterns take priority. */
+  .ramtext ORIGIN(RAM) : AT(ORIGIN(ROM))
+  {
+    _ramtext_start = .;
+    *(.ramtext)
+    *(.ramtext.*)
+    . = ALIGN(4);
+    _ramtext_end = .;
+  } >RAM
+  _ramtext_lma = LOADADDR(.ramtext);
+
+  .text ORIGIN(ROM) + SIZEOF(.ramtext) :
+  {
+    *(.text)
+    *(.text.*)
+  } >ROM
+
+  .rodata : {
+    *(.rodata)
+    *(.rodata.*)
+  } >ROM
+
+  _data_lma = .;
+  .data : AT(_data_lma)
+  {
+    _data_start = .;
+    *(.data)
+    *(.data.*)
+    *(.sdata)
+    _data_end = .;
+  } >RAM
+
+  .bss :
+  {
+    _bss_start = .;
+    *(.bss)
+    *(.bss.*)
+    *(.sbss)
+    _bss_end = .;
+  } >RAM AT>RAM
+
+  .heap (NOLOAD) : ALIGN(8)
+  {
+    _heap_start = .;
+    . = . + _vb_rt_heap_size;
+    _heap_end = .;
+  } >RAM
+
+  /* The stack grows down from the top of RAM to whatever's left */
+  _stack_bottom = _heap_end;
+  _stack_top = ORIGIN(RAM) + LENGTH(RAM);
+
+  /* Battery-backed SRAM on the cartridge. Never initialized, so it keeps its contents across power cycles.
+     Only the low byte of each halfword is backed, so statics here should be halfword arrays
+     like `[u16; N]`, of which only the low bytes are kept */
+  .sram (NOLOAD) :
+  {
+    _sram_start = .;
+    *(.sram)
+    *(.sram.*)
+    _sram_end = .;
+  } >SRAM
+
+  /* RAM in the cartridge's expansion area, if it has any. Never initialized */
+  .expansion (NOLOAD) :
+  {
+    _expansion_start = .;
+    *(.expansion)
+    *(.expansion.*)
+    _expansion_end = .;
+  } >EXPANSION
+
+  __gp = ORIGIN(RAM) + (LENGTH(RAM) / 2);
+
+  __sections_size = _data_lma + SIZEOF(.data) - ORIGIN(ROM);
+  __rom_size = MAX(1 << LOG2CEIL(__sections_size + 0x220), 0);
+  __rom_header_start = ORIGIN(ROM) + __rom_size - 0x220;
+
+  .rom_header 0x07FFFDE0 : AT(__rom_header_start) {
+    KEEP (*(.rom_header))
+  } >ROM
+
+  .handlers 0x07FFFE00 : AT(__rom_header_start + 0x20) {
+    KEEP (*(.handlers._vb_rt_game_pad_handler))
+    KEEP (*(.handlers._vb_rt_timer_handler))
+    KEEP (*(.handlers._vb_rt_game_pak_handler))
+    KEEP (*(.handlers._vb_rt_communication_handler))
+    KEEP (*(.handlers._vb_rt_vip_handler))
+    . = . + 0x110;
+    KEEP (*(.handlers._vb_rt_fp_exception_handler))
+    . = . + 0x10;
+    KEEP (*(.handlers._vb_rt_divide_by_zero_handler))
+    KEEP (*(.handlers._vb_rt_illegal_opcode_handler))
+    KEEP (*(.handlers._vb_rt_lo_trap_handler))
+    KEEP (*(.handlers._vb_rt_hi_trap_handler))
+    KEEP (*(.handlers._vb_rt_address_trap_handler))
+    KEEP (*(.handlers._vb_rt_duplexed_exception_handler))
+    . = . + 0x10;
+    KEEP (*(.handlers._vb_rt_reset))
+  } >ROM =0x008a008a /* fill unset interrupts with "loop forever" */
+}
+
+/* Fail to link if the game doesn't fit, rather than crashing at runtime */
+ASSERT(_stack_bottom + 0 <= _stack_top,
+  ".data, .ramtext, .bss, the heap and 0 bytes of stack don't fit in 64 KiB of RAM");
+ASSERT(__rom_size <= LENGTH(ROM), "The game doesn't fit in 16 MiB of ROM");
@@ -0,0 +1,65 @@
+//! Turn a linked game into a `.vb` ROM, and report what's using its ROM and RAM.
+//!
+//! ```text
+//! vb-rom <ELF> [-o <ROM>] [--min-size <SIZE>] [--max-size <SIZE>] [--symbols <COUNT>]
+//! ```
+//! Sizes are in bytes, or use a `K` or `M` suffix.
+
+use std::{env, error::Error, path::PathBuf, process::ExitCode};
+
+use vb_rt_build::RomOptions;
+
+const USAGE: &str =
+    "usage: vb-rom <ELF> [-o <ROM>] [--min-size <SIZE>] [--max-size <SIZE>] [--symbols <COUNT>]";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut options = RomOptions::default();
+    let mut input = None;
+    let mut output = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "--min-size" => options.min_size = parse_size(&value()?)?,
+            "--max-size" => options.max_size = parse_size(&value()?)?,
+            "--symbols" => options.largest_symbols = value()?.parse()?,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ if input.is_none() && !arg.starts_with('-') => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {arg}\n{USAGE}").into()),
+        }
+    }
+
+    let input = input.ok_or(USAGE)?;
+    let output = output.unwrap_or_else(|| input.with_extension("vb"));
+    let report = vb_rt_build::write_rom(&input, &output, &options)?;
+    print!("{report}");
+    println!("Wrote {}", output.display());
+    Ok(())
+}
+
+fn parse_size(value: &str) -> Result<u32, Box<dyn Error>> {
+    let (number, multiplier) = match value.as_bytes().last() {
+        Some(b'k' | b'K') => (&value[..value.len() - 1], 1024),
+        Some(b'm' | b'M') => (&value[..value.len() - 1], 1024 * 1024),
+        _ => (value, 1),
+    };
+    let number: u32 = number.parse()?;
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size {value} is too large").into())
+}
@@ -0,0 +1,177 @@
+use std::{env, error::Error, fs, path::PathBuf};
+
+use crate::rom::{RAM_SIZE, ROM_MAX_SIZE};
+
+const TEMPLATE: &str = include_str!("../link.x");
+
+/// Options for the linker script.
+///
+/// ```ignore
+/// vb_rt_build::Config::new()
+///     .stack_size(4096)
+///     .min_rom_size(1024 * 1024)
+///     .init()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    stack_size: u32,
+    heap_size: u32,
+    ramtext_inputs: Vec<String>,
+    sram_size: u32,
+    expansion_size: u32,
+    min_rom_size: u32,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self {
+            stack_size: 0,
+            heap_size: 0,
+            ramtext_inputs: vec![],
+            sram_size: 8192,
+            expansion_size: 0,
+            min_rom_size: 0,
+        }
+    }
+
+    /// Fail to link unless at least this many bytes of RAM are left over for the stack,
+    /// after .data, .ramtext, .bss and the heap.
+    /// The stack still gets all of the RAM left over, this is just a lower bound.
+    pub fn stack_size(mut self, bytes: u32) -> Self {
+        assert!(bytes < RAM_SIZE, "The stack must fit in RAM");
+        self.stack_size = bytes.next_multiple_of(4);
+        self
+    }
+
+    /// Reserve RAM for the heap, like `vb_rt::heap_size!`. If the game also uses that macro, the macro wins.
+    pub fn heap_size(mut self, bytes: u32) -> Self {
+        assert!(bytes < RAM_SIZE, "The heap must fit in RAM");
+        self.heap_size = bytes.next_multiple_of(8);
+        self
+    }
+
+    /// Also copy these input sections into RAM at reset, and run them from there.
+    /// Anything in `.ramtext` or `.ramtext.*` is always copied, which is where `vb_rt::ram_fn!` puts functions.
+    /// Patterns use linker script syntax, like `*(.text.hot_loop*)` or `*libmy_game*:*(.text.*)`.
+    pub fn ramtext(mut self, pattern: impl Into<String>) -> Self {
+        self.ramtext_inputs.push(pattern.into());
+        self
+    }
+
+    /// The number of bytes of battery-backed SRAM on the cartridge. Defaults to 8 KiB.
+    /// Only the low byte of each 16-bit word is backed by SRAM, so `N` bytes take up `2 * N` bytes of address space.
+    /// Statics in the `.sram` section live there, but each byte needs a whole halfword,
+    /// e.g. `#[unsafe(link_section = ".sram")] static mut SAVE: [u16; N]` keeps only the low byte of each element.
+    /// `vb_rt::sys::sram` and `vb_rt::save` handle the stride for you.
+    pub fn sram_size(mut self, bytes: u32) -> Self {
+        assert!(
+            bytes <= 8 * 1024 * 1024,
+            "SRAM must fit in 16 MiB of address space"
+        );
+        self.sram_size = bytes;
+        self
+    }
+
+    /// The number of bytes of RAM in the cartridge's expansion area. Defaults to none.
+    /// Statics in the `.expansion` section live there.
+    pub fn expansion_size(mut self, bytes: u32) -> Self {
+        assert!(
+            bytes <= 16 * 1024 * 1024,
+            "Expansion RAM must fit in 16 MiB"
+        );
+        self.expansion_size = bytes;
+        self
+    }
+
+    /// Make the ROM at least this many bytes, even if the game is smaller.
+    /// Must be a power of two, at most 16 MiB.
+    pub fn min_rom_size(mut self, bytes: u32) -> Self {
+        assert!(
+            bytes.is_power_of_two() && bytes <= ROM_MAX_SIZE,
+            "The ROM size must be a power of two, at most 16 MiB"
+        );
+        self.min_rom_size = bytes;
+        self
+    }
+
+    /// Generate the linker script.
+    pub fn linker_script(&self) -> String {
+        let ramtext_inputs: String = self
+            .ramtext_inputs
+            .iter()
+            .map(|pattern| format!("    {pattern}\n"))
+            .collect();
+        TEMPLATE
+            .replace("@EXPANSION_SIZE@", &self.expansion_size.to_string())
+            .replace("@SRAM_SIZE@", &(self.sram_size * 2).to_string())
+            .replace("@HEAP_SIZE@", &self.heap_size.to_string())
+            .replace("@RAMTEXT_INPUTS@\n", &ramtext_inputs)
+            .replace("@STACK_SIZE@", &self.stack_size.to_string())
+            .replace("@MIN_ROM_SIZE@", &self.min_rom_size.to_string())
+    }
+
+    /// Write the linker script to the build directory, and tell cargo to link with it.
+    /// Call this from your `build.rs` file.
+    pub fn init(&self) -> Result<(), Box<dyn Error>> {
+        // build directory for this crate
+        let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+
+        // put `link.x` in the build directory
+        let link_file = out_dir.join("link.x");
+        fs::write(&link_file, self.linker_script())?;
+
+        // Use `link.x` as a linker script
+        println!("cargo:rustc-link-arg=-T{}", link_file.display());
+
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// Compare against the checked-in script. Run with `UPDATE_SNAPSHOTS=1` to rewrite it instead.
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("snapshots")
+            .join(name);
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            expected == actual,
+            "{} doesn't match. If the change is intended, rerun with UPDATE_SNAPSHOTS=1.\n{actual}",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn default_linker_script() {
+        assert_snapshot("default.x", &Config::default().linker_script());
+    }
+
+    #[test]
+    fn customized_linker_script() {
+        let config = Config::new()
+            .stack_size(2047)
+            .heap_size(1001)
+            .ramtext("*(.text.hot_loop*)")
+            .ramtext("*libmy_game*:*(.text.*)")
+            .sram_size(2048)
+            .expansion_size(4096)
+            .min_rom_size(1024 * 1024);
+        assert_snapshot("customized.x", &config.linker_script());
+    }
+}
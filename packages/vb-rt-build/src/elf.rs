@@ -0,0 +1,573 @@
+//! Just enough of an ELF reader to turn a linked game into a ROM.
+//! Only 32-bit little-endian files are supported, since that's all the V810 toolchain emits.
+
+use crate::rom::RomError;
+
+/// `e_machine` for the NEC V810.
+pub const EM_V810: u16 = 36;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u32 = 0x2;
+const PT_LOAD: u32 = 1;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    /// The address the section runs from.
+    pub address: u32,
+    /// The address the section is stored at. Differs from `address` for sections like `.data`,
+    /// which live in ROM but get copied to RAM at reset.
+    pub load_address: u32,
+    pub size: u32,
+    /// True if the section takes up space in memory while the game runs.
+    pub allocated: bool,
+    /// True if the section has bytes in the file. False for sections like `.bss`.
+    pub has_contents: bool,
+    offset: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Object,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub value: u32,
+    pub size: u32,
+    pub kind: SymbolKind,
+    /// Index into [`Elf::sections`], if the symbol belongs to a section.
+    pub section: Option<usize>,
+}
+
+pub struct Elf<'a> {
+    data: &'a [u8],
+    pub machine: u16,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+}
+
+struct Segment {
+    kind: u32,
+    offset: u32,
+    address: u32,
+    load_address: u32,
+    file_size: u32,
+    memory_size: u32,
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, RomError> {
+        if data.get(0..4) != Some(b"\x7fELF") {
+            return Err(invalid("not an ELF file"));
+        }
+        if data.get(4..6) != Some(&[1, 1]) {
+            return Err(invalid("only 32-bit little-endian ELF files are supported"));
+        }
+        let machine = read_u16(data, 18)?;
+        let program_offset = read_u32(data, 28)? as usize;
+        let section_offset = read_u32(data, 32)? as usize;
+        let program_entry_size = read_u16(data, 42)? as usize;
+        let program_count = read_u16(data, 44)? as usize;
+        let section_entry_size = read_u16(data, 46)? as usize;
+        let section_count = read_u16(data, 48)? as usize;
+        let names_index = read_u16(data, 50)? as usize;
+
+        let mut segments = vec![];
+        for index in 0..program_count {
+            let base = program_offset + index * program_entry_size;
+            segments.push(Segment {
+                kind: read_u32(data, base)?,
+                offset: read_u32(data, base + 4)?,
+                address: read_u32(data, base + 8)?,
+                load_address: read_u32(data, base + 12)?,
+                file_size: read_u32(data, base + 16)?,
+                memory_size: read_u32(data, base + 20)?,
+            });
+        }
+
+        struct RawSection {
+            name: u32,
+            kind: u32,
+            flags: u32,
+            address: u32,
+            offset: u32,
+            size: u32,
+            link: u32,
+        }
+        let mut raw_sections = vec![];
+        for index in 0..section_count {
+            let base = section_offset + index * section_entry_size;
+            raw_sections.push(RawSection {
+                name: read_u32(data, base)?,
+                kind: read_u32(data, base + 4)?,
+                flags: read_u32(data, base + 8)?,
+                address: read_u32(data, base + 12)?,
+                offset: read_u32(data, base + 16)?,
+                size: read_u32(data, base + 20)?,
+                link: read_u32(data, base + 24)?,
+            });
+        }
+        let file_range = |raw: &RawSection| -> Result<&'a [u8], RomError> {
+            let start = raw.offset as usize;
+            data.get(start..start + raw.size as usize)
+                .ok_or_else(|| invalid("section extends past the end of the file"))
+        };
+
+        let names = match raw_sections.get(names_index) {
+            Some(raw) => file_range(raw)?,
+            None => &[],
+        };
+        let mut sections = vec![];
+        for raw in &raw_sections {
+            let has_contents = raw.kind != SHT_NOBITS;
+            if has_contents {
+                file_range(raw)?;
+            }
+            let allocated = raw.flags & SHF_ALLOC != 0;
+            let load_address = if allocated {
+                segments
+                    .iter()
+                    .find(|segment| {
+                        segment.kind == PT_LOAD
+                            && contains(segment.address, segment.memory_size, raw.address, raw.size)
+                            && (!has_contents
+                                || contains(
+                                    segment.offset,
+                                    segment.file_size,
+                                    raw.offset,
+                                    raw.size,
+                                ))
+                    })
+                    .map_or(raw.address, |segment| {
+                        segment.load_address + (raw.address - segment.address)
+                    })
+            } else {
+                raw.address
+            };
+            sections.push(Section {
+                name: read_str(names, raw.name)?,
+                address: raw.address,
+                load_address,
+                size: raw.size,
+                allocated,
+                has_contents,
+                offset: raw.offset,
+            });
+        }
+
+        let mut symbols = vec![];
+        for raw in raw_sections.iter().filter(|raw| raw.kind == SHT_SYMTAB) {
+            let table = file_range(raw)?;
+            let strings = match raw_sections.get(raw.link as usize) {
+                Some(strings) => file_range(strings)?,
+                None => &[],
+            };
+            // the first entry is always the null symbol
+            for entry in table.chunks_exact(16).skip(1) {
+                let info = entry[12];
+                let section = u16::from_le_bytes([entry[14], entry[15]]) as usize;
+                symbols.push(Symbol {
+                    name: read_str(strings, read_u32(entry, 0)?)?,
+                    value: read_u32(entry, 4)?,
+                    size: read_u32(entry, 8)?,
+                    kind: match info & 0xf {
+                        STT_FUNC => SymbolKind::Function,
+                        STT_OBJECT => SymbolKind::Object,
+                        _ => SymbolKind::Other,
+                    },
+                    // 0 is "undefined", and anything from 0xff00 up is reserved
+                    section: (section != 0 && section < 0xff00).then_some(section),
+                });
+            }
+        }
+
+        Ok(Self {
+            data,
+            machine,
+            sections,
+            symbols,
+        })
+    }
+
+    /// The bytes of a section, as stored in the file. Empty for sections without contents.
+    pub fn section_data(&self, section: &Section) -> &'a [u8] {
+        if !section.has_contents {
+            return &[];
+        }
+        let start = section.offset as usize;
+        // parse already checked that every section fits in the file
+        &self.data[start..start + section.size as usize]
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+}
+
+/// True if `start..start + size` lies inside `outer_start..outer_start + outer_size`.
+fn contains(outer_start: u32, outer_size: u32, start: u32, size: u32) -> bool {
+    let outer_end = outer_start as u64 + outer_size as u64;
+    start >= outer_start && start as u64 + size as u64 <= outer_end
+}
+
+fn invalid(message: &str) -> RomError {
+    RomError::InvalidElf(message.to_string())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, RomError> {
+    match data.get(offset..offset + 2) {
+        Some(bytes) => Ok(u16::from_le_bytes([bytes[0], bytes[1]])),
+        None => Err(invalid("unexpected end of file")),
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, RomError> {
+    match data.get(offset..offset + 4) {
+        Some(bytes) => Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(invalid("unexpected end of file")),
+    }
+}
+
+fn read_str(table: &[u8], offset: u32) -> Result<String, RomError> {
+    let rest = table
+        .get(offset as usize..)
+        .ok_or_else(|| invalid("string offset out of range"))?;
+    let len = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+    Ok(String::from_utf8_lossy(&rest[..len]).into_owned())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const SHT_PROGBITS: u32 = 1;
+    const SHT_STRTAB: u32 = 3;
+    const SHF_WRITE: u32 = 0x1;
+
+    struct TestSection {
+        name: &'static str,
+        address: u32,
+        load_address: u32,
+        data: Vec<u8>,
+        bss_size: Option<u32>,
+    }
+
+    struct TestSymbol {
+        name: &'static str,
+        value: u32,
+        size: u32,
+        kind: u8,
+        section: Option<&'static str>,
+    }
+
+    /// Writes just enough of an ELF file for [`Elf::parse`] to read.
+    pub(crate) struct ElfBuilder {
+        machine: u16,
+        sections: Vec<TestSection>,
+        symbols: Vec<TestSymbol>,
+    }
+
+    impl ElfBuilder {
+        pub(crate) fn new() -> Self {
+            Self {
+                machine: EM_V810,
+                sections: vec![],
+                symbols: vec![],
+            }
+        }
+
+        pub(crate) fn machine(mut self, machine: u16) -> Self {
+            self.machine = machine;
+            self
+        }
+
+        /// A section stored and run at `address`.
+        pub(crate) fn section(self, name: &'static str, address: u32, data: &[u8]) -> Self {
+            self.loaded_section(name, address, address, data)
+        }
+
+        /// A section stored at `load_address`, which runs from `address`.
+        pub(crate) fn loaded_section(
+            mut self,
+            name: &'static str,
+            address: u32,
+            load_address: u32,
+            data: &[u8],
+        ) -> Self {
+            self.sections.push(TestSection {
+                name,
+                address,
+                load_address,
+                data: data.to_vec(),
+                bss_size: None,
+            });
+            self
+        }
+
+        /// A section which takes up memory but not space in the file.
+        pub(crate) fn bss(mut self, name: &'static str, address: u32, size: u32) -> Self {
+            self.sections.push(TestSection {
+                name,
+                address,
+                load_address: address,
+                data: vec![],
+                bss_size: Some(size),
+            });
+            self
+        }
+
+        /// A symbol with no size, type or section, like the ones `link.x` defines.
+        pub(crate) fn absolute(self, name: &'static str, value: u32) -> Self {
+            self.symbol(name, value, 0, 0, None)
+        }
+
+        pub(crate) fn symbol(
+            mut self,
+            name: &'static str,
+            value: u32,
+            size: u32,
+            kind: u8,
+            section: Option<&'static str>,
+        ) -> Self {
+            self.symbols.push(TestSymbol {
+                name,
+                value,
+                size,
+                kind,
+                section,
+            });
+            self
+        }
+
+        pub(crate) fn build(&self) -> Vec<u8> {
+            let mut out = vec![0u8; 52];
+            let mut strings = Strings::default();
+            let mut section_names = Strings::default();
+
+            // Sections which are stored somewhere other than where they run need a segment to say so
+            let loaded: Vec<&TestSection> = self
+                .sections
+                .iter()
+                .filter(|section| section.load_address != section.address)
+                .collect();
+            let program_offset = out.len();
+            out.resize(out.len() + 32 * loaded.len(), 0);
+
+            // index, type, flags, address, offset, size, link
+            let mut headers = vec![(0, 0, 0, 0, 0, 0, 0)];
+            let mut data_offsets = vec![];
+            for section in &self.sections {
+                let offset = out.len() as u32;
+                data_offsets.push(offset);
+                out.extend_from_slice(&section.data);
+                let (kind, size) = match section.bss_size {
+                    Some(size) => (SHT_NOBITS, size),
+                    None => (SHT_PROGBITS, section.data.len() as u32),
+                };
+                headers.push((
+                    section_names.add(section.name),
+                    kind,
+                    SHF_ALLOC | SHF_WRITE,
+                    section.address,
+                    offset,
+                    size,
+                    0,
+                ));
+            }
+            for (index, section) in loaded.iter().enumerate() {
+                let position = self
+                    .sections
+                    .iter()
+                    .position(|other| core::ptr::eq(other, *section))
+                    .unwrap();
+                let header = program_offset + index * 32;
+                let size = section.data.len() as u32;
+                put_u32(&mut out, header, PT_LOAD);
+                put_u32(&mut out, header + 4, data_offsets[position]);
+                put_u32(&mut out, header + 8, section.address);
+                put_u32(&mut out, header + 12, section.load_address);
+                put_u32(&mut out, header + 16, size);
+                put_u32(&mut out, header + 20, section.bss_size.unwrap_or(size));
+            }
+
+            let mut symbols = vec![0u8; 16];
+            for symbol in &self.symbols {
+                let section = symbol.section.map_or(0xfff1, |name| {
+                    1 + self
+                        .sections
+                        .iter()
+                        .position(|section| section.name == name)
+                        .unwrap() as u16
+                });
+                symbols.extend_from_slice(&strings.add(symbol.name).to_le_bytes());
+                symbols.extend_from_slice(&symbol.value.to_le_bytes());
+                symbols.extend_from_slice(&symbol.size.to_le_bytes());
+                symbols.extend_from_slice(&[symbol.kind, 0]);
+                symbols.extend_from_slice(&section.to_le_bytes());
+            }
+            let symtab_index = headers.len() as u32;
+            let symtab_name = section_names.add(".symtab");
+            let strtab_name = section_names.add(".strtab");
+            let shstrtab_name = section_names.add(".shstrtab");
+            for (name, kind, data, link) in [
+                (symtab_name, SHT_SYMTAB, &symbols, symtab_index + 1),
+                (strtab_name, SHT_STRTAB, &strings.0, 0),
+                (shstrtab_name, SHT_STRTAB, &section_names.0, 0),
+            ] {
+                headers.push((name, kind, 0, 0, out.len() as u32, data.len() as u32, link));
+                out.extend_from_slice(data);
+            }
+
+            let section_offset = out.len();
+            for (name, kind, flags, address, offset, size, link) in &headers {
+                for value in [
+                    *name, *kind, *flags, *address, *offset, *size, *link, 0, 0, 0,
+                ] {
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+
+            out[0..7].copy_from_slice(b"\x7fELF\x01\x01\x01");
+            put_u16(&mut out, 16, 2);
+            put_u16(&mut out, 18, self.machine);
+            put_u32(&mut out, 20, 1);
+            put_u32(&mut out, 28, program_offset as u32);
+            put_u32(&mut out, 32, section_offset as u32);
+            put_u16(&mut out, 40, 52);
+            put_u16(&mut out, 42, 32);
+            put_u16(&mut out, 44, loaded.len() as u16);
+            put_u16(&mut out, 46, 40);
+            put_u16(&mut out, 48, headers.len() as u16);
+            put_u16(&mut out, 50, headers.len() as u16 - 1);
+            out
+        }
+    }
+
+    /// A string table, which always starts with an empty string.
+    struct Strings(Vec<u8>);
+
+    impl Default for Strings {
+        fn default() -> Self {
+            Self(vec![0])
+        }
+    }
+
+    impl Strings {
+        fn add(&mut self, string: &str) -> u32 {
+            let offset = self.0.len() as u32;
+            self.0.extend_from_slice(string.as_bytes());
+            self.0.push(0);
+            offset
+        }
+    }
+
+    fn put_u16(out: &mut [u8], offset: usize, value: u16) {
+        out[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u32(out: &mut [u8], offset: usize, value: u32) {
+        out[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn minimal() -> Vec<u8> {
+        ElfBuilder::new()
+            .section(".text", 0x0700_0000, &[1, 2, 3, 4])
+            .loaded_section(".data", 0x0500_0000, 0x0700_0004, &[5, 6])
+            .bss(".bss", 0x0500_0004, 12)
+            .symbol("main", 0x0700_0000, 4, STT_FUNC, Some(".text"))
+            .symbol("COUNTER", 0x0500_0004, 4, STT_OBJECT, Some(".bss"))
+            .absolute("__rom_size", 0x10_0000)
+            .build()
+    }
+
+    #[test]
+    fn a_minimal_elf_parses() {
+        let data = minimal();
+        let elf = Elf::parse(&data).unwrap();
+        assert_eq!(elf.machine, EM_V810);
+
+        let text = elf.section(".text").unwrap();
+        assert_eq!(
+            (text.address, text.load_address, text.size),
+            (0x0700_0000, 0x0700_0000, 4)
+        );
+        assert!(text.allocated && text.has_contents);
+        assert_eq!(elf.section_data(text), &[1, 2, 3, 4]);
+
+        let data_section = elf.section(".data").unwrap();
+        assert_eq!(data_section.address, 0x0500_0000);
+        assert_eq!(data_section.load_address, 0x0700_0004);
+        assert_eq!(elf.section_data(data_section), &[5, 6]);
+
+        let bss = elf.section(".bss").unwrap();
+        assert_eq!(bss.size, 12);
+        assert!(bss.allocated && !bss.has_contents);
+        assert_eq!(elf.section_data(bss), &[] as &[u8]);
+
+        let symtab = elf.section(".symtab").unwrap();
+        assert!(!symtab.allocated);
+    }
+
+    #[test]
+    fn symbols_are_read_with_their_kinds_and_sections() {
+        let data = minimal();
+        let elf = Elf::parse(&data).unwrap();
+        let main = elf.symbol("main").unwrap();
+        assert_eq!(main.kind, SymbolKind::Function);
+        assert_eq!(elf.sections[main.section.unwrap()].name, ".text");
+
+        let counter = elf.symbol("COUNTER").unwrap();
+        assert_eq!((counter.kind, counter.size), (SymbolKind::Object, 4));
+
+        let rom_size = elf.symbol("__rom_size").unwrap();
+        assert_eq!(rom_size.value, 0x10_0000);
+        assert_eq!(rom_size.kind, SymbolKind::Other);
+        assert_eq!(rom_size.section, None);
+
+        assert!(elf.symbol("missing").is_none());
+    }
+
+    #[test]
+    fn truncated_elfs_are_errors_not_panics() {
+        let data = minimal();
+        // The file ends with the last section header, whose last three fields are never read
+        for len in 0..data.len() - 12 {
+            assert!(
+                matches!(Elf::parse(&data[..len]), Err(RomError::InvalidElf(_))),
+                "truncated to {len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn only_32_bit_little_endian_elfs_are_supported() {
+        let mut data = minimal();
+        data[4] = 2;
+        assert_eq!(
+            Elf::parse(&data).err(),
+            Some(invalid("only 32-bit little-endian ELF files are supported"))
+        );
+        data[4] = 1;
+        data[5] = 2;
+        assert!(Elf::parse(&data).is_err());
+    }
+
+    #[test]
+    fn other_files_arent_elfs() {
+        assert_eq!(
+            Elf::parse(b"VB ROM").err(),
+            Some(invalid("not an ELF file"))
+        );
+    }
+}
@@ -1,15 +1,16 @@
-use std::{env, error::Error, fs::File, io::Write as _, path::PathBuf};
+use std::error::Error;
 
-pub fn init() -> Result<(), Box<dyn Error>> {
-    // build directory for this crate
-    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
-
-    // put `link.x` in the build directory
-    let link_file = out_dir.join("link.x");
-    File::create(&link_file)?.write_all(include_bytes!("../link.x"))?;
+mod config;
+pub mod elf;
+mod rom;
 
-    // Use `link.x`` as a linker script
-    println!("cargo:rustc-link-arg=-T{}", link_file.display());
+pub use config::Config;
+pub use rom::{
+    HEADER_SIZE, RAM_ORIGIN, RAM_SIZE, ROM_MAX_SIZE, ROM_ORIGIN, Report, Rom, RomError, RomOptions,
+    SectionUsage, SymbolUsage, build_rom, write_rom,
+};
 
-    Ok(())
+/// Link with the default [`Config`].
+pub fn init() -> Result<(), Box<dyn Error>> {
+    Config::new().init()
 }
@@ -0,0 +1,609 @@
+//! Turn a linked game into a `.vb` ROM image.
+//!
+//! The Virtual Boy mirrors the cartridge ROM across its whole address space,
+//! and starts executing from the very top of it. So a ROM must be a power of two in size,
+//! with the header and interrupt vectors in its last 0x220 bytes.
+//! `link.x` lays the game out that way, and [`build_rom`] checks that it worked.
+
+use std::{error::Error, fmt, fs, path::Path};
+
+use crate::elf::{EM_V810, Elf, Section, SymbolKind};
+
+pub const ROM_ORIGIN: u32 = 0x0700_0000;
+/// The most ROM the address space can hold.
+pub const ROM_MAX_SIZE: u32 = 16 * 1024 * 1024;
+pub const RAM_ORIGIN: u32 = 0x0500_0000;
+pub const RAM_SIZE: u32 = 64 * 1024;
+/// The ROM header plus the interrupt vector table.
+pub const HEADER_SIZE: u32 = 0x220;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomError {
+    /// The input couldn't be parsed.
+    InvalidElf(String),
+    /// The input was built for a CPU other than the V810.
+    WrongMachine(u16),
+    /// A symbol which `link.x` defines is missing.
+    MissingSymbol(&'static str),
+    /// `__rom_size` isn't a power of two which fits in the address space.
+    InvalidRomSize(u32),
+    /// The header or vector table isn't at the top of the ROM.
+    HeaderMisplaced {
+        section: String,
+        expected: u32,
+        actual: u32,
+    },
+    /// A section doesn't fit in the memory it belongs to.
+    SectionOverflow {
+        section: String,
+        region: &'static str,
+        start: u32,
+        end: u32,
+        region_start: u32,
+        region_end: u32,
+    },
+    /// Two sections would be stored at the same place in ROM.
+    SectionOverlap { first: String, second: String },
+    /// The image is larger than [`RomOptions::max_size`].
+    RomTooLarge { size: u32, max_size: u32 },
+    /// [`RomOptions::min_size`] has no power of two at or above it which fits in 32 bits.
+    InvalidMinSize(u32),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidElf(message) => write!(f, "invalid ELF file: {message}"),
+            Self::WrongMachine(machine) => write!(
+                f,
+                "expected an ELF file for the V810 (machine {EM_V810}), but found machine {machine}"
+            ),
+            Self::MissingSymbol(symbol) => write!(
+                f,
+                "symbol {symbol} is missing. Was the game linked with vb-rt-build's linker script?"
+            ),
+            Self::InvalidRomSize(size) => write!(
+                f,
+                "ROM size {size:#x} must be a power of two between {HEADER_SIZE:#x} and {ROM_MAX_SIZE:#x}"
+            ),
+            Self::HeaderMisplaced {
+                section,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{section} should be stored at {expected:#010x}, but was stored at {actual:#010x}"
+            ),
+            Self::SectionOverflow {
+                section,
+                region,
+                start,
+                end,
+                region_start,
+                region_end,
+            } => write!(
+                f,
+                "section {section} ({start:#010x}..{end:#010x}) overflows {region} ({region_start:#010x}..{region_end:#010x}) by {} bytes",
+                overflow(*start, *end, *region_start, *region_end)
+            ),
+            Self::SectionOverlap { first, second } => {
+                write!(f, "sections {first} and {second} overlap in ROM")
+            }
+            Self::RomTooLarge { size, max_size } => write!(
+                f,
+                "ROM is {size} bytes, but must be at most {max_size} bytes"
+            ),
+            Self::InvalidMinSize(size) => write!(
+                f,
+                "minimum ROM size {size:#x} can't be rounded up to a power of two"
+            ),
+        }
+    }
+}
+
+impl Error for RomError {}
+
+fn overflow(start: u32, end: u32, region_start: u32, region_end: u32) -> u32 {
+    region_start
+        .saturating_sub(start)
+        .max(end.saturating_sub(region_end))
+}
+
+#[derive(Debug, Clone)]
+pub struct RomOptions {
+    /// Pad the image to at least this many bytes, by mirroring it.
+    /// Rounded up to a power of two.
+    pub min_size: u32,
+    /// Fail if the image would be larger than this.
+    pub max_size: u32,
+    /// The value of any bytes which the game doesn't use.
+    pub fill: u8,
+    /// How many of the largest symbols to include in the report.
+    pub largest_symbols: usize,
+}
+
+impl Default for RomOptions {
+    fn default() -> Self {
+        Self {
+            min_size: 0,
+            max_size: ROM_MAX_SIZE,
+            fill: 0xff,
+            largest_symbols: 10,
+        }
+    }
+}
+
+/// How much space one section takes up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionUsage {
+    pub name: String,
+    pub address: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolUsage {
+    pub name: String,
+    pub section: String,
+    pub size: u32,
+}
+
+/// What's using the game's ROM and RAM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The size of the ROM before mirroring, from `__rom_size`.
+    pub rom_size: u32,
+    /// The size of the final image.
+    pub image_size: u32,
+    /// Sections stored in ROM, including the header and vectors.
+    pub rom: Vec<SectionUsage>,
+    /// Sections which live in RAM while the game runs.
+    pub ram: Vec<SectionUsage>,
+    /// The RAM left over for the stack.
+    pub stack_size: u32,
+    pub largest_symbols: Vec<SymbolUsage>,
+}
+
+impl Report {
+    pub fn rom_used(&self) -> u32 {
+        self.rom.iter().map(|section| section.size).sum()
+    }
+
+    pub fn ram_used(&self) -> u32 {
+        self.ram.iter().map(|section| section.size).sum()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rom_used = self.rom_used();
+        writeln!(
+            f,
+            "ROM: {rom_used} of {} bytes used ({:.1}%), image is {} bytes",
+            self.rom_size,
+            percent(rom_used, self.rom_size),
+            self.image_size
+        )?;
+        for section in &self.rom {
+            write_section(f, section)?;
+        }
+
+        let ram_used = self.ram_used();
+        writeln!(
+            f,
+            "RAM: {ram_used} of {RAM_SIZE} bytes used ({:.1}%), {} bytes left for the stack",
+            percent(ram_used, RAM_SIZE),
+            self.stack_size
+        )?;
+        for section in &self.ram {
+            write_section(f, section)?;
+        }
+
+        if !self.largest_symbols.is_empty() {
+            writeln!(f, "Largest symbols:")?;
+            for symbol in &self.largest_symbols {
+                writeln!(
+                    f,
+                    "  {:>8}  {:<12} {}",
+                    symbol.size, symbol.section, symbol.name
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_section(f: &mut fmt::Formatter<'_>, section: &SectionUsage) -> fmt::Result {
+    writeln!(
+        f,
+        "  {:<12} {:#010x} {:>8}",
+        section.name, section.address, section.size
+    )
+}
+
+fn percent(used: u32, total: u32) -> f64 {
+    used as f64 * 100.0 / total as f64
+}
+
+pub struct Rom {
+    pub image: Vec<u8>,
+    pub report: Report,
+}
+
+/// Build a `.vb` image from the bytes of a linked ELF file.
+pub fn build_rom(elf: &[u8], options: &RomOptions) -> Result<Rom, RomError> {
+    let elf = Elf::parse(elf)?;
+    if elf.machine != EM_V810 {
+        return Err(RomError::WrongMachine(elf.machine));
+    }
+
+    let rom_size = required_symbol(&elf, "__rom_size")?;
+    if !rom_size.is_power_of_two() || !(HEADER_SIZE..=ROM_MAX_SIZE).contains(&rom_size) {
+        return Err(RomError::InvalidRomSize(rom_size));
+    }
+    let rom_end = ROM_ORIGIN + rom_size;
+    let header_start = required_symbol(&elf, "__rom_header_start")?;
+    check_placement("__rom_header_start", rom_end - HEADER_SIZE, header_start)?;
+    if let Some(header) = elf.section(".rom_header") {
+        check_placement(".rom_header", rom_end - HEADER_SIZE, header.load_address)?;
+    }
+    if let Some(handlers) = elf.section(".handlers") {
+        check_placement(
+            ".handlers",
+            rom_end - HEADER_SIZE + 0x20,
+            handlers.load_address,
+        )?;
+    }
+
+    // Copy every section with contents into its place in ROM
+    let mut image = vec![options.fill; rom_size as usize];
+    let mut stored: Vec<&Section> = elf
+        .sections
+        .iter()
+        .filter(|section| section.allocated && section.has_contents && section.size > 0)
+        .collect();
+    stored.sort_by_key(|section| section.load_address);
+    for section in &stored {
+        let start = section.load_address;
+        let end = start.saturating_add(section.size);
+        if start < ROM_ORIGIN || end > rom_end {
+            return Err(RomError::SectionOverflow {
+                section: section.name.clone(),
+                region: "ROM",
+                start,
+                end,
+                region_start: ROM_ORIGIN,
+                region_end: rom_end,
+            });
+        }
+        let offset = (start - ROM_ORIGIN) as usize;
+        image[offset..offset + section.size as usize].copy_from_slice(elf.section_data(section));
+    }
+    for pair in stored.windows(2) {
+        if pair[0].load_address + pair[0].size > pair[1].load_address {
+            return Err(RomError::SectionOverlap {
+                first: pair[0].name.clone(),
+                second: pair[1].name.clone(),
+            });
+        }
+    }
+
+    // Everything in RAM has to leave room for the stack
+    let ram_end = RAM_ORIGIN + RAM_SIZE;
+    let mut in_ram: Vec<&Section> = elf
+        .sections
+        .iter()
+        .filter(|section| {
+            section.allocated
+                && section.size > 0
+                && (RAM_ORIGIN..ram_end).contains(&section.address)
+        })
+        .collect();
+    in_ram.sort_by_key(|section| section.address);
+    let mut ram_top = RAM_ORIGIN;
+    for section in &in_ram {
+        let end = section.address.saturating_add(section.size);
+        if end > ram_end {
+            return Err(RomError::SectionOverflow {
+                section: section.name.clone(),
+                region: "RAM",
+                start: section.address,
+                end,
+                region_start: RAM_ORIGIN,
+                region_end: ram_end,
+            });
+        }
+        ram_top = ram_top.max(end);
+    }
+
+    // The hardware mirrors the ROM anyway, so padding it out is just repetition
+    let min_size = options
+        .min_size
+        .checked_next_power_of_two()
+        .ok_or(RomError::InvalidMinSize(options.min_size))?;
+    let image_size = rom_size.max(min_size);
+    if image_size > options.max_size {
+        return Err(RomError::RomTooLarge {
+            size: image_size,
+            max_size: options.max_size,
+        });
+    }
+    let image = image.repeat((image_size / rom_size) as usize);
+
+    let report = Report {
+        rom_size,
+        image_size,
+        rom: stored.iter().map(|section| usage(section, true)).collect(),
+        ram: in_ram.iter().map(|section| usage(section, false)).collect(),
+        stack_size: ram_end - ram_top,
+        largest_symbols: largest_symbols(&elf, options.largest_symbols),
+    };
+    Ok(Rom { image, report })
+}
+
+/// Build a `.vb` image from a linked ELF file, and write it to `rom_path`.
+pub fn write_rom(
+    elf_path: impl AsRef<Path>,
+    rom_path: impl AsRef<Path>,
+    options: &RomOptions,
+) -> Result<Report, Box<dyn Error>> {
+    let elf = fs::read(elf_path)?;
+    let rom = build_rom(&elf, options)?;
+    fs::write(rom_path, &rom.image)?;
+    Ok(rom.report)
+}
+
+fn required_symbol(elf: &Elf, name: &'static str) -> Result<u32, RomError> {
+    elf.symbol(name)
+        .map(|symbol| symbol.value)
+        .ok_or(RomError::MissingSymbol(name))
+}
+
+fn check_placement(section: &str, expected: u32, actual: u32) -> Result<(), RomError> {
+    if expected != actual {
+        return Err(RomError::HeaderMisplaced {
+            section: section.to_string(),
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+fn usage(section: &Section, stored: bool) -> SectionUsage {
+    SectionUsage {
+        name: section.name.clone(),
+        address: if stored {
+            section.load_address
+        } else {
+            section.address
+        },
+        size: section.size,
+    }
+}
+
+fn largest_symbols(elf: &Elf, count: usize) -> Vec<SymbolUsage> {
+    let mut symbols: Vec<SymbolUsage> = elf
+        .symbols
+        .iter()
+        .filter(|symbol| symbol.size > 0 && symbol.kind != SymbolKind::Other)
+        .filter_map(|symbol| {
+            let section = elf.sections.get(symbol.section?)?;
+            Some(SymbolUsage {
+                name: format!("{:#}", rustc_demangle::demangle(&symbol.name)),
+                section: section.name.clone(),
+                size: symbol.size,
+            })
+        })
+        .collect();
+    symbols.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    symbols.dedup();
+    symbols.truncate(count);
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::tests::ElfBuilder;
+
+    const MIB: u32 = 1024 * 1024;
+
+    /// A game laid out the way `link.x` does it, in a ROM of `rom_size` bytes.
+    fn game(rom_size: u32) -> ElfBuilder {
+        let header = ROM_ORIGIN + rom_size - HEADER_SIZE;
+        ElfBuilder::new()
+            .section(".text", ROM_ORIGIN, &[1, 2, 3, 4])
+            .loaded_section(".data", RAM_ORIGIN, ROM_ORIGIN + 4, &[5, 6])
+            .bss(".bss", RAM_ORIGIN + 8, 24)
+            .section(".rom_header", header, &[b'H'; 0x20])
+            .section(".handlers", header + 0x20, &[b'V'; 0x200])
+            // 2 is STT_FUNC
+            .symbol("main", ROM_ORIGIN, 4, 2, Some(".text"))
+            .absolute("__rom_size", rom_size)
+            .absolute("__rom_header_start", header)
+    }
+
+    fn build(elf: &ElfBuilder, options: &RomOptions) -> Result<Rom, RomError> {
+        build_rom(&elf.build(), options)
+    }
+
+    fn assert_laid_out(image: &[u8], rom_size: usize) {
+        assert_eq!(&image[..6], &[1, 2, 3, 4, 5, 6]);
+        assert!(image[6..rom_size - 0x220].iter().all(|b| *b == 0xff));
+        assert!(
+            image[rom_size - 0x220..rom_size - 0x200]
+                .iter()
+                .all(|b| *b == b'H')
+        );
+        assert!(image[rom_size - 0x200..rom_size].iter().all(|b| *b == b'V'));
+    }
+
+    #[test]
+    fn the_header_goes_at_the_top_of_the_rom() {
+        for rom_size in [MIB, 2 * MIB, 4 * MIB] {
+            let rom = build(&game(rom_size), &RomOptions::default()).unwrap();
+            assert_eq!(rom.image.len(), rom_size as usize);
+            assert_laid_out(&rom.image, rom_size as usize);
+        }
+    }
+
+    #[test]
+    fn small_roms_are_mirrored_up_to_the_minimum_size() {
+        for (min_size, image_size) in [(2 * MIB, 2 * MIB), (3 * MIB, 4 * MIB), (4 * MIB, 4 * MIB)] {
+            let options = RomOptions {
+                min_size,
+                ..RomOptions::default()
+            };
+            let rom = build(&game(MIB), &options).unwrap();
+            assert_eq!(rom.image.len(), image_size as usize, "min_size {min_size}");
+            assert_eq!(rom.report.image_size, image_size);
+            for mirror in rom.image.chunks(MIB as usize) {
+                assert_laid_out(mirror, MIB as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn the_minimum_size_never_shrinks_the_rom() {
+        let options = RomOptions {
+            min_size: MIB,
+            ..RomOptions::default()
+        };
+        let rom = build(&game(2 * MIB), &options).unwrap();
+        assert_eq!(rom.image.len(), 2 * MIB as usize);
+    }
+
+    #[test]
+    fn unrepresentable_minimum_sizes_are_errors() {
+        let options = RomOptions {
+            min_size: u32::MAX,
+            ..RomOptions::default()
+        };
+        assert_eq!(
+            build(&game(MIB), &options).err(),
+            Some(RomError::InvalidMinSize(u32::MAX))
+        );
+    }
+
+    #[test]
+    fn images_over_the_maximum_size_are_errors() {
+        let options = RomOptions {
+            min_size: 4 * MIB,
+            max_size: 2 * MIB,
+            ..RomOptions::default()
+        };
+        assert_eq!(
+            build(&game(MIB), &options).err(),
+            Some(RomError::RomTooLarge {
+                size: 4 * MIB,
+                max_size: 2 * MIB
+            })
+        );
+    }
+
+    #[test]
+    fn unused_bytes_get_the_fill_value() {
+        let options = RomOptions {
+            fill: 0,
+            ..RomOptions::default()
+        };
+        let rom = build(&game(MIB), &options).unwrap();
+        assert_eq!(rom.image[6], 0);
+    }
+
+    #[test]
+    fn a_misplaced_header_is_an_error() {
+        let header = ROM_ORIGIN + MIB - HEADER_SIZE;
+        let elf = ElfBuilder::new()
+            .section(".rom_header", header - 4, &[0; 0x20])
+            .absolute("__rom_size", MIB)
+            .absolute("__rom_header_start", header - 4);
+        assert_eq!(
+            build(&elf, &RomOptions::default()).err(),
+            Some(RomError::HeaderMisplaced {
+                section: "__rom_header_start".into(),
+                expected: header,
+                actual: header - 4
+            })
+        );
+    }
+
+    #[test]
+    fn bad_rom_sizes_are_errors() {
+        for rom_size in [0, 3 * MIB, 32 * MIB] {
+            let elf = ElfBuilder::new().absolute("__rom_size", rom_size);
+            assert_eq!(
+                build(&elf, &RomOptions::default()).err(),
+                Some(RomError::InvalidRomSize(rom_size))
+            );
+        }
+    }
+
+    #[test]
+    fn missing_symbols_are_errors() {
+        assert_eq!(
+            build(&ElfBuilder::new(), &RomOptions::default()).err(),
+            Some(RomError::MissingSymbol("__rom_size"))
+        );
+    }
+
+    #[test]
+    fn other_machines_are_rejected() {
+        let elf = game(MIB).machine(3);
+        assert_eq!(
+            build(&elf, &RomOptions::default()).err(),
+            Some(RomError::WrongMachine(3))
+        );
+    }
+
+    #[test]
+    fn sections_outside_rom_are_errors() {
+        let elf = game(MIB).section(".rodata", ROM_ORIGIN + MIB - 2, &[0; 4]);
+        assert!(matches!(
+            build(&elf, &RomOptions::default()),
+            Err(RomError::SectionOverflow { region: "ROM", .. })
+        ));
+    }
+
+    #[test]
+    fn overlapping_sections_are_errors() {
+        let elf = game(MIB).section(".rodata", ROM_ORIGIN + 2, &[0; 4]);
+        assert_eq!(
+            build(&elf, &RomOptions::default()).err(),
+            Some(RomError::SectionOverlap {
+                first: ".text".into(),
+                second: ".rodata".into()
+            })
+        );
+    }
+
+    #[test]
+    fn sections_overflowing_ram_are_errors() {
+        let elf = game(MIB).bss(".big", RAM_ORIGIN + 32, RAM_SIZE);
+        assert!(matches!(
+            build(&elf, &RomOptions::default()),
+            Err(RomError::SectionOverflow { region: "RAM", .. })
+        ));
+    }
+
+    #[test]
+    fn the_report_lists_rom_and_ram_usage() {
+        let report = build(&game(MIB), &RomOptions::default()).unwrap().report;
+        assert_eq!(report.rom_size, MIB);
+        assert_eq!(report.image_size, MIB);
+        assert_eq!(report.rom_used(), 6 + HEADER_SIZE);
+        let ram: Vec<_> = report.ram.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(ram, [".data", ".bss"]);
+        assert_eq!(report.ram_used(), 26);
+        assert_eq!(report.stack_size, RAM_SIZE - 32);
+        assert_eq!(
+            report.largest_symbols,
+            [SymbolUsage {
+                name: "main".into(),
+                section: ".text".into(),
+                size: 4
+            }]
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use std::{
+    env,
+    f64::consts::{PI, TAU},
+    fmt::Write as _,
+    fs,
+    path::PathBuf,
+};
+
+fn main() {
+    println!("cargo::rerun-if-changed=build.rs");
+
+    let sin = table(|t| (t * PI / 2.0).sin() * 16384.0).map(|v| v as i16);
+    let atan = table(|t| t.atan() * 65536.0 / TAU).map(|v| v as u16);
+    let recip = table(|t| 32768.0 / (1.0 + t)).map(|v| v as u16);
+    let sqrt = table(|t| (1.0 + t).sqrt() * 32768.0).map(|v| v as u16);
+
+    let mut out = String::new();
+    out.push_str("// Generated by vb-rt's build.rs\n");
+    write_table(&mut out, "SIN", "i16", &sin);
+    write_table(&mut out, "ATAN", "u16", &atan);
+    write_table(&mut out, "RECIP", "u16", &recip);
+    write_table(&mut out, "SQRT", "u16", &sqrt);
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("math_tables.rs"), out).unwrap();
+}
+
+fn table(f: impl Fn(f64) -> f64) -> [i64; 257] {
+    std::array::from_fn(|i| f(i as f64 / 256.0).round() as i64)
+}
+
+fn write_table<T: std::fmt::Display>(out: &mut String, name: &str, ty: &str, values: &[T]) {
+    writeln!(out, "pub(super) const {name}: [{ty}; {}] = [", values.len()).unwrap();
+    for row in values.chunks(16) {
+        let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+        writeln!(out, "    {},", row.join(", ")).unwrap();
+    }
+    out.push_str("];\n");
+}
@@ -0,0 +1,431 @@
+//! Shows panics and CPU exceptions on screen, so there's something to look at on real hardware.
+
+use core::{
+    fmt::{self, Write},
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    interrupt, println,
+    stdio::OutWriter,
+    sys::{halt, vip},
+};
+
+const SCREEN_COLUMNS: usize = 384 / 8;
+const SCREEN_ROWS: usize = 224 / 8;
+const MARGIN: usize = 1;
+
+static CRASHED: AtomicBool = AtomicBool::new(false);
+
+/// Render a panic to the screen.
+pub fn show_panic(panic: &PanicInfo<'_>) {
+    let Some(mut screen) = Screen::take() else {
+        // We panicked while drawing the crash screen, don't make it worse
+        return;
+    };
+    let _ = writeln!(screen, "PANIC\n");
+    let _ = writeln!(screen, "{}\n", panic.message());
+    if let Some(location) = panic.location() {
+        let _ = writeln!(
+            screen,
+            "at {}:{}:{}",
+            location.file(),
+            location.line(),
+            location.column()
+        );
+    }
+}
+
+#[derive(Clone, Copy)]
+#[repr(u32)]
+enum Exception {
+    FloatingPoint = 0,
+    DivideByZero = 1,
+    IllegalOpcode = 2,
+    Duplexed = 3,
+}
+
+/// Everything the exception handlers save before handing off to Rust.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ExceptionFrame {
+    registers: [u32; 32],
+    eipc: u32,
+    eipsw: u32,
+    fepc: u32,
+    fepsw: u32,
+    ecr: u32,
+}
+
+static mut EXCEPTION_FRAME: ExceptionFrame = ExceptionFrame {
+    registers: [0; 32],
+    eipc: 0,
+    eipsw: 0,
+    fepc: 0,
+    fepsw: 0,
+    ecr: 0,
+};
+
+// Saves every register (except r1, which the handler stub uses for the jump)
+// and the system registers describing the exception, then calls into Rust.
+// Expects r1 to point at EXCEPTION_FRAME, r6 to already be saved, and r6 to hold the exception kind.
+core::arch::global_asm!("
+    .section .text._vb_rt_crash_exception,\"ax\",@progbits
+    .globl _vb_rt_crash_exception
+    .type _vb_rt_crash_exception,@function
+_vb_rt_crash_exception:
+    st.w r0, 0[r1]
+    st.w r2, 8[r1]
+    st.w r3, 12[r1]
+    st.w r4, 16[r1]
+    st.w r5, 20[r1]
+    st.w r7, 28[r1]
+    st.w r8, 32[r1]
+    st.w r9, 36[r1]
+    st.w r10, 40[r1]
+    st.w r11, 44[r1]
+    st.w r12, 48[r1]
+    st.w r13, 52[r1]
+    st.w r14, 56[r1]
+    st.w r15, 60[r1]
+    st.w r16, 64[r1]
+    st.w r17, 68[r1]
+    st.w r18, 72[r1]
+    st.w r19, 76[r1]
+    st.w r20, 80[r1]
+    st.w r21, 84[r1]
+    st.w r22, 88[r1]
+    st.w r23, 92[r1]
+    st.w r24, 96[r1]
+    st.w r25, 100[r1]
+    st.w r26, 104[r1]
+    st.w r27, 108[r1]
+    st.w r28, 112[r1]
+    st.w r29, 116[r1]
+    st.w r30, 120[r1]
+    st.w r31, 124[r1]
+    stsr eipc, r7
+    st.w r7, 128[r1]
+    stsr eipsw, r7
+    st.w r7, 132[r1]
+    stsr fepc, r7
+    st.w r7, 136[r1]
+    stsr fepsw, r7
+    st.w r7, 140[r1]
+    stsr ecr, r7
+    st.w r7, 144[r1]
+
+    /* start from a fresh stack, in case the old one is what broke */
+    movhi hi(__gp), r0, sp
+
+    movhi hi({crash}), r0, r1
+    movea lo({crash}), r1, r1
+    jmp [r1]
+.L_vb_rt_crash_exception_end:
+    .size _vb_rt_crash_exception, .L_vb_rt_crash_exception_end-_vb_rt_crash_exception
+", crash = sym show_exception);
+
+// Weak, so games which define their own handlers override these.
+macro_rules! exception_handler {
+    ($handler:ident, $kind:expr) => {
+        unsafe extern "Rust" {
+            unsafe fn $handler();
+        }
+        core::arch::global_asm!("
+            .section .text.{handler},\"ax\",@progbits
+            .weak {handler}
+            .type {handler},@function
+        {handler}:
+            movhi hi({frame}), r0, r1
+            movea lo({frame}), r1, r1
+            st.w r6, 24[r1]
+            movea {kind}, r0, r6
+            jr _vb_rt_crash_exception
+        .L__crash_{handler}_end:
+            .size {handler}, .L__crash_{handler}_end-{handler}
+        ",
+        handler = sym $handler,
+        frame = sym EXCEPTION_FRAME,
+        kind = const $kind as u32);
+    };
+}
+
+exception_handler!(_vb_rt_fp_exception_handler, Exception::FloatingPoint);
+exception_handler!(_vb_rt_divide_by_zero_handler, Exception::DivideByZero);
+exception_handler!(_vb_rt_illegal_opcode_handler, Exception::IllegalOpcode);
+exception_handler!(_vb_rt_duplexed_exception_handler, Exception::Duplexed);
+
+extern "C" fn show_exception(kind: u32) -> ! {
+    // SAFETY: the handler finished writing this before calling us, and nothing else touches it.
+    let frame = unsafe { (&raw const EXCEPTION_FRAME).read_volatile() };
+    let kind = match kind {
+        0 => Exception::FloatingPoint,
+        1 => Exception::DivideByZero,
+        2 => Exception::IllegalOpcode,
+        _ => Exception::Duplexed,
+    };
+
+    let _ = describe_exception(&mut OutWriter, kind, &frame);
+    println!();
+    if let Some(mut screen) = Screen::take() {
+        let _ = describe_exception(&mut screen, kind, &frame);
+    }
+    loop {
+        halt();
+    }
+}
+
+fn describe_exception(
+    out: &mut impl Write,
+    kind: Exception,
+    frame: &ExceptionFrame,
+) -> fmt::Result {
+    let eicc = frame.ecr & 0xffff;
+    let name = match kind {
+        Exception::FloatingPoint => match eicc {
+            0xff60 => "FP RESERVED OPERAND",
+            0xff64 => "FP OVERFLOW",
+            0xff68 => "FP DIVIDE BY ZERO",
+            0xff70 => "FP INVALID OPERATION",
+            _ => "FP EXCEPTION",
+        },
+        Exception::DivideByZero => "DIVIDE BY ZERO",
+        Exception::IllegalOpcode => "ILLEGAL OPCODE",
+        Exception::Duplexed => "DUPLEXED EXCEPTION",
+    };
+    writeln!(out, "{name}\n")?;
+    writeln!(out, "PC  {:08x}  PSW {:08x}", frame.eipc, frame.eipsw)?;
+    if let Exception::Duplexed = kind {
+        // The original exception is in EIPC/EIPSW, the one it caused is in FEPC/FEPSW.
+        writeln!(out, "FEPC {:08x} FEPSW {:08x}", frame.fepc, frame.fepsw)?;
+    }
+    writeln!(out, "ECR {:08x}\n", frame.ecr)?;
+    for (index, value) in frame.registers.iter().enumerate() {
+        let separator = if index % 3 == 2 { "\n" } else { "  " };
+        if index == 1 {
+            // the handler stub clobbered this one
+            write!(out, "r1  --------{separator}")?;
+        } else {
+            write!(out, "r{index:<2} {value:08x}{separator}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Draws text into background map 0, which is shown in both eyes.
+struct Screen {
+    column: usize,
+    row: usize,
+}
+
+impl Screen {
+    /// Reset the VIP and prepare to draw. Returns `None` if we've already crashed once.
+    fn take() -> Option<Self> {
+        if CRASHED.load(Ordering::Relaxed) {
+            return None;
+        }
+        CRASHED.store(true, Ordering::Relaxed);
+        interrupt::disable();
+        reset_display();
+        Some(Self {
+            column: MARGIN,
+            row: MARGIN,
+        })
+    }
+
+    fn newline(&mut self) {
+        self.column = MARGIN;
+        self.row += 1;
+    }
+
+    fn put(&mut self, byte: u8) {
+        if byte == b'\n' {
+            self.newline();
+            return;
+        }
+        if self.column >= SCREEN_COLUMNS - MARGIN {
+            self.newline();
+        }
+        if self.row >= SCREEN_ROWS - MARGIN {
+            return;
+        }
+        let glyph = match byte {
+            b' '..=b'~' => byte - b' ',
+            _ => b'?' - b' ',
+        };
+        let cell = vip::Cell::new().with_character(glyph as u16);
+        vip::BG_CELLS.index(self.row * 64 + self.column).write(cell);
+        self.column += 1;
+    }
+}
+
+impl Write for Screen {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.put(byte);
+        }
+        Ok(())
+    }
+}
+
+fn reset_display() {
+    vip::INTENB.write(vip::InterruptFlags::new());
+    vip::INTCLR.write(vip::InterruptFlags::from_bits(0xffff));
+    vip::XPCTRL.write(vip::DrawingFlags::new().with_xprst(true));
+    vip::DPCTRL.write(vip::DisplayFlags::new().with_dprst(true));
+
+    vip::REST.write(0);
+    vip::BKCOL.write(0);
+    vip::BRTA.write(32);
+    vip::BRTB.write(64);
+    vip::BRTC.write(32);
+    vip::GPLT0.write(vip::Palette::new().with_c1(1).with_c2(2).with_c3(3));
+
+    for (index, glyph) in FONT.iter().enumerate() {
+        let mut character = [0u16; 8];
+        for (row, bits) in character.iter_mut().zip(glyph) {
+            for x in 0..8 {
+                if bits & (1 << x) != 0 {
+                    *row |= 3 << (x * 2);
+                }
+            }
+        }
+        vip::CHARACTERS
+            .index(index)
+            .write(vip::Character(character));
+    }
+    // character 0 is a space, so this blanks the whole map
+    for index in 0..64 * 64 {
+        vip::BG_CELLS.index(index).write(vip::Cell::new());
+    }
+
+    let world = vip::WORLDS.index(31);
+    world.header().write(
+        vip::WorldHeader::new()
+            .with_lon(true)
+            .with_ron(true)
+            .with_bgm(vip::WorldMode::Normal)
+            .with_bg_map_base(0),
+    );
+    world.gx().write(0);
+    world.gp().write(0);
+    world.gy().write(0);
+    world.mx().write(0);
+    world.mp().write(0);
+    world.my().write(0);
+    world.w().write(384 - 1);
+    world.h().write(224 - 1);
+    let world = vip::WORLDS.index(30);
+    world.header().write(vip::WorldHeader::new().with_end(true));
+
+    while !vip::DPSTTS.read().scanrdy() {}
+    vip::DPCTRL.write(
+        vip::DisplayFlags::new()
+            .with_disp(true)
+            .with_re(true)
+            .with_synce(true),
+    );
+    vip::XPCTRL.write(vip::DrawingFlags::new().with_xpen(true));
+}
+
+/// An 8x8 font covering printable ASCII, starting at ' '.
+/// Each byte is a row of pixels, with the leftmost pixel in the lowest bit.
+#[rustfmt::skip]
+static FONT: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x18, 0x3c, 0x3c, 0x18, 0x18, 0x00, 0x18, 0x00], // '!'
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x36, 0x36, 0x7f, 0x36, 0x7f, 0x36, 0x36, 0x00], // '#'
+    [0x0c, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x0c, 0x00], // '$'
+    [0x00, 0x63, 0x33, 0x18, 0x0c, 0x66, 0x63, 0x00], // '%'
+    [0x1c, 0x36, 0x1c, 0x6e, 0x3b, 0x33, 0x6e, 0x00], // '&'
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // '\''
+    [0x18, 0x0c, 0x06, 0x06, 0x06, 0x0c, 0x18, 0x00], // '('
+    [0x06, 0x0c, 0x18, 0x18, 0x18, 0x0c, 0x06, 0x00], // ')'
+    [0x00, 0x66, 0x3c, 0xff, 0x3c, 0x66, 0x00, 0x00], // '*'
+    [0x00, 0x0c, 0x0c, 0x3f, 0x0c, 0x0c, 0x00, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x06], // ','
+    [0x00, 0x00, 0x00, 0x3f, 0x00, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x00], // '.'
+    [0x60, 0x30, 0x18, 0x0c, 0x06, 0x03, 0x01, 0x00], // '/'
+    [0x3e, 0x63, 0x73, 0x7b, 0x6f, 0x67, 0x3e, 0x00], // '0'
+    [0x0c, 0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x3f, 0x00], // '1'
+    [0x1e, 0x33, 0x30, 0x1c, 0x06, 0x33, 0x3f, 0x00], // '2'
+    [0x1e, 0x33, 0x30, 0x1c, 0x30, 0x33, 0x1e, 0x00], // '3'
+    [0x38, 0x3c, 0x36, 0x33, 0x7f, 0x30, 0x78, 0x00], // '4'
+    [0x3f, 0x03, 0x1f, 0x30, 0x30, 0x33, 0x1e, 0x00], // '5'
+    [0x1c, 0x06, 0x03, 0x1f, 0x33, 0x33, 0x1e, 0x00], // '6'
+    [0x3f, 0x33, 0x30, 0x18, 0x0c, 0x0c, 0x0c, 0x00], // '7'
+    [0x1e, 0x33, 0x33, 0x1e, 0x33, 0x33, 0x1e, 0x00], // '8'
+    [0x1e, 0x33, 0x33, 0x3e, 0x30, 0x18, 0x0e, 0x00], // '9'
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x00], // ':'
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x06], // ';'
+    [0x18, 0x0c, 0x06, 0x03, 0x06, 0x0c, 0x18, 0x00], // '<'
+    [0x00, 0x00, 0x3f, 0x00, 0x00, 0x3f, 0x00, 0x00], // '='
+    [0x06, 0x0c, 0x18, 0x30, 0x18, 0x0c, 0x06, 0x00], // '>'
+    [0x1e, 0x33, 0x30, 0x18, 0x0c, 0x00, 0x0c, 0x00], // '?'
+    [0x3e, 0x63, 0x7b, 0x7b, 0x7b, 0x03, 0x1e, 0x00], // '@'
+    [0x0c, 0x1e, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x00], // 'A'
+    [0x3f, 0x66, 0x66, 0x3e, 0x66, 0x66, 0x3f, 0x00], // 'B'
+    [0x3c, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3c, 0x00], // 'C'
+    [0x1f, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1f, 0x00], // 'D'
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x46, 0x7f, 0x00], // 'E'
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x06, 0x0f, 0x00], // 'F'
+    [0x3c, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7c, 0x00], // 'G'
+    [0x33, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x33, 0x00], // 'H'
+    [0x1e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'I'
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e, 0x00], // 'J'
+    [0x67, 0x66, 0x36, 0x1e, 0x36, 0x66, 0x67, 0x00], // 'K'
+    [0x0f, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7f, 0x00], // 'L'
+    [0x63, 0x77, 0x7f, 0x7f, 0x6b, 0x63, 0x63, 0x00], // 'M'
+    [0x63, 0x67, 0x6f, 0x7b, 0x73, 0x63, 0x63, 0x00], // 'N'
+    [0x1c, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1c, 0x00], // 'O'
+    [0x3f, 0x66, 0x66, 0x3e, 0x06, 0x06, 0x0f, 0x00], // 'P'
+    [0x1e, 0x33, 0x33, 0x33, 0x3b, 0x1e, 0x38, 0x00], // 'Q'
+    [0x3f, 0x66, 0x66, 0x3e, 0x36, 0x66, 0x67, 0x00], // 'R'
+    [0x1e, 0x33, 0x07, 0x0e, 0x38, 0x33, 0x1e, 0x00], // 'S'
+    [0x3f, 0x2d, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'T'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3f, 0x00], // 'U'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00], // 'V'
+    [0x63, 0x63, 0x63, 0x6b, 0x7f, 0x77, 0x63, 0x00], // 'W'
+    [0x63, 0x63, 0x36, 0x1c, 0x1c, 0x36, 0x63, 0x00], // 'X'
+    [0x33, 0x33, 0x33, 0x1e, 0x0c, 0x0c, 0x1e, 0x00], // 'Y'
+    [0x7f, 0x63, 0x31, 0x18, 0x4c, 0x66, 0x7f, 0x00], // 'Z'
+    [0x1e, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1e, 0x00], // '['
+    [0x03, 0x06, 0x0c, 0x18, 0x30, 0x60, 0x40, 0x00], // '\\'
+    [0x1e, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1e, 0x00], // ']'
+    [0x08, 0x1c, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff], // '_'
+    [0x0c, 0x0c, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x1e, 0x30, 0x3e, 0x33, 0x6e, 0x00], // 'a'
+    [0x07, 0x06, 0x06, 0x3e, 0x66, 0x66, 0x3b, 0x00], // 'b'
+    [0x00, 0x00, 0x1e, 0x33, 0x03, 0x33, 0x1e, 0x00], // 'c'
+    [0x38, 0x30, 0x30, 0x3e, 0x33, 0x33, 0x6e, 0x00], // 'd'
+    [0x00, 0x00, 0x1e, 0x33, 0x3f, 0x03, 0x1e, 0x00], // 'e'
+    [0x1c, 0x36, 0x06, 0x0f, 0x06, 0x06, 0x0f, 0x00], // 'f'
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x1f], // 'g'
+    [0x07, 0x06, 0x36, 0x6e, 0x66, 0x66, 0x67, 0x00], // 'h'
+    [0x0c, 0x00, 0x0e, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'i'
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e], // 'j'
+    [0x07, 0x06, 0x66, 0x36, 0x1e, 0x36, 0x67, 0x00], // 'k'
+    [0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'l'
+    [0x00, 0x00, 0x33, 0x7f, 0x7f, 0x6b, 0x63, 0x00], // 'm'
+    [0x00, 0x00, 0x1f, 0x33, 0x33, 0x33, 0x33, 0x00], // 'n'
+    [0x00, 0x00, 0x1e, 0x33, 0x33, 0x33, 0x1e, 0x00], // 'o'
+    [0x00, 0x00, 0x3b, 0x66, 0x66, 0x3e, 0x06, 0x0f], // 'p'
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x78], // 'q'
+    [0x00, 0x00, 0x3b, 0x6e, 0x66, 0x06, 0x0f, 0x00], // 'r'
+    [0x00, 0x00, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x00], // 's'
+    [0x08, 0x0c, 0x3e, 0x0c, 0x0c, 0x2c, 0x18, 0x00], // 't'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6e, 0x00], // 'u'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00], // 'v'
+    [0x00, 0x00, 0x63, 0x6b, 0x7f, 0x7f, 0x36, 0x00], // 'w'
+    [0x00, 0x00, 0x63, 0x36, 0x1c, 0x36, 0x63, 0x00], // 'x'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3e, 0x30, 0x1f], // 'y'
+    [0x00, 0x00, 0x3f, 0x19, 0x0c, 0x26, 0x3f, 0x00], // 'z'
+    [0x38, 0x0c, 0x0c, 0x07, 0x0c, 0x0c, 0x38, 0x00], // '{'
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // '|'
+    [0x07, 0x0c, 0x0c, 0x38, 0x0c, 0x0c, 0x07, 0x00], // '}'
+    [0x6e, 0x3b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+];
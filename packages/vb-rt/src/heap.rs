@@ -0,0 +1,396 @@
+//! A heap in WRAM, for use with `alloc`.
+//! Reserve space for it with [`heap_size!`](crate::heap_size).
+//!
+//! The heap sits just above .bss, and the stack grows down towards it from the top of RAM.
+//! Every allocation checks that the stack hasn't collided with the heap,
+//! and panics if it has.
+//!
+//! On the host, [`Heap`] is still available, but there's no global allocator.
+
+use core::{alloc::Layout, ptr::null_mut};
+
+#[cfg(target_arch = "v810")]
+mod wram;
+#[cfg(target_arch = "v810")]
+pub use wram::{stack_collided, stats};
+
+/// A snapshot of the heap's usage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// The number of bytes the heap manages.
+    pub size: usize,
+    /// The number of bytes currently allocated, including padding.
+    pub used: usize,
+    /// The most bytes which have ever been allocated at once.
+    pub peak_used: usize,
+    /// The number of live allocations.
+    pub allocations: usize,
+    /// The number of allocations which failed because there was no room.
+    pub failed_allocations: usize,
+    /// The size of the largest allocation which would currently succeed.
+    pub largest_free_block: usize,
+}
+
+impl HeapStats {
+    /// The number of bytes not currently allocated.
+    /// They may be too fragmented to use all at once, see `largest_free_block`.
+    pub const fn free(&self) -> usize {
+        self.size - self.used
+    }
+}
+
+struct FreeBlock {
+    size: usize,
+    next: *mut FreeBlock,
+}
+
+/// Every block is a multiple of this size, and aligned to it,
+/// so that any gap left over can hold a [`FreeBlock`].
+const BLOCK_SIZE: usize = size_of::<FreeBlock>();
+
+/// A first-fit allocator over a single region of memory.
+/// Free blocks form a linked list sorted by address, stored inside the free memory itself,
+/// and neighbouring blocks are merged as soon as they're freed.
+/// Allocations have no header, so the only overhead is rounding sizes up to 8 bytes.
+///
+/// This doesn't touch any hardware, so it works with any region of memory.
+pub struct Heap {
+    head: *mut FreeBlock,
+    stats: HeapStats,
+}
+
+impl Heap {
+    /// A heap with no memory to hand out.
+    pub const fn empty() -> Self {
+        Self {
+            head: null_mut(),
+            stats: HeapStats {
+                size: 0,
+                used: 0,
+                peak_used: 0,
+                allocations: 0,
+                failed_allocations: 0,
+                largest_free_block: 0,
+            },
+        }
+    }
+
+    /// Hand the region from `start` to `start + size` over to the heap.
+    /// Any unaligned bytes at either end are left unused.
+    ///
+    /// # Safety
+    ///
+    /// The region must be valid for reads and writes, and not used by anything else
+    /// for as long as the heap is.
+    pub unsafe fn init(&mut self, start: *mut u8, size: usize) {
+        let first = start.align_offset(BLOCK_SIZE);
+        let size = size.saturating_sub(first) & !(BLOCK_SIZE - 1);
+        *self = Self::empty();
+        if size == 0 {
+            return;
+        }
+        let block = unsafe { start.add(first) }.cast::<FreeBlock>();
+        unsafe {
+            block.write(FreeBlock {
+                size,
+                next: null_mut(),
+            })
+        };
+        self.head = block;
+        self.stats.size = size;
+    }
+
+    /// Allocate memory for the given layout. Returns null if there's no room.
+    pub fn allocate(&mut self, layout: Layout) -> *mut u8 {
+        let (size, align) = Self::normalize(layout);
+        let mut prev: *mut *mut FreeBlock = &raw mut self.head;
+        // SAFETY: every block in the list lies within the region passed to init
+        unsafe {
+            while !(*prev).is_null() {
+                let block = *prev;
+                let start = block.addr();
+                let end = start + (*block).size;
+                let aligned = start.next_multiple_of(align);
+                let alloc_end = aligned.saturating_add(size);
+                if alloc_end > end {
+                    prev = &raw mut (*block).next;
+                    continue;
+                }
+
+                // Give back whatever's left after the allocation...
+                let mut rest = (*block).next;
+                if alloc_end < end {
+                    let tail = block.with_addr(alloc_end);
+                    tail.write(FreeBlock {
+                        size: end - alloc_end,
+                        next: rest,
+                    });
+                    rest = tail;
+                }
+                // ...and whatever was skipped to align it.
+                if aligned > start {
+                    (*block).size = aligned - start;
+                    (*block).next = rest;
+                } else {
+                    *prev = rest;
+                }
+
+                self.stats.used += size;
+                self.stats.peak_used = self.stats.peak_used.max(self.stats.used);
+                self.stats.allocations += 1;
+                return block.with_addr(aligned).cast();
+            }
+        }
+        self.stats.failed_allocations += 1;
+        null_mut()
+    }
+
+    /// Return memory to the heap.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must have come from [`Heap::allocate`] on this heap with the same layout,
+    /// and must not be freed twice.
+    pub unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout) {
+        let (size, _) = Self::normalize(layout);
+        let addr = ptr.addr();
+        let block = ptr.cast::<FreeBlock>();
+        // SAFETY: the caller promises this memory is ours, and every block in the list is too
+        unsafe {
+            let mut prev: *mut FreeBlock = null_mut();
+            let mut next = self.head;
+            while !next.is_null() && next.addr() < addr {
+                prev = next;
+                next = (*next).next;
+            }
+
+            block.write(FreeBlock { size, next });
+            if !next.is_null() && addr + size == next.addr() {
+                (*block).size += (*next).size;
+                (*block).next = (*next).next;
+            }
+            if prev.is_null() {
+                self.head = block;
+            } else if prev.addr() + (*prev).size == addr {
+                (*prev).size += (*block).size;
+                (*prev).next = (*block).next;
+            } else {
+                (*prev).next = block;
+            }
+        }
+        self.stats.used -= size;
+        self.stats.allocations -= 1;
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        let mut largest_free_block = 0;
+        let mut block = self.head;
+        while !block.is_null() {
+            // SAFETY: every block in the list lies within the region passed to init
+            unsafe {
+                largest_free_block = largest_free_block.max((*block).size);
+                block = (*block).next;
+            }
+        }
+        HeapStats {
+            largest_free_block,
+            ..self.stats
+        }
+    }
+
+    /// Round the layout up so that every allocation and every gap between them can hold a [`FreeBlock`].
+    const fn normalize(layout: Layout) -> (usize, usize) {
+        let align = if layout.align() > BLOCK_SIZE {
+            layout.align()
+        } else {
+            BLOCK_SIZE
+        };
+        let size = if layout.size() == 0 {
+            BLOCK_SIZE
+        } else {
+            layout.size().next_multiple_of(BLOCK_SIZE)
+        };
+        (size, align)
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARENA_SIZE: usize = 16 * BLOCK_SIZE;
+
+    #[repr(C, align(64))]
+    struct Arena([u8; ARENA_SIZE]);
+
+    fn with_heap(f: impl FnOnce(&mut Heap, *mut u8)) {
+        let mut arena = Arena([0; ARENA_SIZE]);
+        let start = arena.0.as_mut_ptr();
+        let mut heap = Heap::empty();
+        unsafe { heap.init(start, ARENA_SIZE) };
+        f(&mut heap, start);
+    }
+
+    fn blocks(count: usize) -> Layout {
+        Layout::from_size_align(count * BLOCK_SIZE, 1).unwrap()
+    }
+
+    fn offset(ptr: *mut u8, start: *mut u8) -> usize {
+        assert!(!ptr.is_null());
+        (ptr.addr() - start.addr()) / BLOCK_SIZE
+    }
+
+    #[test]
+    fn an_empty_heap_has_nothing_to_give() {
+        let mut heap = Heap::empty();
+        assert!(heap.allocate(blocks(1)).is_null());
+        assert_eq!(heap.stats().failed_allocations, 1);
+    }
+
+    #[test]
+    fn init_skips_unaligned_bytes() {
+        let mut arena = Arena([0; ARENA_SIZE]);
+        let mut heap = Heap::empty();
+        unsafe { heap.init(arena.0.as_mut_ptr().add(1), ARENA_SIZE - 2) };
+        assert_eq!(heap.stats().size, ARENA_SIZE - 2 * BLOCK_SIZE);
+        let ptr = heap.allocate(blocks(1));
+        assert_eq!(ptr.addr() % BLOCK_SIZE, 0);
+        assert_eq!(ptr.addr() - arena.0.as_ptr().addr(), BLOCK_SIZE);
+    }
+
+    #[test]
+    fn allocations_are_handed_out_first_fit() {
+        with_heap(|heap, start| {
+            let a = heap.allocate(blocks(2));
+            let b = heap.allocate(blocks(3));
+            let c = heap.allocate(Layout::new::<u8>());
+            assert_eq!(offset(a, start), 0);
+            assert_eq!(offset(b, start), 2);
+            assert_eq!(offset(c, start), 5);
+
+            let stats = heap.stats();
+            assert_eq!(stats.used, 6 * BLOCK_SIZE);
+            assert_eq!(stats.allocations, 3);
+            assert_eq!(stats.largest_free_block, 10 * BLOCK_SIZE);
+            assert_eq!(stats.free(), 10 * BLOCK_SIZE);
+        });
+    }
+
+    #[test]
+    fn zero_sized_allocations_still_take_a_block() {
+        with_heap(|heap, start| {
+            let a = heap.allocate(Layout::new::<()>());
+            let b = heap.allocate(Layout::new::<()>());
+            assert_eq!(offset(a, start), 0);
+            assert_eq!(offset(b, start), 1);
+        });
+    }
+
+    #[test]
+    fn alignment_padding_is_returned_to_the_heap() {
+        with_heap(|heap, start| {
+            let a = heap.allocate(blocks(1));
+            let big_align = Layout::from_size_align(BLOCK_SIZE, 4 * BLOCK_SIZE).unwrap();
+            let b = heap.allocate(big_align);
+            assert_eq!(offset(a, start), 0);
+            assert_eq!(offset(b, start), 4);
+            // The gap between them is still free.
+            let c = heap.allocate(blocks(3));
+            assert_eq!(offset(c, start), 1);
+            assert_eq!(heap.stats().used, 5 * BLOCK_SIZE);
+        });
+    }
+
+    #[test]
+    fn freed_memory_is_reused() {
+        with_heap(|heap, start| {
+            let a = heap.allocate(blocks(2));
+            let _b = heap.allocate(blocks(2));
+            unsafe { heap.deallocate(a, blocks(2)) };
+            let c = heap.allocate(blocks(1));
+            assert_eq!(offset(c, start), 0);
+            assert_eq!(heap.stats().allocations, 2);
+        });
+    }
+
+    #[test]
+    fn neighbouring_free_blocks_coalesce_in_any_order() {
+        for order in [[0, 1, 2], [2, 1, 0], [0, 2, 1], [1, 0, 2]] {
+            with_heap(|heap, _| {
+                let ptrs = [
+                    heap.allocate(blocks(4)),
+                    heap.allocate(blocks(4)),
+                    heap.allocate(blocks(4)),
+                ];
+                let _guard = heap.allocate(blocks(4));
+                assert_eq!(heap.stats().largest_free_block, 0);
+                for index in order {
+                    unsafe { heap.deallocate(ptrs[index], blocks(4)) };
+                }
+                let stats = heap.stats();
+                assert_eq!(stats.largest_free_block, 12 * BLOCK_SIZE, "order {order:?}");
+                assert_eq!(stats.used, 4 * BLOCK_SIZE);
+                assert!(!heap.allocate(blocks(12)).is_null());
+            });
+        }
+    }
+
+    #[test]
+    fn fragmented_memory_cant_satisfy_large_allocations() {
+        with_heap(|heap, start| {
+            let ptrs: [_; 8] = core::array::from_fn(|_| heap.allocate(blocks(2)));
+            assert_eq!(heap.stats().free(), 0);
+            for ptr in ptrs.iter().step_by(2) {
+                unsafe { heap.deallocate(*ptr, blocks(2)) };
+            }
+            let stats = heap.stats();
+            assert_eq!(stats.free(), 8 * BLOCK_SIZE);
+            assert_eq!(stats.largest_free_block, 2 * BLOCK_SIZE);
+
+            assert!(heap.allocate(blocks(3)).is_null());
+            assert_eq!(heap.stats().failed_allocations, 1);
+
+            // Freeing the block between two holes joins all three.
+            unsafe { heap.deallocate(ptrs[1], blocks(2)) };
+            assert_eq!(heap.stats().largest_free_block, 6 * BLOCK_SIZE);
+            assert_eq!(offset(heap.allocate(blocks(5)), start), 0);
+        });
+    }
+
+    #[test]
+    fn peak_usage_is_remembered() {
+        with_heap(|heap, _| {
+            let a = heap.allocate(blocks(10));
+            unsafe { heap.deallocate(a, blocks(10)) };
+            let _b = heap.allocate(blocks(1));
+            let stats = heap.stats();
+            assert_eq!(stats.used, BLOCK_SIZE);
+            assert_eq!(stats.peak_used, 10 * BLOCK_SIZE);
+        });
+    }
+
+    #[test]
+    fn everything_freed_leaves_one_block() {
+        with_heap(|heap, start| {
+            let mut ptrs = [null_mut(); 16];
+            for ptr in &mut ptrs {
+                *ptr = heap.allocate(blocks(1));
+            }
+            assert!(heap.allocate(blocks(1)).is_null());
+            for index in [3, 9, 0, 15, 7, 1, 12, 4, 10, 2, 14, 6, 11, 5, 13, 8] {
+                unsafe { heap.deallocate(ptrs[index], blocks(1)) };
+            }
+            let stats = heap.stats();
+            assert_eq!(stats.used, 0);
+            assert_eq!(stats.allocations, 0);
+            assert_eq!(stats.largest_free_block, ARENA_SIZE);
+            assert_eq!(offset(heap.allocate(blocks(16)), start), 0);
+        });
+    }
+}
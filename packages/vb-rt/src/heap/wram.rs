@@ -0,0 +1,84 @@
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::UnsafeCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::{Heap, HeapStats};
+use crate::interrupt;
+
+unsafe extern "C" {
+    static mut _heap_start: u8;
+    static mut _heap_end: u8;
+}
+
+/// Written to the last word of the heap. If it changes, the stack has grown into the heap.
+const STACK_GUARD: u32 = 0x4845_4150;
+
+struct WramHeap {
+    heap: UnsafeCell<Heap>,
+    initialized: AtomicBool,
+}
+
+// SAFETY: the heap is only touched with interrupts disabled.
+unsafe impl Sync for WramHeap {}
+
+#[global_allocator]
+static HEAP: WramHeap = WramHeap {
+    heap: UnsafeCell::new(Heap::empty()),
+    initialized: AtomicBool::new(false),
+};
+
+impl WramHeap {
+    fn with_heap<R>(&self, f: impl FnOnce(&mut Heap) -> R) -> R {
+        interrupt::free(|_| {
+            // SAFETY: interrupts are disabled, so nothing else can be using the heap
+            let heap = unsafe { &mut *self.heap.get() };
+            if !self.initialized.load(Ordering::Relaxed) {
+                let (start, end) = region();
+                let guard = end.wrapping_sub(size_of::<u32>()).cast::<u32>();
+                if end.addr() - start.addr() >= size_of::<u32>() {
+                    // SAFETY: the linker reserved this region for us
+                    unsafe {
+                        guard.write_volatile(STACK_GUARD);
+                        heap.init(start, guard.addr() - start.addr());
+                    }
+                }
+                self.initialized.store(true, Ordering::Relaxed);
+            }
+            assert!(!stack_collided(), "the stack has collided with the heap");
+            f(heap)
+        })
+    }
+}
+
+unsafe impl GlobalAlloc for WramHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.with_heap(|heap| heap.allocate(layout))
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.with_heap(|heap| unsafe { heap.deallocate(ptr, layout) })
+    }
+}
+
+/// Get a snapshot of the heap's usage.
+pub fn stats() -> HeapStats {
+    HEAP.with_heap(|heap| heap.stats())
+}
+
+/// Returns true if the stack is currently inside the heap, or has been at some point.
+/// Allocating or freeing panics if this is true, but you can call it yourself to check more often.
+pub fn stack_collided() -> bool {
+    let (start, end) = region();
+    if start == end || !HEAP.initialized.load(Ordering::Relaxed) {
+        return false;
+    }
+    let guard = end.wrapping_sub(size_of::<u32>()).cast::<u32>();
+    // SAFETY: the linker reserved this region for us
+    crate::stack::pointer() < end.addr() || unsafe { guard.read_volatile() } != STACK_GUARD
+}
+
+fn region() -> (*mut u8, *mut u8) {
+    (&raw mut _heap_start, &raw mut _heap_end)
+}
@@ -0,0 +1,299 @@
+use crate::sys::hardware::{self, GamePadData};
+
+/// A button on the game pad.
+/// Each variant's value is the index of its bit in [`GamePadData`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Button {
+    A = 2,
+    B = 3,
+    RightTrigger = 4,
+    LeftTrigger = 5,
+    RightUp = 6,
+    RightRight = 7,
+    LeftRight = 8,
+    LeftLeft = 9,
+    LeftDown = 10,
+    LeftUp = 11,
+    Start = 12,
+    Select = 13,
+    RightLeft = 14,
+    RightDown = 15,
+}
+
+impl Button {
+    pub const ALL: [Button; 14] = [
+        Button::A,
+        Button::B,
+        Button::RightTrigger,
+        Button::LeftTrigger,
+        Button::RightUp,
+        Button::RightRight,
+        Button::LeftRight,
+        Button::LeftLeft,
+        Button::LeftDown,
+        Button::LeftUp,
+        Button::Start,
+        Button::Select,
+        Button::RightLeft,
+        Button::RightDown,
+    ];
+
+    const fn mask(self) -> u16 {
+        1 << self as u8
+    }
+}
+
+/// Bits of [`GamePadData`] which aren't buttons.
+const STATUS_MASK: u16 = GamePadData::new().with_pwr(true).with_sgn(true).into_bits();
+
+/// Tracks the state of the game pad from frame to frame.
+/// Call [`InputState::update`] exactly once per frame, then query it as often as you like.
+#[derive(Debug, Clone)]
+pub struct InputState {
+    current: u16,
+    previous: u16,
+    hold_frames: [u16; 16],
+    repeat_delay: u16,
+    repeat_interval: u16,
+    low_battery: bool,
+    low_battery_changed: bool,
+}
+
+impl InputState {
+    pub const fn new() -> Self {
+        Self {
+            current: 0,
+            previous: 0,
+            hold_frames: [0; 16],
+            repeat_delay: 0,
+            repeat_interval: 0,
+            low_battery: false,
+            low_battery_changed: false,
+        }
+    }
+
+    /// Configure auto-repeat for [`InputState::repeated`].
+    /// After a button has been held for `delay` frames, it repeats every `interval` frames.
+    pub const fn with_repeat(self, delay: u16, interval: u16) -> Self {
+        Self {
+            repeat_delay: delay,
+            repeat_interval: interval,
+            ..self
+        }
+    }
+
+    /// Read the game pad, and advance to the next frame.
+    pub fn update(&mut self) {
+        self.update_with(hardware::read_controller());
+    }
+
+    /// Advance to the next frame, using the given game pad state.
+    pub fn update_with(&mut self, data: GamePadData) {
+        let bits = data.into_bits();
+        self.previous = self.current;
+        self.current = bits & !STATUS_MASK;
+        for button in Button::ALL {
+            let frames = &mut self.hold_frames[button as usize];
+            if self.current & button.mask() != 0 {
+                *frames = frames.saturating_add(1);
+            } else {
+                *frames = 0;
+            }
+        }
+        let low_battery = data.pwr();
+        self.low_battery_changed = low_battery != self.low_battery;
+        self.low_battery = low_battery;
+    }
+
+    /// Returns true if the button is down this frame.
+    pub const fn held(&self, button: Button) -> bool {
+        self.current & button.mask() != 0
+    }
+
+    /// Returns true if the button went down this frame.
+    pub const fn pressed(&self, button: Button) -> bool {
+        self.current & !self.previous & button.mask() != 0
+    }
+
+    /// Returns true if the button went up this frame.
+    pub const fn released(&self, button: Button) -> bool {
+        !self.current & self.previous & button.mask() != 0
+    }
+
+    /// How many frames the button has been down, including this one.
+    /// Zero if the button is up.
+    pub const fn hold_frames(&self, button: Button) -> u16 {
+        self.hold_frames[button as usize]
+    }
+
+    /// Returns true if the button went down this frame, or if it has been held long enough to auto-repeat.
+    /// Useful for scrolling through menus.
+    pub const fn repeated(&self, button: Button) -> bool {
+        let frames = self.hold_frames(button);
+        if frames == 1 {
+            return true;
+        }
+        if self.repeat_interval == 0 || frames <= self.repeat_delay {
+            return false;
+        }
+        (frames - self.repeat_delay - 1).is_multiple_of(self.repeat_interval)
+    }
+
+    /// Returns true if every button in the chord is down this frame.
+    pub const fn chord_held(&self, chord: GamePadData) -> bool {
+        let mask = chord.into_bits() & !STATUS_MASK;
+        self.current & mask == mask
+    }
+
+    /// Returns true if every button in the chord is down this frame, but they weren't all down last frame.
+    /// The buttons can be pressed in any order.
+    pub const fn chord_pressed(&self, chord: GamePadData) -> bool {
+        let mask = chord.into_bits() & !STATUS_MASK;
+        self.current & mask == mask && self.previous & mask != mask
+    }
+
+    /// Returns true if the game pad is reporting low battery.
+    pub const fn low_battery(&self) -> bool {
+        self.low_battery
+    }
+
+    /// Returns true if the low battery flag changed this frame.
+    pub const fn low_battery_changed(&self) -> bool {
+        self.low_battery_changed
+    }
+
+    /// The state of every button this frame.
+    pub const fn raw(&self) -> GamePadData {
+        GamePadData::from_bits(self.current)
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTHING: GamePadData = GamePadData::new().with_sgn(true);
+    const A: GamePadData = NOTHING.with_a(true);
+    const A_AND_B: GamePadData = A.with_b(true);
+    const B: GamePadData = NOTHING.with_b(true);
+
+    fn feed(input: &mut InputState, frames: &[GamePadData]) {
+        for frame in frames {
+            input.update_with(*frame);
+        }
+    }
+
+    #[test]
+    fn edges_are_reported_for_one_frame() {
+        let mut input = InputState::new();
+        input.update_with(A);
+        assert!(input.pressed(Button::A) && input.held(Button::A));
+        assert!(!input.released(Button::A));
+
+        input.update_with(A);
+        assert!(!input.pressed(Button::A) && input.held(Button::A));
+
+        input.update_with(NOTHING);
+        assert!(input.released(Button::A) && !input.held(Button::A));
+
+        input.update_with(NOTHING);
+        assert!(!input.released(Button::A));
+    }
+
+    #[test]
+    fn status_bits_are_not_buttons() {
+        let mut input = InputState::new();
+        input.update_with(NOTHING.with_pwr(true));
+        assert_eq!(input.raw().into_bits(), 0);
+        assert!(Button::ALL.iter().all(|button| !input.held(*button)));
+    }
+
+    #[test]
+    fn hold_frames_count_up_and_reset() {
+        let mut input = InputState::new();
+        assert_eq!(input.hold_frames(Button::B), 0);
+        feed(&mut input, &[B, B, A_AND_B]);
+        assert_eq!(input.hold_frames(Button::B), 3);
+        assert_eq!(input.hold_frames(Button::A), 1);
+        input.update_with(A);
+        assert_eq!(input.hold_frames(Button::B), 0);
+        assert_eq!(input.hold_frames(Button::A), 2);
+    }
+
+    #[test]
+    fn auto_repeat_waits_for_the_delay_then_repeats_every_interval() {
+        let mut input = InputState::new().with_repeat(3, 2);
+        let mut repeats = [false; 10];
+        for repeat in &mut repeats {
+            input.update_with(A);
+            *repeat = input.repeated(Button::A);
+        }
+        // Frame 1 is the press, frames 2 and 3 are the delay, then every other frame repeats.
+        assert_eq!(
+            repeats,
+            [
+                true, false, false, true, false, true, false, true, false, true
+            ]
+        );
+
+        // Releasing and pressing again starts over.
+        input.update_with(NOTHING);
+        assert!(!input.repeated(Button::A));
+        input.update_with(A);
+        assert!(input.repeated(Button::A));
+        input.update_with(A);
+        assert!(!input.repeated(Button::A));
+    }
+
+    #[test]
+    fn without_repeat_only_the_press_counts() {
+        let mut input = InputState::new();
+        input.update_with(A);
+        assert!(input.repeated(Button::A));
+        for _ in 0..100 {
+            input.update_with(A);
+            assert!(!input.repeated(Button::A));
+        }
+    }
+
+    #[test]
+    fn chords_can_be_pressed_in_any_order() {
+        let chord = NOTHING.with_a(true).with_b(true);
+        let mut input = InputState::new();
+        input.update_with(A);
+        assert!(!input.chord_held(chord) && !input.chord_pressed(chord));
+        input.update_with(A_AND_B);
+        assert!(input.chord_held(chord) && input.chord_pressed(chord));
+        input.update_with(A_AND_B);
+        assert!(input.chord_held(chord) && !input.chord_pressed(chord));
+
+        let mut input = InputState::new();
+        feed(&mut input, &[B, A_AND_B]);
+        assert!(input.chord_pressed(chord));
+
+        // Extra buttons don't break the chord.
+        input.update_with(A_AND_B.with_sta(true));
+        assert!(input.chord_held(chord));
+    }
+
+    #[test]
+    fn low_battery_changes_are_reported_once() {
+        let low = NOTHING.with_pwr(true);
+        let mut input = InputState::new();
+        input.update_with(NOTHING);
+        assert!(!input.low_battery() && !input.low_battery_changed());
+        input.update_with(low);
+        assert!(input.low_battery() && input.low_battery_changed());
+        input.update_with(low);
+        assert!(input.low_battery() && !input.low_battery_changed());
+        input.update_with(NOTHING);
+        assert!(!input.low_battery() && input.low_battery_changed());
+    }
+}
@@ -0,0 +1,174 @@
+//! Control when interrupts can fire, and share data safely with interrupt handlers.
+//!
+//! An interrupt only fires while PSW.ID is clear, and only if its level is at least PSW.I.
+//! The levels are:
+//!
+//! | Interrupt     | Level |
+//! |---------------|-------|
+//! | Game pad      | 0     |
+//! | Timer         | 1     |
+//! | Game pak      | 2     |
+//! | Communication | 3     |
+//! | VIP           | 4     |
+//!
+//! While an interrupt handler runs, the CPU sets PSW.ID,
+//! so handlers never interrupt each other unless they call [`enable`].
+
+use core::{
+    cell::{Ref, RefCell, RefMut, UnsafeCell},
+    marker::PhantomData,
+};
+
+/// Interrupt disable
+const PSW_ID: u32 = 1 << 12;
+/// NMI pending. Set at reset, and blocks interrupts until cleared.
+const PSW_NP: u32 = 1 << 15;
+/// Interrupt level
+const PSW_I_SHIFT: u32 = 16;
+const PSW_I_MASK: u32 = 0xf << PSW_I_SHIFT;
+
+#[cfg(target_arch = "v810")]
+fn read_psw() -> u32 {
+    let psw: u32;
+    unsafe { core::arch::asm!("stsr psw, {}", out(reg) psw, options(nomem, nostack)) };
+    psw
+}
+
+/// Not marked `nomem`, so the compiler won't move memory accesses in or out of a critical section.
+#[cfg(target_arch = "v810")]
+fn write_psw(psw: u32) {
+    unsafe { core::arch::asm!("ldsr {}, psw", in(reg) psw, options(nostack)) };
+}
+
+#[cfg(not(target_arch = "v810"))]
+use crate::sys::mock::{psw as read_psw, set_psw as write_psw};
+
+/// Allow interrupts to fire.
+pub fn enable() {
+    write_psw(read_psw() & !(PSW_ID | PSW_NP));
+}
+
+/// Stop interrupts from firing until [`enable`] is called.
+pub fn disable() {
+    write_psw(read_psw() | PSW_ID);
+}
+
+/// Returns true if interrupts are allowed to fire.
+pub fn enabled() -> bool {
+    read_psw() & (PSW_ID | PSW_NP) == 0
+}
+
+/// Only allow interrupts with a level of at least `level` to fire.
+/// 0 allows every interrupt, 5 blocks them all.
+pub fn set_level(level: u8) {
+    assert!(level < 16, "interrupt level must be less than 16");
+    write_psw((read_psw() & !PSW_I_MASK) | ((level as u32) << PSW_I_SHIFT));
+}
+
+/// The lowest level of interrupt which is currently allowed to fire.
+pub fn level() -> u8 {
+    ((read_psw() & PSW_I_MASK) >> PSW_I_SHIFT) as u8
+}
+
+/// Proof that interrupts are disabled.
+#[derive(Clone, Copy)]
+pub struct CriticalSection<'cs> {
+    _lifetime: PhantomData<&'cs ()>,
+}
+
+impl CriticalSection<'_> {
+    /// # Safety
+    ///
+    /// Interrupts must be disabled for as long as this exists.
+    pub unsafe fn new() -> Self {
+        Self {
+            _lifetime: PhantomData,
+        }
+    }
+}
+
+/// Run `f` with interrupts disabled.
+/// Afterwards, interrupts are enabled again only if they were enabled before,
+/// so this is safe to nest and to call from interrupt handlers.
+pub fn free<R>(f: impl FnOnce(CriticalSection<'_>) -> R) -> R {
+    let previous = read_psw() & PSW_ID;
+    disable();
+    // SAFETY: we just disabled interrupts
+    let result = f(unsafe { CriticalSection::new() });
+    write_psw((read_psw() & !PSW_ID) | previous);
+    result
+}
+
+/// Data which can be shared between main code and interrupt handlers,
+/// but can only be accessed inside [`free`].
+///
+/// Wrap the data in a [`RefCell`] to mutate it:
+/// ```ignore
+/// static SCORE: Mutex<RefCell<u32>> = Mutex::new(RefCell::new(0));
+/// interrupt::free(|cs| *SCORE.borrow_ref_mut(cs) += 1);
+/// ```
+pub struct Mutex<T> {
+    value: UnsafeCell<T>,
+}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn borrow<'cs>(&'cs self, _cs: CriticalSection<'cs>) -> &'cs T {
+        // SAFETY: interrupts are disabled, so nothing can mutate this while the reference lives
+        unsafe { &*self.value.get() }
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T> Mutex<RefCell<T>> {
+    /// Borrow the value inside the [`RefCell`]. Panics if it's mutably borrowed.
+    pub fn borrow_ref<'cs>(&'cs self, cs: CriticalSection<'cs>) -> Ref<'cs, T> {
+        self.borrow(cs).borrow()
+    }
+
+    /// Mutably borrow the value inside the [`RefCell`]. Panics if it's already borrowed.
+    pub fn borrow_ref_mut<'cs>(&'cs self, cs: CriticalSection<'cs>) -> RefMut<'cs, T> {
+        self.borrow(cs).borrow_mut()
+    }
+
+    /// Replace the value inside the [`RefCell`], returning the old one.
+    pub fn replace<'cs>(&'cs self, cs: CriticalSection<'cs>, value: T) -> T {
+        self.borrow(cs).replace(value)
+    }
+}
+
+// SAFETY: the value is only ever accessed with interrupts disabled,
+// and there's only one core, so nothing else can access it at the same time.
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+#[cfg(feature = "critical-section")]
+mod critical_section_impl {
+    use super::{PSW_ID, read_psw, write_psw};
+
+    struct VbCriticalSection;
+    critical_section::set_impl!(VbCriticalSection);
+
+    unsafe impl critical_section::Impl for VbCriticalSection {
+        unsafe fn acquire() -> critical_section::RawRestoreState {
+            let psw = read_psw();
+            write_psw(psw | PSW_ID);
+            psw & PSW_ID
+        }
+
+        unsafe fn release(previous: critical_section::RawRestoreState) {
+            write_psw((read_psw() & !PSW_ID) | previous);
+        }
+    }
+}
@@ -1,18 +1,38 @@
 #![no_std]
-#![cfg_attr(target_arch = "v810", feature(macro_metavar_expr_concat))]
-#![cfg(target_arch = "v810")]
+#![cfg_attr(
+    any(target_arch = "v810", feature = "host-mock"),
+    feature(macro_metavar_expr_concat)
+)]
+#![cfg(any(target_arch = "v810", feature = "host-mock"))]
 
+#[cfg(target_arch = "v810")]
 mod builtins;
+#[cfg(all(feature = "crash-screen", target_arch = "v810"))]
+mod crash;
+#[cfg(feature = "alloc")]
+pub mod heap;
+pub mod input;
+pub mod interrupt;
+pub mod link;
+pub mod log;
 pub mod macros;
+pub mod math;
+pub mod rand;
+#[cfg(target_arch = "v810")]
 mod reset;
+pub mod save;
+#[cfg(target_arch = "v810")]
+pub mod stack;
 pub mod stdio;
 pub mod sys;
+pub mod timer;
 
-use core::panic::PanicInfo;
-
+#[cfg(target_arch = "v810")]
 #[panic_handler]
-fn panic(panic: &PanicInfo<'_>) -> ! {
+fn panic(panic: &core::panic::PanicInfo<'_>) -> ! {
     println!("{panic}");
+    #[cfg(feature = "crash-screen")]
+    crash::show_panic(panic);
     loop {
         sys::halt();
     }
@@ -0,0 +1,779 @@
+use core::{
+    cell::Cell,
+    sync::atomic::{
+        AtomicBool, AtomicU8, AtomicUsize,
+        Ordering::{Relaxed, Release},
+        compiler_fence,
+    },
+};
+
+use crate::sys::hardware::{self, CommClock, CommControlData};
+
+/// Sent whenever a console has nothing else to say.
+const IDLE: u8 = 0x00;
+/// Precedes any data byte which would otherwise look like IDLE or ESCAPE.
+const ESCAPE: u8 = 0x7d;
+/// Marks the start of a packet.
+const PACKET_SYNC: u8 = 0xa5;
+/// Sync byte, length byte, and two checksum bytes.
+const PACKET_OVERHEAD: usize = 4;
+
+/// How long the master waits before clocking another byte, so the slave has time to get ready.
+const MASTER_DELAY: u32 = 32;
+
+/// Which console drives the clock during transfers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Master,
+    Slave,
+}
+
+/// The connection between two consoles.
+/// [`Hardware`] talks to the real link port, but a [`Link`] can run on top of anything which implements this.
+pub trait Port {
+    /// Begin exchanging a byte with the other console.
+    fn begin(&self, role: Role, value: u8);
+    /// Returns true while an exchange is in progress.
+    fn busy(&self) -> bool;
+    /// The byte received during the last exchange.
+    fn received(&self) -> u8;
+    /// Acknowledge the interrupt which fires when an exchange is complete.
+    fn acknowledge(&self);
+    /// Returns true if either console is signalling on COMCNT.
+    fn signalled(&self) -> bool;
+    /// Start or stop signalling on COMCNT.
+    fn set_signal(&self, active: bool);
+}
+
+/// The link port on the back of the Virtual Boy.
+pub struct Hardware;
+
+impl Port for Hardware {
+    fn begin(&self, role: Role, value: u8) {
+        let clock = match role {
+            Role::Master => {
+                for _ in 0..MASTER_DELAY {
+                    core::hint::spin_loop();
+                }
+                CommClock::Internal
+            }
+            Role::Slave => CommClock::External,
+        };
+        hardware::CDTR.write(value);
+        hardware::CCR.write(
+            CommControlData::new()
+                .with_c_clk_sel(clock)
+                .with_c_start(true),
+        );
+    }
+
+    fn busy(&self) -> bool {
+        hardware::CCR.read().c_stat()
+    }
+
+    fn received(&self) -> u8 {
+        hardware::CDRR.read()
+    }
+
+    fn acknowledge(&self) {
+        hardware::CCR.write(CommControlData::new().with_c_int_inh(true));
+    }
+
+    fn signalled(&self) -> bool {
+        !hardware::CCSR.read().cc_rd()
+    }
+
+    fn set_signal(&self, active: bool) {
+        hardware::CCSR.write(hardware::CCSR.read().with_cc_wr(!active));
+    }
+}
+
+/// An imaginary link cable, for running both ends of a [`Link`] in one program (like a test on the host).
+/// An exchange completes as soon as both ends have begun one, as long as one of them is the master.
+/// Nothing raises interrupts for you: call [`Link::on_interrupt`] whenever [`LoopbackCable::take_interrupt`] says so.
+pub struct LoopbackCable {
+    ends: [LoopbackEnd; 2],
+}
+
+#[derive(Default)]
+struct LoopbackEnd {
+    outgoing: Cell<Option<(Role, u8)>>,
+    received: Cell<u8>,
+    interrupt: Cell<bool>,
+    signal: Cell<bool>,
+}
+
+impl LoopbackCable {
+    pub fn new() -> Self {
+        Self {
+            ends: Default::default(),
+        }
+    }
+
+    /// One end of the cable, 0 or 1.
+    pub fn port(&self, end: usize) -> LoopbackPort<'_> {
+        assert!(end < 2, "a cable only has two ends");
+        LoopbackPort { cable: self, end }
+    }
+
+    /// Returns true if an exchange has completed on this end since the last call.
+    pub fn take_interrupt(&self, end: usize) -> bool {
+        self.ends[end].interrupt.replace(false)
+    }
+
+    fn exchange(&self) {
+        let [a, b] = &self.ends;
+        let (Some((a_role, a_value)), Some((b_role, b_value))) =
+            (a.outgoing.get(), b.outgoing.get())
+        else {
+            return;
+        };
+        if a_role == Role::Slave && b_role == Role::Slave {
+            // Nobody is driving the clock.
+            return;
+        }
+        for (end, value) in [(a, b_value), (b, a_value)] {
+            end.outgoing.set(None);
+            end.received.set(value);
+            end.interrupt.set(true);
+        }
+    }
+}
+
+impl Default for LoopbackCable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One end of a [`LoopbackCable`].
+pub struct LoopbackPort<'a> {
+    cable: &'a LoopbackCable,
+    end: usize,
+}
+
+impl LoopbackPort<'_> {
+    fn this_end(&self) -> &LoopbackEnd {
+        &self.cable.ends[self.end]
+    }
+}
+
+impl Port for LoopbackPort<'_> {
+    fn begin(&self, role: Role, value: u8) {
+        self.this_end().outgoing.set(Some((role, value)));
+        self.cable.exchange();
+    }
+
+    fn busy(&self) -> bool {
+        self.this_end().outgoing.get().is_some()
+    }
+
+    fn received(&self) -> u8 {
+        self.this_end().received.get()
+    }
+
+    fn acknowledge(&self) {
+        self.this_end().interrupt.set(false);
+    }
+
+    fn signalled(&self) -> bool {
+        self.cable.ends.iter().any(|end| end.signal.get())
+    }
+
+    fn set_signal(&self, active: bool) {
+        self.this_end().signal.set(active);
+    }
+}
+
+/// A two-player link over the link cable.
+///
+/// Bytes are exchanged in the background by the communication interrupt,
+/// so call [`Link::on_interrupt`] from `communication_interrupt_handler!`
+/// and [`Link::poll`] once per frame.
+///
+/// Data is escaped on the wire, so every byte you send arrives exactly once and in order,
+/// unless the other side's receive queue overflows.
+pub struct Link<P: Port, const N: usize> {
+    port: P,
+    role: AtomicU8,
+    in_flight: AtomicBool,
+    tx: ByteQueue<N>,
+    rx: ByteQueue<N>,
+    tx_escaped: AtomicU8,
+    rx_escaped: AtomicBool,
+    overflowed: AtomicBool,
+}
+
+impl<const N: usize> Link<Hardware, N> {
+    pub const fn new() -> Self {
+        Self::with_port(Hardware)
+    }
+}
+
+impl<const N: usize> Default for Link<Hardware, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: Port, const N: usize> Link<P, N> {
+    pub const fn with_port(port: P) -> Self {
+        Self {
+            port,
+            role: AtomicU8::new(0),
+            in_flight: AtomicBool::new(false),
+            tx: ByteQueue::new(),
+            rx: ByteQueue::new(),
+            tx_escaped: AtomicU8::new(0),
+            rx_escaped: AtomicBool::new(false),
+            overflowed: AtomicBool::new(false),
+        }
+    }
+
+    /// Decide which console is the master.
+    /// The first console to call this becomes the master, and signals the other over COMCNT.
+    /// The second sees that signal and becomes the slave.
+    ///
+    /// If both consoles negotiate at the exact same time, both will decide to be the master.
+    /// Negotiating in response to player input (like pressing START) makes that unlikely.
+    pub fn negotiate(&self) -> Role {
+        let role = if self.port.signalled() {
+            Role::Slave
+        } else {
+            self.port.set_signal(true);
+            Role::Master
+        };
+        self.set_role(role);
+        role
+    }
+
+    /// Skip negotiation and use the given role.
+    pub fn set_role(&self, role: Role) {
+        self.role.store(
+            match role {
+                Role::Master => 1,
+                Role::Slave => 2,
+            },
+            Relaxed,
+        );
+        if role == Role::Slave && !self.in_flight.load(Relaxed) {
+            // The slave always has a transfer ready, so it never misses the master's clock.
+            self.begin(role);
+        }
+    }
+
+    pub fn role(&self) -> Option<Role> {
+        match self.role.load(Relaxed) {
+            1 => Some(Role::Master),
+            2 => Some(Role::Slave),
+            _ => None,
+        }
+    }
+
+    /// Stop talking to the other console, and throw away anything left to send or receive.
+    pub fn disconnect(&self) {
+        self.role.store(0, Relaxed);
+        self.port.set_signal(false);
+        self.tx.clear();
+        self.rx.clear();
+        self.tx_escaped.store(0, Relaxed);
+        self.rx_escaped.store(false, Relaxed);
+    }
+
+    /// Kick off a transfer if the line is idle. Call this once per frame.
+    /// The master polls the slave this way, so the slave can send data even when the master has none.
+    pub fn poll(&self) {
+        if self.role() == Some(Role::Master) && !self.in_flight.load(Relaxed) {
+            self.begin(Role::Master);
+        }
+    }
+
+    /// Handle a communication interrupt. Call this from `communication_interrupt_handler!`.
+    pub fn on_interrupt(&self) {
+        self.port.acknowledge();
+        if !self.in_flight.load(Relaxed) {
+            return;
+        }
+        self.in_flight.store(false, Relaxed);
+        let received = self.port.received();
+        self.receive_raw(received);
+
+        match self.role() {
+            Some(Role::Slave) => self.begin(Role::Slave),
+            // Keep clocking while either side has more to say.
+            Some(Role::Master)
+                if received != IDLE
+                    || !self.tx.is_empty()
+                    || self.tx_escaped.load(Relaxed) != 0 =>
+            {
+                self.begin(Role::Master);
+            }
+            Some(Role::Master) | None => {}
+        }
+    }
+
+    /// Returns true if any received data was lost because the receive queue was full.
+    /// Clears the flag.
+    pub fn take_overflow(&self) -> bool {
+        let overflowed = self.overflowed.load(Relaxed);
+        self.overflowed.store(false, Relaxed);
+        overflowed
+    }
+
+    /// Queue a byte to send. Returns false if the send queue is full.
+    pub fn send_byte(&self, value: u8) -> bool {
+        let sent = self.tx.push(value);
+        self.poll();
+        sent
+    }
+
+    pub fn receive_byte(&self) -> Option<u8> {
+        self.rx.pop()
+    }
+
+    /// Queue as much of `data` as fits in the send queue. Returns the number of bytes queued.
+    pub fn send_block(&self, data: &[u8]) -> usize {
+        let mut sent = 0;
+        for byte in data {
+            if !self.tx.push(*byte) {
+                break;
+            }
+            sent += 1;
+        }
+        self.poll();
+        sent
+    }
+
+    /// Fill `buffer` with received bytes. Returns the number of bytes received.
+    pub fn receive_block(&self, buffer: &mut [u8]) -> usize {
+        let mut received = 0;
+        for dst in buffer.iter_mut() {
+            let Some(byte) = self.rx.pop() else {
+                break;
+            };
+            *dst = byte;
+            received += 1;
+        }
+        received
+    }
+
+    /// Queue a packet to send. Returns false if it doesn't fit in the send queue.
+    pub fn send_packet(&self, data: &[u8]) -> bool {
+        assert!(data.len() <= u8::MAX as usize, "packet too long");
+        if self.tx.remaining_capacity() < data.len() + PACKET_OVERHEAD {
+            return false;
+        }
+        let checksum = packet_checksum(data);
+        self.tx.push(PACKET_SYNC);
+        self.tx.push(data.len() as u8);
+        for byte in data {
+            self.tx.push(*byte);
+        }
+        self.tx.push((checksum >> 8) as u8);
+        self.tx.push(checksum as u8);
+        self.poll();
+        true
+    }
+
+    /// Read received bytes into `reader` until a full packet arrives.
+    /// Returns `None` if no packet is ready yet.
+    pub fn receive_packet<'a, const M: usize>(
+        &self,
+        reader: &'a mut PacketReader<M>,
+    ) -> Option<Result<&'a [u8], PacketError>> {
+        loop {
+            let byte = self.rx.pop()?;
+            match reader.push(byte) {
+                Some(Ok(len)) => return Some(Ok(&reader.buffer[..len])),
+                Some(Err(error)) => return Some(Err(error)),
+                None => {}
+            }
+        }
+    }
+
+    fn begin(&self, role: Role) {
+        let value = self.next_raw().unwrap_or(IDLE);
+        self.in_flight.store(true, Relaxed);
+        self.port.begin(role, value);
+    }
+
+    fn next_raw(&self) -> Option<u8> {
+        let escaped = self.tx_escaped.load(Relaxed);
+        if escaped != 0 {
+            self.tx_escaped.store(0, Relaxed);
+            return Some(escaped);
+        }
+        let value = self.tx.pop()?;
+        if value == IDLE || value == ESCAPE {
+            self.tx_escaped.store(value ^ 0x20, Relaxed);
+            Some(ESCAPE)
+        } else {
+            Some(value)
+        }
+    }
+
+    fn receive_raw(&self, raw: u8) {
+        let value = if self.rx_escaped.load(Relaxed) {
+            self.rx_escaped.store(false, Relaxed);
+            raw ^ 0x20
+        } else if raw == ESCAPE {
+            self.rx_escaped.store(true, Relaxed);
+            return;
+        } else if raw == IDLE {
+            return;
+        } else {
+            raw
+        };
+        if !self.rx.push(value) {
+            self.overflowed.store(true, Relaxed);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketError {
+    /// The packet was longer than the reader's buffer.
+    TooLong,
+    /// The packet was corrupted in transit.
+    BadChecksum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReaderState {
+    Sync,
+    Length,
+    Payload,
+    ChecksumHigh,
+    ChecksumLow(u8),
+}
+
+/// Reassembles packets from a stream of bytes.
+/// Holds up to `N` bytes of payload.
+pub struct PacketReader<const N: usize> {
+    state: ReaderState,
+    buffer: [u8; N],
+    len: usize,
+    pos: usize,
+}
+
+impl<const N: usize> PacketReader<N> {
+    pub const fn new() -> Self {
+        Self {
+            state: ReaderState::Sync,
+            buffer: [0; N],
+            len: 0,
+            pos: 0,
+        }
+    }
+
+    /// Feed the next byte of the stream to the reader.
+    /// Returns the length of the packet once a full packet has arrived.
+    /// After an error, the reader skips ahead to the start of the next packet.
+    pub fn push(&mut self, byte: u8) -> Option<Result<usize, PacketError>> {
+        match self.state {
+            ReaderState::Sync => {
+                if byte == PACKET_SYNC {
+                    self.state = ReaderState::Length;
+                }
+            }
+            ReaderState::Length => {
+                let len = byte as usize;
+                if len > N {
+                    self.state = ReaderState::Sync;
+                    return Some(Err(PacketError::TooLong));
+                }
+                self.len = len;
+                self.pos = 0;
+                self.state = if len == 0 {
+                    ReaderState::ChecksumHigh
+                } else {
+                    ReaderState::Payload
+                };
+            }
+            ReaderState::Payload => {
+                self.buffer[self.pos] = byte;
+                self.pos += 1;
+                if self.pos == self.len {
+                    self.state = ReaderState::ChecksumHigh;
+                }
+            }
+            ReaderState::ChecksumHigh => {
+                self.state = ReaderState::ChecksumLow(byte);
+            }
+            ReaderState::ChecksumLow(high) => {
+                self.state = ReaderState::Sync;
+                let checksum = ((high as u16) << 8) | byte as u16;
+                if checksum != packet_checksum(self.packet()) {
+                    return Some(Err(PacketError::BadChecksum));
+                }
+                return Some(Ok(self.len));
+            }
+        }
+        None
+    }
+
+    /// The payload of the last packet the reader received.
+    pub fn packet(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+impl<const N: usize> Default for PacketReader<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fletcher-16 over the length and payload of a packet.
+fn packet_checksum(data: &[u8]) -> u16 {
+    let mut a = data.len() as u16 % 255;
+    let mut b = a;
+    for byte in data {
+        a = (a + *byte as u16) % 255;
+        b = (b + a) % 255;
+    }
+    (b << 8) | a
+}
+
+/// A fixed-size byte queue, which one writer and one reader can share without locking
+/// (for example, main code and an interrupt handler).
+pub struct ByteQueue<const N: usize> {
+    buffer: [AtomicU8; N],
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+impl<const N: usize> ByteQueue<N> {
+    pub const fn new() -> Self {
+        assert!(N.is_power_of_two(), "queue size must be a power of two");
+        Self {
+            buffer: [const { AtomicU8::new(0) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tail
+            .load(Relaxed)
+            .wrapping_sub(self.head.load(Relaxed))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn remaining_capacity(&self) -> usize {
+        N - self.len()
+    }
+
+    /// Add a byte to the end of the queue. Returns false if the queue is full.
+    pub fn push(&self, value: u8) -> bool {
+        let tail = self.tail.load(Relaxed);
+        if tail.wrapping_sub(self.head.load(Relaxed)) == N {
+            return false;
+        }
+        self.buffer[tail % N].store(value, Relaxed);
+        compiler_fence(Release);
+        self.tail.store(tail.wrapping_add(1), Relaxed);
+        true
+    }
+
+    /// Remove a byte from the front of the queue.
+    pub fn pop(&self) -> Option<u8> {
+        let head = self.head.load(Relaxed);
+        if head == self.tail.load(Relaxed) {
+            return None;
+        }
+        let value = self.buffer[head % N].load(Relaxed);
+        compiler_fence(Release);
+        self.head.store(head.wrapping_add(1), Relaxed);
+        Some(value)
+    }
+
+    /// Throw away everything in the queue. Only call this while nothing else is reading.
+    pub fn clear(&self) {
+        self.head.store(self.tail.load(Relaxed), Relaxed);
+    }
+}
+
+impl<const N: usize> Default for ByteQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestLink<'a> = Link<LoopbackPort<'a>, 64>;
+
+    fn connect(cable: &LoopbackCable) -> (TestLink<'_>, TestLink<'_>) {
+        let master = Link::with_port(cable.port(0));
+        let slave = Link::with_port(cable.port(1));
+        assert_eq!(master.negotiate(), Role::Master);
+        assert_eq!(slave.negotiate(), Role::Slave);
+        (master, slave)
+    }
+
+    /// Poll once, like at the start of a frame, then handle interrupts until the line goes quiet.
+    fn run_frame(cable: &LoopbackCable, links: [&TestLink<'_>; 2]) {
+        for link in links {
+            link.poll();
+        }
+        for _ in 0..10_000 {
+            let mut handled = false;
+            for (end, link) in links.into_iter().enumerate() {
+                if cable.take_interrupt(end) {
+                    link.on_interrupt();
+                    handled = true;
+                }
+            }
+            if !handled {
+                return;
+            }
+        }
+        panic!("the link never went quiet");
+    }
+
+    fn receive_all(link: &TestLink<'_>) -> ([u8; 64], usize) {
+        let mut buffer = [0; 64];
+        let len = link.receive_block(&mut buffer);
+        (buffer, len)
+    }
+
+    #[test]
+    fn negotiation_picks_one_master() {
+        let cable = LoopbackCable::new();
+        let (master, slave) = connect(&cable);
+        assert_eq!(master.role(), Some(Role::Master));
+        assert_eq!(slave.role(), Some(Role::Slave));
+        master.disconnect();
+        slave.disconnect();
+        assert_eq!(master.role(), None);
+        assert!(!cable.port(0).signalled());
+    }
+
+    #[test]
+    fn bytes_arrive_in_order_in_both_directions() {
+        let cable = LoopbackCable::new();
+        let (master, slave) = connect(&cable);
+        // IDLE and ESCAPE have to be escaped on the wire.
+        let to_slave = [1, IDLE, 2, ESCAPE, ESCAPE ^ 0x20, IDLE, 0xff];
+        let to_master = [IDLE, IDLE, 9, ESCAPE];
+        assert_eq!(master.send_block(&to_slave), to_slave.len());
+        assert_eq!(slave.send_block(&to_master), to_master.len());
+        run_frame(&cable, [&master, &slave]);
+
+        let (buffer, len) = receive_all(&slave);
+        assert_eq!(&buffer[..len], &to_slave);
+        let (buffer, len) = receive_all(&master);
+        assert_eq!(&buffer[..len], &to_master);
+        assert!(!master.take_overflow());
+        assert!(!slave.take_overflow());
+    }
+
+    #[test]
+    fn the_slave_can_send_when_the_master_has_nothing_to_say() {
+        let cable = LoopbackCable::new();
+        let (master, slave) = connect(&cable);
+        run_frame(&cable, [&master, &slave]);
+        assert!(slave.send_byte(42));
+        // The slave's next byte was loaded before 42 was queued,
+        // so the master hears IDLE once before it hears 42.
+        run_frame(&cable, [&master, &slave]);
+        assert_eq!(master.receive_byte(), None);
+        run_frame(&cable, [&master, &slave]);
+        assert_eq!(master.receive_byte(), Some(42));
+        assert_eq!(master.receive_byte(), None);
+    }
+
+    #[test]
+    fn packets_are_framed() {
+        let cable = LoopbackCable::new();
+        let (master, slave) = connect(&cable);
+        assert!(master.send_packet(&[]));
+        assert!(master.send_packet(b"hello"));
+        assert!(master.send_packet(&[PACKET_SYNC, IDLE, ESCAPE]));
+        run_frame(&cable, [&master, &slave]);
+
+        let mut reader = PacketReader::<16>::new();
+        assert_eq!(slave.receive_packet(&mut reader), Some(Ok(&[][..])));
+        assert_eq!(slave.receive_packet(&mut reader), Some(Ok(&b"hello"[..])));
+        assert_eq!(
+            slave.receive_packet(&mut reader),
+            Some(Ok(&[PACKET_SYNC, IDLE, ESCAPE][..]))
+        );
+        assert_eq!(slave.receive_packet(&mut reader), None);
+    }
+
+    #[test]
+    fn packets_which_dont_fit_are_not_queued() {
+        let cable = LoopbackCable::new();
+        let (master, _slave) = connect(&cable);
+        assert!(!master.send_packet(&[1; 61]));
+        assert!(master.send_packet(&[1; 60]));
+    }
+
+    #[test]
+    fn corrupted_packets_fail_their_checksum() {
+        let cable = LoopbackCable::new();
+        let (master, slave) = connect(&cable);
+        let checksum = packet_checksum(b"abc");
+        let corrupted = [
+            PACKET_SYNC,
+            3,
+            b'a',
+            b'x',
+            b'c',
+            (checksum >> 8) as u8,
+            checksum as u8,
+        ];
+        master.send_block(&corrupted);
+        master.send_packet(b"abc");
+        run_frame(&cable, [&master, &slave]);
+
+        let mut reader = PacketReader::<16>::new();
+        assert_eq!(
+            slave.receive_packet(&mut reader),
+            Some(Err(PacketError::BadChecksum))
+        );
+        assert_eq!(slave.receive_packet(&mut reader), Some(Ok(&b"abc"[..])));
+    }
+
+    #[test]
+    fn the_reader_resyncs_after_noise_and_oversized_packets() {
+        let cable = LoopbackCable::new();
+        let (master, slave) = connect(&cable);
+        // Noise before the first sync byte is skipped.
+        master.send_block(&[1, 2, 3]);
+        master.send_packet(b"one");
+        // Too long for the reader, so it skips ahead to the next sync byte.
+        master.send_packet(&[7; 20]);
+        master.send_packet(b"two");
+        run_frame(&cable, [&master, &slave]);
+
+        let mut reader = PacketReader::<16>::new();
+        assert_eq!(slave.receive_packet(&mut reader), Some(Ok(&b"one"[..])));
+        assert_eq!(
+            slave.receive_packet(&mut reader),
+            Some(Err(PacketError::TooLong))
+        );
+        assert_eq!(slave.receive_packet(&mut reader), Some(Ok(&b"two"[..])));
+    }
+
+    #[test]
+    fn a_full_receive_queue_overflows() {
+        let cable = LoopbackCable::new();
+        let (master, slave) = connect(&cable);
+        master.send_block(&[5; 64]);
+        run_frame(&cable, [&master, &slave]);
+        master.send_byte(6);
+        run_frame(&cable, [&master, &slave]);
+        assert!(slave.take_overflow());
+        assert!(!slave.take_overflow());
+        let (buffer, len) = receive_all(&slave);
+        assert_eq!(&buffer[..len], &[5; 64]);
+    }
+}
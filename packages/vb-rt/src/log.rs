@@ -0,0 +1,127 @@
+//! Leveled logging and profiling markers, for the Lemur emulator.
+//!
+//! Log with [`error!`](crate::error), [`warn!`](crate::warn), [`info!`](crate::info),
+//! [`debug!`](crate::debug) and [`trace!`](crate::trace).
+//! Messages go to STDOUT, which real hardware ignores.
+//!
+//! Messages more verbose than [`MAX_LEVEL`] are compiled out.
+//! By default, debug builds keep every message and release builds keep none.
+//! To choose a level yourself, enable one of the `log-off`, `log-error`, `log-warn`,
+//! `log-info`, `log-debug` or `log-trace` features. If several are enabled, the quietest one wins.
+
+use core::{
+    ffi::CStr,
+    fmt::{self, Write as _},
+};
+
+use crate::{stdio::OutWriter, sys::hardware};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+            Self::Trace => "TRACE",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// The most verbose level which gets logged, or `None` if logging is off.
+pub const MAX_LEVEL: Option<Level> = if cfg!(feature = "log-off") {
+    None
+} else if cfg!(feature = "log-error") {
+    Some(Level::Error)
+} else if cfg!(feature = "log-warn") {
+    Some(Level::Warn)
+} else if cfg!(feature = "log-info") {
+    Some(Level::Info)
+} else if cfg!(feature = "log-debug") {
+    Some(Level::Debug)
+} else if cfg!(any(feature = "log-trace", debug_assertions)) {
+    Some(Level::Trace)
+} else {
+    None
+};
+
+/// True if messages at this level get logged.
+pub const fn enabled(level: Level) -> bool {
+    match MAX_LEVEL {
+        Some(max) => level as u8 <= max as u8,
+        None => false,
+    }
+}
+
+/// True if [`profile_scope!`](crate::profile_scope) emits markers.
+/// That's in debug builds, or with the `profiling` feature.
+pub const PROFILING: bool = cfg!(any(debug_assertions, feature = "profiling"));
+
+#[doc(hidden)]
+pub fn write(level: Level, module: &str, args: fmt::Arguments<'_>) {
+    let mut writer = OutWriter;
+    let _ = write!(writer, "[{level} {module}] ");
+    let _ = writer.write_fmt(args);
+    writer.write_nl();
+}
+
+#[doc(hidden)]
+#[cold]
+#[track_caller]
+pub fn assert_failed(condition: &str, args: Option<fmt::Arguments<'_>>) -> ! {
+    if PROFILING {
+        hardware::emit_profiling_marker(c"assertion failed");
+    }
+    match args {
+        Some(args) => panic!("assertion failed: {condition}: {args}"),
+        None => panic!("assertion failed: {condition}"),
+    }
+}
+
+/// Emits a profiling marker when created, and another when dropped.
+/// Create one with [`profile_scope!`](crate::profile_scope).
+#[must_use = "the scope ends as soon as this is dropped"]
+pub struct ProfileScope {
+    end: &'static CStr,
+}
+
+impl ProfileScope {
+    #[doc(hidden)]
+    pub fn new(begin: &'static CStr, end: &'static CStr) -> Self {
+        if PROFILING {
+            hardware::emit_profiling_marker(begin);
+        }
+        Self { end }
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        if PROFILING {
+            hardware::emit_profiling_marker(self.end);
+        }
+    }
+}
+
+#[doc(hidden)]
+pub const fn marker(bytes: &'static [u8]) -> &'static CStr {
+    match CStr::from_bytes_with_nul(bytes) {
+        Ok(marker) => marker,
+        Err(_) => panic!("Profiling markers can't contain null bytes"),
+    }
+}
@@ -1,4 +1,12 @@
+pub mod column_table;
+#[cfg(target_arch = "v810")]
 pub mod handlers;
 pub mod header;
+#[cfg(feature = "alloc")]
+pub mod heap;
+pub mod log;
 pub mod main;
+pub mod ram;
+pub mod stack;
 pub mod stdio;
+pub mod wait_states;
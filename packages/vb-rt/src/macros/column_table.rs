@@ -0,0 +1,19 @@
+#[macro_export]
+/// Replace the column tables which are loaded at reset.
+/// The column tables control how long the display's LEDs stay lit in each column,
+/// which evens out the brightness across the screen.
+/// Pass one table for both eyes, or a different table for each.
+/// ```ignore
+/// vb_rt::column_table!(ColumnTable::DEFAULT.with_repeat(1));
+/// vb_rt::column_table!(left: LEFT_TABLE, right: RIGHT_TABLE);
+/// ```
+/// To change them after reset, use `vb_graphics::column_table`.
+macro_rules! column_table {
+    (left: $left:expr, right: $right:expr $(,)?) => {
+        #[unsafe(no_mangle)]
+        static _vb_rt_column_table: [$crate::sys::vip::ColumnTable; 2] = [$left, $right];
+    };
+    ($table:expr) => {
+        $crate::column_table!(left: $table, right: $table);
+    };
+}
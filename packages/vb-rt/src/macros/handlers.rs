@@ -24,7 +24,7 @@ macro_rules! handler {
 
 macro_rules! in2rupt_handlers{
     () => {};
-    ($(#[$attr:meta])* $macro:ident($handler:ident); $($rest:tt)*) => {
+    ($(#[$attr:meta])* $macro:ident($handler:ident) $(in $section:literal)? $(before $($hook:ident)::+)?; $($rest:tt)*) => {
         handler!($handler);
 
         #[macro_export]
@@ -33,10 +33,21 @@ macro_rules! in2rupt_handlers{
             ($body:block) => {
                 #[interrupt]
                 #[unsafe(no_mangle)]
+                $(#[unsafe(link_section = $section)])?
                 pub fn $handler() {
+                    $($crate::$($hook)::+();)?
                     $body
                 }
             };
+            ($callback:path) => {
+                #[interrupt]
+                #[unsafe(no_mangle)]
+                $(#[unsafe(link_section = $section)])?
+                pub fn $handler() {
+                    $($crate::$($hook)::+();)?
+                    $callback()
+                }
+            };
         }
         in2rupt_handlers!($($rest)*);
     };
@@ -52,6 +63,7 @@ in2rupt_handlers! {
     game_pad_interrupt_handler(_vb_rt_game_pad_handler);
 
     /// Define a handler to run on timer interrupts.
+    /// The handler should call `vb_rt::timer::acknowledge_interrupts`, or it will fire again immediately.
     timer_interrupt_handler(_vb_rt_timer_handler);
 
     /// Define a handler to run on game pak interrupts.
@@ -62,7 +74,9 @@ in2rupt_handlers! {
     communication_interrupt_handler(_vb_rt_communication_handler);
 
     /// Define a handler to run on VIP interrupts.
-    vip_interrupt_handler(_vb_rt_vip_handler);
+    /// The handler runs from WRAM, since it usually runs every frame.
+    /// With the `stack-canary` feature, this checks for stack overflow before running your handler.
+    vip_interrupt_handler(_vb_rt_vip_handler) in ".ramtext._vb_rt_vip_handler" before stack::on_vip_interrupt;
 
     /// Define a handler to run on floating point exceptions.
     fp_exception_handler(_vb_rt_fp_exception_handler);
@@ -1,33 +1,53 @@
+mod sjis_table;
+
 #[macro_export]
 /// Define the ROM header for your game.
-/// It should include a <=20 character game tile, a 4 character maker code, and a 2 character game code.
+/// It should include a <=20 byte game title, a 2 character maker code, and a 4 character game code.
 /// Try to choose something unique.
+///
+/// The title is stored as Shift-JIS, so it can use ASCII, kana (half-width or full-width) and JIS X 0208 kanji.
+/// Full-width characters take up 2 bytes each.
+///
+/// Optionally, pass a version number to store in the header. It defaults to 1.
+/// ```ignore
+/// vb_rt::rom_header!("ゲーム", "SG", "GAME", version: 2);
+/// ```
 macro_rules! rom_header {
-    ($game_title:expr, $maker_code:expr, $game_code:expr) => {
+    ($game_title:expr, $maker_code:expr, $game_code:expr $(,)?) => {
+        $crate::rom_header!($game_title, $maker_code, $game_code, version: 1);
+    };
+    ($game_title:expr, $maker_code:expr, $game_code:expr, version: $version:expr $(,)?) => {
         #[unsafe(link_section = ".rom_header")]
         #[used]
         pub static ROM_HEADER: [u8; 32] =
-            vb_rt::macros::header::rom_header($game_title, $maker_code, $game_code);
+            $crate::macros::header::rom_header($game_title, $maker_code, $game_code, $version);
     };
 }
 
-pub const fn rom_header(game_title: &str, maker_code: &str, game_code: &str) -> [u8; 32] {
+pub const fn rom_header(
+    game_title: &str,
+    maker_code: &str,
+    game_code: &str,
+    version: u8,
+) -> [u8; 32] {
     let mut result = [0; 32];
 
-    // This should be shift-jis
-    let game_title_bytes = game_title.as_bytes();
-    assert!(
-        game_title_bytes.len() <= 20,
-        "Game title must be <= 20 bytes"
-    );
+    let (game_title_bytes, game_title_len) = shift_jis::<20>(game_title);
     let mut idx = 0;
-    while idx < game_title_bytes.len() {
+    while idx < game_title_len {
         result[idx] = game_title_bytes[idx];
         idx += 1;
     }
 
     let maker_code_bytes = maker_code.as_bytes();
-    assert!(maker_code_bytes.len() == 2, "Maker code must be 2 bytes");
+    assert!(
+        maker_code_bytes.len() == 2,
+        "Maker code must be 2 characters"
+    );
+    assert!(
+        is_code(maker_code_bytes),
+        "Maker code must be uppercase ASCII letters or digits"
+    );
     let mut idx = 0;
     while idx < maker_code_bytes.len() {
         result[idx + 0x19] = maker_code_bytes[idx];
@@ -35,13 +55,229 @@ pub const fn rom_header(game_title: &str, maker_code: &str, game_code: &str) ->
     }
 
     let game_code_bytes = game_code.as_bytes();
-    assert!(game_code_bytes.len() <= 4, "Game code must be <= 4 bytes");
+    assert!(game_code_bytes.len() == 4, "Game code must be 4 characters");
+    assert!(
+        is_code(game_code_bytes),
+        "Game code must be uppercase ASCII letters or digits"
+    );
     let mut idx = 0;
     while idx < game_code_bytes.len() {
         result[idx + 0x1b] = game_code_bytes[idx];
         idx += 1;
     }
 
-    result[0x1f] = 1;
+    result[0x1f] = version;
     result
 }
+
+const fn is_code(bytes: &[u8]) -> bool {
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if !bytes[idx].is_ascii_uppercase() && !bytes[idx].is_ascii_digit() {
+            return false;
+        }
+        idx += 1;
+    }
+    true
+}
+
+/// Encode text as Shift-JIS, at compile time.
+/// Returns a buffer holding the encoded text, and how many bytes of it were used.
+/// Panics if the text doesn't fit in `N` bytes, or uses a character Shift-JIS can't represent.
+pub const fn shift_jis<const N: usize>(text: &str) -> ([u8; N], usize) {
+    let bytes = text.as_bytes();
+    let mut result = [0; N];
+    let mut len = 0;
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let (code_point, char_len) = decode_utf8(bytes, idx);
+        idx += char_len;
+
+        let code = encode_char(code_point);
+        if code > 0xff {
+            assert!(len + 2 <= N, "Text is too long");
+            result[len] = (code >> 8) as u8;
+            result[len + 1] = code as u8;
+            len += 2;
+        } else {
+            assert!(len < N, "Text is too long");
+            result[len] = code as u8;
+            len += 1;
+        }
+    }
+    (result, len)
+}
+
+/// Returns the Shift-JIS encoding of one character. Values over 0xff take two bytes.
+const fn encode_char(code_point: u32) -> u16 {
+    match code_point {
+        // ASCII maps to itself
+        0x00..=0x7f => code_point as u16,
+        // half-width katakana
+        0xff61..=0xff9f => (code_point - 0xff61 + 0xa1) as u16,
+        // everything else comes from the table
+        _ => {
+            let table = &sjis_table::DOUBLE_BYTE;
+            let mut low = 0;
+            let mut high = table.len();
+            while low < high {
+                let mid = (low + high) / 2;
+                let (unicode, sjis) = table[mid];
+                if unicode as u32 == code_point {
+                    return sjis;
+                }
+                if (unicode as u32) < code_point {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            panic!("Text contains a character which Shift-JIS can't represent");
+        }
+    }
+}
+
+/// Decode the UTF-8 character starting at `idx`. Returns the code point and its length in bytes.
+const fn decode_utf8(bytes: &[u8], idx: usize) -> (u32, usize) {
+    let first = bytes[idx] as u32;
+    if first < 0x80 {
+        (first, 1)
+    } else if first < 0xe0 {
+        (((first & 0x1f) << 6) | (bytes[idx + 1] as u32 & 0x3f), 2)
+    } else if first < 0xf0 {
+        (
+            ((first & 0x0f) << 12)
+                | ((bytes[idx + 1] as u32 & 0x3f) << 6)
+                | (bytes[idx + 2] as u32 & 0x3f),
+            3,
+        )
+    } else {
+        (
+            ((first & 0x07) << 18)
+                | ((bytes[idx + 1] as u32 & 0x3f) << 12)
+                | ((bytes[idx + 2] as u32 & 0x3f) << 6)
+                | (bytes[idx + 3] as u32 & 0x3f),
+            4,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(text: &str) -> ([u8; 20], usize) {
+        shift_jis::<20>(text)
+    }
+
+    #[test]
+    fn ascii_encodes_as_itself() {
+        let (bytes, len) = encoded("Hello, VB! 123");
+        assert_eq!(&bytes[..len], b"Hello, VB! 123");
+    }
+
+    #[test]
+    fn half_width_katakana_takes_one_byte() {
+        let (bytes, len) = encoded("ｱｲｳﾟ");
+        assert_eq!(&bytes[..len], &[0xb1, 0xb2, 0xb3, 0xdf]);
+    }
+
+    #[test]
+    fn full_width_kana_takes_two_bytes() {
+        let (bytes, len) = encoded("あアー");
+        assert_eq!(&bytes[..len], &[0x82, 0xa0, 0x83, 0x41, 0x81, 0x5b]);
+    }
+
+    #[test]
+    fn kanji_take_two_bytes() {
+        let (bytes, len) = encoded("日本漢字");
+        assert_eq!(
+            &bytes[..len],
+            &[0x93, 0xfa, 0x96, 0x7b, 0x8a, 0xbf, 0x8e, 0x9a]
+        );
+    }
+
+    #[test]
+    fn mixed_widths_encode_in_order() {
+        let (bytes, len) = encoded("Aｱア漢Ａ");
+        assert_eq!(
+            &bytes[..len],
+            &[0x41, 0xb1, 0x83, 0x41, 0x8a, 0xbf, 0x82, 0x60]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "can't represent")]
+    fn unmappable_characters_are_rejected() {
+        encoded("€");
+    }
+
+    #[test]
+    #[should_panic(expected = "can't represent")]
+    fn characters_outside_the_bmp_are_rejected() {
+        encoded("🎮");
+    }
+
+    #[test]
+    fn short_titles_are_padded_with_zeros() {
+        let header = rom_header("ゲーム", "SG", "GAME", 1);
+        assert_eq!(&header[..6], &[0x83, 0x51, 0x81, 0x5b, 0x83, 0x80]);
+        assert_eq!(&header[6..0x19], &[0; 0x13]);
+    }
+
+    #[test]
+    fn titles_can_fill_all_20_bytes() {
+        let header = rom_header("ABCDEFGHIJKLMNOPQRST", "SG", "GAME", 1);
+        assert_eq!(&header[..20], b"ABCDEFGHIJKLMNOPQRST");
+        assert_eq!(&header[20..0x19], &[0; 5]);
+
+        let header = rom_header("ABCDEFGHIJKLMNOPQR漢", "SG", "GAME", 1);
+        assert_eq!(&header[..18], b"ABCDEFGHIJKLMNOPQR");
+        assert_eq!(&header[18..20], &[0x8a, 0xbf]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Text is too long")]
+    fn titles_over_20_bytes_are_rejected() {
+        rom_header("ABCDEFGHIJKLMNOPQRSTU", "SG", "GAME", 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Text is too long")]
+    fn double_byte_characters_cant_straddle_the_end() {
+        rom_header("ABCDEFGHIJKLMNOPQRS漢", "SG", "GAME", 1);
+    }
+
+    #[test]
+    fn codes_and_version_are_stored_after_the_title() {
+        let header = rom_header("TITLE", "SG", "GM01", 7);
+        assert_eq!(&header[0x14..0x19], &[0; 5]);
+        assert_eq!(&header[0x19..0x1b], b"SG");
+        assert_eq!(&header[0x1b..0x1f], b"GM01");
+        assert_eq!(header[0x1f], 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "Maker code must be 2 characters")]
+    fn maker_codes_must_be_2_characters() {
+        rom_header("TITLE", "SGS", "GAME", 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Maker code must be uppercase")]
+    fn maker_codes_must_be_uppercase() {
+        rom_header("TITLE", "sg", "GAME", 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Game code must be 4 characters")]
+    fn game_codes_must_be_4_characters() {
+        rom_header("TITLE", "SG", "GAM", 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Game code must be uppercase")]
+    fn game_codes_must_be_ascii_letters_or_digits() {
+        rom_header("TITLE", "SG", "GA-E", 1);
+    }
+}
@@ -0,0 +1,18 @@
+#[macro_export]
+/// Reserve some of RAM for the heap, so that `alloc` types like `Vec` and `Box` can use it.
+/// The heap takes `size` bytes from just above .bss, and whatever's left over goes to the stack.
+/// Requires the `alloc` feature. Without this, every allocation fails.
+macro_rules! heap_size {
+    ($size:expr) => {
+        core::arch::global_asm!(
+            ".globl _vb_rt_heap_size",
+            ".set _vb_rt_heap_size, {size}",
+            size = const $crate::macros::heap::heap_size($size),
+        );
+    };
+}
+
+pub const fn heap_size(size: usize) -> usize {
+    assert!(size < 64 * 1024, "The heap must fit in RAM");
+    size.next_multiple_of(8)
+}
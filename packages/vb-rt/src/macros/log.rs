@@ -0,0 +1,104 @@
+/// Logs a message at the given [`Level`](crate::log::Level).
+/// Messages more verbose than [`MAX_LEVEL`](crate::log::MAX_LEVEL) are compiled out.
+/// Note that this will only have an effect in the Lemur emulator.
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {
+        if $crate::log::enabled($level) {
+            $crate::log::write($level, module_path!(), format_args!($($arg)+));
+        }
+    };
+}
+
+/// Logs a message at the error level.
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Error, $($arg)+)
+    };
+}
+
+/// Logs a message at the warn level.
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Warn, $($arg)+)
+    };
+}
+
+/// Logs a message at the info level.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Info, $($arg)+)
+    };
+}
+
+/// Logs a message at the debug level.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Debug, $($arg)+)
+    };
+}
+
+/// Logs a message at the trace level.
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Trace, $($arg)+)
+    };
+}
+
+/// Marks the rest of the current scope in the Lemur profiler.
+/// Emits a `begin:name` marker now, and an `end:name` marker when the result is dropped.
+/// ```ignore
+/// let _scope = vb_rt::profile_scope!("update");
+/// ```
+/// Compiled out of release builds, unless the `profiling` feature is enabled.
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:literal) => {
+        $crate::log::ProfileScope::new(
+            const { $crate::log::marker(concat!("begin:", $name, "\0").as_bytes()) },
+            const { $crate::log::marker(concat!("end:", $name, "\0").as_bytes()) },
+        )
+    };
+}
+
+/// Like `debug_assert!`, but also emits a profiling marker when the assertion fails,
+/// so you can find the failure on the Lemur profiler's timeline.
+/// Compiled out of release builds.
+#[macro_export]
+macro_rules! debug_assert_vb {
+    ($cond:expr $(,)?) => {
+        if cfg!(debug_assertions) && !$cond {
+            $crate::log::assert_failed(stringify!($cond), None);
+        }
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if cfg!(debug_assertions) && !$cond {
+            $crate::log::assert_failed(stringify!($cond), Some(format_args!($($arg)+)));
+        }
+    };
+}
+
+/// Like `debug_assert_eq!`, but also emits a profiling marker when the assertion fails.
+/// Compiled out of release builds.
+#[macro_export]
+macro_rules! debug_assert_eq_vb {
+    ($left:expr, $right:expr $(,)?) => {
+        if cfg!(debug_assertions) {
+            match (&$left, &$right) {
+                (left, right) => {
+                    if !(*left == *right) {
+                        $crate::log::assert_failed(
+                            concat!(stringify!($left), " == ", stringify!($right)),
+                            Some(format_args!("{:?} != {:?}", left, right)),
+                        );
+                    }
+                }
+            }
+        }
+    };
+}
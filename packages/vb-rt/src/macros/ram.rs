@@ -0,0 +1,24 @@
+#[macro_export]
+/// Run a function from WRAM instead of cartridge ROM.
+/// The V810 runs code much faster from WRAM, so use this for hot inner loops.
+///
+/// The function is copied into WRAM at reset, so it takes up space in both ROM and RAM.
+/// Only the function itself moves: anything it calls stays in ROM, unless it's inlined or also uses `ram_fn!`.
+/// ```ignore
+/// vb_rt::ram_fn! {
+///     fn blend(dst: &mut [u16], src: &[u16]) {
+///         for (d, s) in dst.iter_mut().zip(src) {
+///             *d |= *s;
+///         }
+///     }
+/// }
+/// ```
+/// This works for methods in `impl` blocks too.
+macro_rules! ram_fn {
+    ($(#[$attr:meta])* $vis:vis fn $name:ident $($rest:tt)*) => {
+        $(#[$attr])*
+        #[cfg_attr(target_arch = "v810", unsafe(link_section = concat!(".ramtext.", stringify!($name))))]
+        #[inline(never)]
+        $vis fn $name $($rest)*
+    };
+}
@@ -0,0 +1,17 @@
+#[macro_export]
+/// Define what happens when `vb_rt::stack::check_canary` finds that the stack has overflowed.
+/// By default, it panics.
+macro_rules! stack_overflow_handler {
+    ($body:block) => {
+        #[unsafe(no_mangle)]
+        pub fn _vb_rt_stack_overflow_handler() {
+            $body
+        }
+    };
+    ($callback:path) => {
+        #[unsafe(no_mangle)]
+        pub fn _vb_rt_stack_overflow_handler() {
+            $callback()
+        }
+    };
+}
@@ -0,0 +1,40 @@
+use crate::sys::hardware::WaitControlData;
+
+#[macro_export]
+/// Configure how many wait states the CPU uses when reading cartridge ROM and the expansion area.
+/// The hardware defaults to 2 wait states for both. Using 1 is faster, but only works if your cartridge supports it.
+/// The new settings are applied at reset, before your main function runs.
+macro_rules! wait_states {
+    (rom: $rom:expr, expansion: $expansion:expr) => {
+        core::arch::global_asm!(
+            ".globl _vb_rt_wcr",
+            ".set _vb_rt_wcr, {wcr}",
+            wcr = const $crate::macros::wait_states::wcr($rom, $expansion),
+        );
+    };
+}
+
+pub const fn wcr(rom: u8, expansion: u8) -> u8 {
+    assert!(rom == 1 || rom == 2, "ROM wait states must be 1 or 2");
+    assert!(
+        expansion == 1 || expansion == 2,
+        "Expansion wait states must be 1 or 2"
+    );
+    WaitControlData::new()
+        .with_rom1w(rom == 1)
+        .with_exp1w(expansion == 1)
+        .into_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wcr;
+
+    #[test]
+    fn rom_is_bit_0_and_expansion_is_bit_1() {
+        assert_eq!(wcr(2, 2), 0b00);
+        assert_eq!(wcr(1, 2), 0b01);
+        assert_eq!(wcr(2, 1), 0b10);
+        assert_eq!(wcr(1, 1), 0b11);
+    }
+}
@@ -0,0 +1,231 @@
+//! Fixed-point math, for rotating affine worlds and projecting things in 3D without the FPU.
+//!
+//! [`sin`], [`cos`], [`atan2`], [`recip`] and [`sqrt`] interpolate between entries in small lookup tables.
+//! vb-rt's build script generates those tables, and the tests check every function against `f64`.
+//!
+//! Angles are [`Angle`]s, where a full turn is 0x10000. Everything else is an [`I16F16`].
+
+use core::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+pub use fixed::types::{I2F14, I16F16};
+
+mod lut;
+mod matrix;
+mod projection;
+mod vector;
+
+pub use matrix::{Mat2, Mat3};
+pub use projection::{Projected, Projection};
+pub use vector::{Vec2, Vec3};
+
+mod tables {
+    include!(concat!(env!("OUT_DIR"), "/math_tables.rs"));
+}
+
+/// An angle, where 0x10000 is a full turn and positive angles turn from +x towards +y.
+/// Arithmetic wraps around, the same way angles do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Angle(u16);
+
+impl Angle {
+    pub const ZERO: Self = Self(0);
+    pub const QUARTER_TURN: Self = Self(0x4000);
+    pub const HALF_TURN: Self = Self(0x8000);
+
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    /// Convert from degrees, rounding to the nearest 1/0x10000 of a turn.
+    pub const fn from_degrees(degrees: i32) -> Self {
+        let degrees = degrees.rem_euclid(360) as u32;
+        Self(((degrees * 0x10000 + 180) / 360) as u16)
+    }
+
+    /// Convert from turns. Only the fractional part matters.
+    pub const fn from_turns(turns: I16F16) -> Self {
+        Self(turns.to_bits() as u16)
+    }
+
+    pub const fn sin(self) -> I2F14 {
+        sin(self)
+    }
+
+    pub const fn cos(self) -> I2F14 {
+        cos(self)
+    }
+}
+
+impl Add for Angle {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl AddAssign for Angle {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Angle {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl SubAssign for Angle {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Angle {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(self.0.wrapping_neg())
+    }
+}
+
+/// Sine, accurate to within 1/16384.
+pub const fn sin(angle: Angle) -> I2F14 {
+    I2F14::from_bits(lut::sin(&tables::SIN, angle.0))
+}
+
+/// Cosine, accurate to within 1/16384.
+pub const fn cos(angle: Angle) -> I2F14 {
+    sin(Angle(angle.0.wrapping_add(0x4000)))
+}
+
+/// The angle from the positive x axis to the point (x, y), accurate to within 2/0x10000 of a turn.
+/// Returns [`Angle::ZERO`] for the origin.
+pub const fn atan2(y: I16F16, x: I16F16) -> Angle {
+    Angle(lut::atan2(&tables::ATAN, y.to_bits(), x.to_bits()))
+}
+
+/// `1 / x`, accurate to about 1 part in 16384.
+/// Saturates if `x` is zero, or so small that the result doesn't fit.
+pub const fn recip(x: I16F16) -> I16F16 {
+    I16F16::from_bits(lut::recip(&tables::RECIP, x.to_bits()))
+}
+
+/// The square root of `x`, accurate to about 1 part in 16384. Returns zero if `x` is negative.
+pub const fn sqrt(x: I16F16) -> I16F16 {
+    I16F16::from_bits(lut::sqrt(&tables::SQRT, x.to_bits()))
+}
+
+/// Widen the result of [`sin`] or [`cos`], for multiplying with other [`I16F16`]s.
+const fn widen(value: I2F14) -> I16F16 {
+    I16F16::from_bits((value.to_bits() as i32) << 2)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::f64::consts::TAU;
+
+    use super::*;
+
+    #[test]
+    fn sin_is_accurate() {
+        for angle in 0..=u16::MAX {
+            let expected = (angle as f64 * TAU / 65536.0).sin();
+            let actual = sin(Angle(angle)).to_num::<f64>();
+            check("sin", angle, expected, actual, 1.0 / 16384.0);
+        }
+    }
+
+    #[test]
+    fn cos_is_accurate() {
+        for angle in 0..=u16::MAX {
+            let expected = (angle as f64 * TAU / 65536.0).cos();
+            let actual = cos(Angle(angle)).to_num::<f64>();
+            check("cos", angle, expected, actual, 1.0 / 16384.0);
+        }
+    }
+
+    #[test]
+    fn atan2_is_accurate() {
+        let mut coords = std::vec![];
+        for scale in [1, 7, 100, 0x1_0000, 0x7fff_0000] {
+            for step in -32..=32 {
+                coords.push(step * (scale / 32).max(1));
+            }
+        }
+        for &y in &coords {
+            for &x in &coords {
+                if x == 0 && y == 0 {
+                    continue;
+                }
+                let expected = (y as f64).atan2(x as f64) * 65536.0 / TAU;
+                let actual = atan2(I16F16::from_bits(y), I16F16::from_bits(x)).0 as f64;
+                // Compare around the circle, so that just under a full turn matches just over zero.
+                let error = (actual - expected).rem_euclid(65536.0);
+                let error = error.min(65536.0 - error);
+                check("atan2", (y, x), 0.0, error, 2.0);
+            }
+        }
+        assert_eq!(atan2(I16F16::ZERO, I16F16::ZERO), Angle::ZERO);
+    }
+
+    #[test]
+    fn recip_is_accurate() {
+        for x in sample_i16f16() {
+            let expected = 65536.0 / x as f64;
+            if expected.abs() > i32::MAX as f64 / 65536.0 {
+                continue;
+            }
+            let actual = recip(I16F16::from_bits(x)).to_num::<f64>();
+            check("recip", x, expected, actual, tolerance(expected));
+        }
+    }
+
+    #[test]
+    fn recip_saturates() {
+        assert_eq!(recip(I16F16::ZERO), I16F16::MAX);
+        assert_eq!(recip(I16F16::DELTA), I16F16::MAX);
+        assert_eq!(recip(-I16F16::DELTA), I16F16::MIN);
+    }
+
+    #[test]
+    fn sqrt_is_accurate() {
+        for x in sample_i16f16().filter(|x| *x >= 0) {
+            let expected = (x as f64 / 65536.0).sqrt();
+            let actual = sqrt(I16F16::from_bits(x)).to_num::<f64>();
+            check("sqrt", x, expected, actual, tolerance(expected));
+        }
+        assert_eq!(sqrt(-I16F16::ONE), I16F16::ZERO);
+    }
+
+    /// Every small I16F16, and a spread of mantissas at every larger magnitude.
+    fn sample_i16f16() -> impl Iterator<Item = i32> {
+        let small = -4096..=4096;
+        let large = (12..31).flat_map(|msb| (0..4096).map(move |m| (1 << msb) + (m << (msb - 12))));
+        small.chain(large.clone()).chain(large.map(|x: i32| -x))
+    }
+
+    /// 1 part in 2^14 plus rounding, but never less than one bit of I16F16 precision.
+    fn tolerance(expected: f64) -> f64 {
+        (0.5 / 65536.0 + expected.abs() / 16384.0).max(1.0 / 65536.0)
+    }
+
+    fn check<T: core::fmt::Debug>(
+        name: &str,
+        input: T,
+        expected: f64,
+        actual: f64,
+        tolerance: f64,
+    ) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{name}({input:?}) was {actual}, expected {expected} (tolerance {tolerance})"
+        );
+    }
+}
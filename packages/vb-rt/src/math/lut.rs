@@ -0,0 +1,139 @@
+//! Lookups into the tables generated by build.rs, on raw integers.
+//!
+//! Every table has 257 entries, covering 256 equal steps plus the end of the range,
+//! and lookups interpolate linearly between neighbouring entries.
+
+/// `sin(angle)` as an I2F14, where a full turn is 0x10000.
+/// `table[i]` holds `sin(i/256 * π/2)` as an I2F14.
+pub const fn sin(table: &[i16; 257], angle: u16) -> i16 {
+    let quadrant = angle >> 14;
+    let mut offset = (angle & 0x3fff) as u32;
+    // Sine is symmetric around each quarter turn, so the table only needs to cover one of them.
+    if quadrant & 1 != 0 {
+        offset = 0x4000 - offset;
+    }
+    let value = interpolate_i16(table, offset >> 6, offset & 0x3f, 6);
+    if quadrant & 2 != 0 { -value } else { value }
+}
+
+/// The angle from the positive x axis to (x, y), where a full turn is 0x10000.
+/// `table[i]` holds `atan(i/256)` in those units.
+/// Both coordinates can be in any unit, as long as it's the same one.
+pub const fn atan2(table: &[u16; 257], y: i32, x: i32) -> u16 {
+    if x == 0 && y == 0 {
+        return 0;
+    }
+    let (ax, ay) = (x.unsigned_abs(), y.unsigned_abs());
+    let (mut small, mut big) = if ay <= ax { (ay, ax) } else { (ax, ay) };
+    // Keep 15 significant bits, so the ratio below can't overflow.
+    let bits = u32::BITS - big.leading_zeros();
+    if bits > 15 {
+        small >>= bits - 15;
+        big >>= bits - 15;
+    }
+    // small / big, from 0 to 1 with 16 fractional bits
+    let ratio = (small << 16) / big;
+    let octant = interpolate_u16(table, ratio >> 8, ratio & 0xff, 8);
+
+    let mut angle = if ay > ax { 0x4000 - octant } else { octant };
+    if x < 0 {
+        angle = 0x8000 - angle;
+    }
+    if y < 0 {
+        angle = 0x10000 - angle;
+    }
+    angle as u16
+}
+
+/// `1 / x` on the bits of an I16F16. Saturates if the result doesn't fit, or if `x` is zero.
+/// `table[i]` holds `1 / (1 + i/256)` with 15 fractional bits.
+pub const fn recip(table: &[u16; 257], x: i32) -> i32 {
+    if x == 0 {
+        return i32::MAX;
+    }
+    let magnitude = x.unsigned_abs();
+    let msb = u32::BITS - 1 - magnitude.leading_zeros();
+    let mantissa = normalize(magnitude, msb);
+    // 1 / mantissa, with 15 fractional bits
+    let inverse = interpolate_u16(table, (mantissa >> 8) & 0xff, mantissa & 0xff, 8);
+
+    // x is mantissa * 2^(msb - 16), so 1/x has 16 fractional bits after shifting by 17 - msb.
+    let result = shift(inverse, 17 - msb as i32);
+    match result {
+        Some(result) if x < 0 => -(result as i32),
+        Some(result) => result as i32,
+        None if x < 0 => i32::MIN,
+        None => i32::MAX,
+    }
+}
+
+/// `sqrt(x)` on the bits of an I16F16. Returns 0 if `x` is negative.
+/// `table[i]` holds `sqrt(1 + i/256)` with 15 fractional bits.
+pub const fn sqrt(table: &[u16; 257], x: i32) -> i32 {
+    if x <= 0 {
+        return 0;
+    }
+    let magnitude = x as u32;
+    let msb = u32::BITS - 1 - magnitude.leading_zeros();
+    let mantissa = normalize(magnitude, msb);
+    // sqrt(mantissa), with 15 fractional bits
+    let mut root = interpolate_u16(table, (mantissa >> 8) & 0xff, mantissa & 0xff, 8);
+
+    // x is mantissa * 2^exponent. Halving an odd exponent leaves a factor of sqrt(2) behind.
+    let mut exponent = msb as i32 - 16;
+    if exponent & 1 != 0 {
+        root = (root * SQRT_2 + (1 << 14)) >> 15;
+        exponent -= 1;
+    }
+    match shift(root, exponent / 2 + 1) {
+        Some(result) => result as i32,
+        None => i32::MAX,
+    }
+}
+
+/// sqrt(2), with 15 fractional bits.
+const SQRT_2: u32 = 46341;
+
+/// Shift a value so that its most significant bit is bit 16.
+const fn normalize(magnitude: u32, msb: u32) -> u32 {
+    if msb >= 16 {
+        magnitude >> (msb - 16)
+    } else {
+        magnitude << (16 - msb)
+    }
+}
+
+/// Shift left by a positive amount or right (rounding) by a negative one.
+/// Returns `None` if the result doesn't fit in an `i32`.
+const fn shift(value: u32, amount: i32) -> Option<u32> {
+    if amount >= 0 {
+        if value > (i32::MAX as u32) >> amount {
+            None
+        } else {
+            Some(value << amount)
+        }
+    } else {
+        let amount = -amount as u32;
+        Some((value + (1 << (amount - 1))) >> amount)
+    }
+}
+
+const fn interpolate_i16(table: &[i16; 257], index: u32, frac: u32, frac_bits: u32) -> i16 {
+    let index = index as usize;
+    let a = table[index] as i32;
+    if index == 256 {
+        return a as i16;
+    }
+    let b = table[index + 1] as i32;
+    (a + (((b - a) * frac as i32 + (1 << (frac_bits - 1))) >> frac_bits)) as i16
+}
+
+const fn interpolate_u16(table: &[u16; 257], index: u32, frac: u32, frac_bits: u32) -> u32 {
+    let index = index as usize;
+    let a = table[index] as i32;
+    if index == 256 {
+        return a as u32;
+    }
+    let b = table[index + 1] as i32;
+    (a + (((b - a) * frac as i32 + (1 << (frac_bits - 1))) >> frac_bits)) as u32
+}
@@ -0,0 +1,250 @@
+use core::ops::Mul;
+
+use fixed::types::{I7F9, I13F3};
+
+use super::{Angle, I16F16, Vec2, Vec3, cos, recip, sin, widen};
+use crate::sys::vip::AffineElement;
+
+/// A 2x2 matrix, stored as rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mat2 {
+    pub x: Vec2,
+    pub y: Vec2,
+}
+
+impl Mat2 {
+    pub const IDENTITY: Self = Self::new(
+        Vec2::new(I16F16::ONE, I16F16::ZERO),
+        Vec2::new(I16F16::ZERO, I16F16::ONE),
+    );
+
+    pub const fn new(x: Vec2, y: Vec2) -> Self {
+        Self { x, y }
+    }
+
+    pub fn rotation(angle: Angle) -> Self {
+        let (sin, cos) = (widen(sin(angle)), widen(cos(angle)));
+        Self::new(Vec2::new(cos, -sin), Vec2::new(sin, cos))
+    }
+
+    pub const fn scale(x: I16F16, y: I16F16) -> Self {
+        Self::new(Vec2::new(x, I16F16::ZERO), Vec2::new(I16F16::ZERO, y))
+    }
+
+    pub const fn transpose(self) -> Self {
+        Self::new(Vec2::new(self.x.x, self.y.x), Vec2::new(self.x.y, self.y.y))
+    }
+
+    pub fn determinant(self) -> I16F16 {
+        self.x.x * self.y.y - self.x.y * self.y.x
+    }
+
+    /// The matrix which undoes this one, or `None` if nothing can.
+    pub fn inverse(self) -> Option<Self> {
+        let determinant = self.determinant();
+        if determinant == I16F16::ZERO {
+            return None;
+        }
+        let inv = recip(determinant);
+        Some(Self::new(
+            Vec2::new(self.y.y * inv, -self.x.y * inv),
+            Vec2::new(-self.y.x * inv, self.x.x * inv),
+        ))
+    }
+
+    /// The parameters for one row of an affine world, which maps screen pixels to background pixels with this matrix.
+    /// `source` is the background pixel shown at the left edge of the row.
+    /// Each pixel to the right steps through the background by the matrix's first column.
+    pub fn affine_element(self, source: Vec2, parallax: i16) -> AffineElement {
+        AffineElement::new(
+            I13F3::saturating_from_num(source.x),
+            parallax,
+            I13F3::saturating_from_num(source.y),
+            I7F9::saturating_from_num(self.x.x),
+            I7F9::saturating_from_num(self.y.x),
+        )
+    }
+
+    /// The parameters for row `row` of an affine world, which maps screen pixels to background pixels with this matrix.
+    /// The screen pixel at `center` (relative to the world's top left corner) shows the background pixel at `pivot`,
+    /// and everything else rotates and scales around it.
+    /// ```ignore
+    /// // Spin the background around its middle, at 2x zoom
+    /// let zoom = I16F16::from_num(2);
+    /// let m = (Mat2::rotation(angle) * Mat2::scale(zoom, zoom)).inverse().unwrap();
+    /// for row in 0..224 {
+    ///     vip::AFFINE.index(base + row).write(m.affine_row(pivot, center, row as i16, 0));
+    /// }
+    /// ```
+    pub fn affine_row(self, pivot: Vec2, center: Vec2, row: i16, parallax: i16) -> AffineElement {
+        let offset = Vec2::new(-center.x, I16F16::from_num(row) - center.y);
+        self.affine_element(pivot + self * offset, parallax)
+    }
+}
+
+impl Default for Mat2 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul<Vec2> for Mat2 {
+    type Output = Vec2;
+    fn mul(self, rhs: Vec2) -> Vec2 {
+        Vec2::new(self.x.dot(rhs), self.y.dot(rhs))
+    }
+}
+
+impl Mul for Mat2 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        let rhs = rhs.transpose();
+        Self::new(
+            Vec2::new(self.x.dot(rhs.x), self.x.dot(rhs.y)),
+            Vec2::new(self.y.dot(rhs.x), self.y.dot(rhs.y)),
+        )
+    }
+}
+
+/// A 3x3 matrix, stored as rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mat3 {
+    pub x: Vec3,
+    pub y: Vec3,
+    pub z: Vec3,
+}
+
+impl Mat3 {
+    pub const IDENTITY: Self = Self::new(
+        Vec3::new(I16F16::ONE, I16F16::ZERO, I16F16::ZERO),
+        Vec3::new(I16F16::ZERO, I16F16::ONE, I16F16::ZERO),
+        Vec3::new(I16F16::ZERO, I16F16::ZERO, I16F16::ONE),
+    );
+
+    pub const fn new(x: Vec3, y: Vec3, z: Vec3) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Rotate around the x axis, turning +y towards +z.
+    pub fn rotation_x(angle: Angle) -> Self {
+        let (sin, cos) = (widen(sin(angle)), widen(cos(angle)));
+        let (zero, one) = (I16F16::ZERO, I16F16::ONE);
+        Self::new(
+            Vec3::new(one, zero, zero),
+            Vec3::new(zero, cos, -sin),
+            Vec3::new(zero, sin, cos),
+        )
+    }
+
+    /// Rotate around the y axis, turning +z towards +x.
+    pub fn rotation_y(angle: Angle) -> Self {
+        let (sin, cos) = (widen(sin(angle)), widen(cos(angle)));
+        let (zero, one) = (I16F16::ZERO, I16F16::ONE);
+        Self::new(
+            Vec3::new(cos, zero, sin),
+            Vec3::new(zero, one, zero),
+            Vec3::new(-sin, zero, cos),
+        )
+    }
+
+    /// Rotate around the z axis, turning +x towards +y.
+    pub fn rotation_z(angle: Angle) -> Self {
+        let (sin, cos) = (widen(sin(angle)), widen(cos(angle)));
+        let (zero, one) = (I16F16::ZERO, I16F16::ONE);
+        Self::new(
+            Vec3::new(cos, -sin, zero),
+            Vec3::new(sin, cos, zero),
+            Vec3::new(zero, zero, one),
+        )
+    }
+
+    pub const fn scale(x: I16F16, y: I16F16, z: I16F16) -> Self {
+        let zero = I16F16::ZERO;
+        Self::new(
+            Vec3::new(x, zero, zero),
+            Vec3::new(zero, y, zero),
+            Vec3::new(zero, zero, z),
+        )
+    }
+
+    pub const fn transpose(self) -> Self {
+        Self::new(
+            Vec3::new(self.x.x, self.y.x, self.z.x),
+            Vec3::new(self.x.y, self.y.y, self.z.y),
+            Vec3::new(self.x.z, self.y.z, self.z.z),
+        )
+    }
+}
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        Vec3::new(self.x.dot(rhs), self.y.dot(rhs), self.z.dot(rhs))
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        let rhs = rhs.transpose();
+        Self::new(
+            Vec3::new(self.x.dot(rhs.x), self.x.dot(rhs.y), self.x.dot(rhs.z)),
+            Vec3::new(self.y.dot(rhs.x), self.y.dot(rhs.y), self.y.dot(rhs.z)),
+            Vec3::new(self.z.dot(rhs.x), self.z.dot(rhs.y), self.z.dot(rhs.z)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: I16F16, expected: I16F16) {
+        let error = (actual - expected).abs();
+        assert!(
+            error <= I16F16::from_num(1) / 256,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn rotations_times_their_inverse_are_the_identity() {
+        for degrees in [0, 30, 45, 90, 135, 200, 333] {
+            let m = Mat2::rotation(Angle::from_degrees(degrees));
+            let product = m * m.inverse().unwrap();
+            assert_close(product.x.x, I16F16::ONE);
+            assert_close(product.x.y, I16F16::ZERO);
+            assert_close(product.y.x, I16F16::ZERO);
+            assert_close(product.y.y, I16F16::ONE);
+        }
+    }
+
+    #[test]
+    fn singular_matrices_have_no_inverse() {
+        let m = Mat2::scale(I16F16::ONE, I16F16::ZERO);
+        assert_eq!(m.inverse(), None);
+    }
+
+    #[test]
+    fn affine_rows_of_a_quarter_turn() {
+        let m = Mat2::rotation(Angle::QUARTER_TURN);
+        let pivot = Vec2::from_ints(64, 32);
+        let center = Vec2::from_ints(16, 8);
+
+        // The row 2 pixels below the center starts 16 pixels left of it,
+        // which the rotation maps 2 pixels left and 16 pixels up from the pivot.
+        let row = m.affine_row(pivot, center, 10, 3);
+        assert_eq!(row.mx, I13F3::from_num(62));
+        assert_eq!(row.my, I13F3::from_num(16));
+        assert_eq!(row.mp, 3);
+        // Stepping right on screen steps down through the background
+        assert_eq!(row.dx, I7F9::ZERO);
+        assert_eq!(row.dy, I7F9::ONE);
+    }
+}
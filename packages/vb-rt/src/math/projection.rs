@@ -0,0 +1,115 @@
+use super::{I16F16, Vec2, Vec3, recip};
+
+/// Projects points in 3D space onto the screen, with parallax so that each eye sees them at the right depth.
+///
+/// The camera sits at the origin, looking down +z, with +x to the right and +y down like the screen.
+/// Distances are in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Projection {
+    /// How far the screen is from the camera. Points at this depth have no parallax.
+    pub focal_length: I16F16,
+    /// How far apart the eyes are. Points infinitely far away are drawn this far apart.
+    pub eye_separation: I16F16,
+    /// The point on screen straight ahead of the camera.
+    pub center: Vec2,
+    /// Points closer to the camera than this aren't drawn.
+    pub near: I16F16,
+}
+
+/// Where a projected point lands on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Projected {
+    pub x: i16,
+    pub y: i16,
+    /// Ready to use as a world's GP or an object's JP.
+    /// The left eye sees the point at `x - parallax`, and the right eye at `x + parallax`.
+    pub parallax: i16,
+}
+
+impl Projection {
+    /// A projection centered on the screen, with the near plane 1 pixel from the camera.
+    pub const fn new(focal_length: I16F16, eye_separation: I16F16) -> Self {
+        Self {
+            focal_length,
+            eye_separation,
+            center: Vec2::from_ints(192, 112),
+            near: I16F16::ONE,
+        }
+    }
+
+    /// Returns `None` if the point is closer to the camera than the near plane.
+    pub fn project(&self, point: Vec3) -> Option<Projected> {
+        if point.z < self.near {
+            return None;
+        }
+        let scale = self.scale(point.z);
+        let x = self.center.x.saturating_add(point.x.saturating_mul(scale));
+        let y = self.center.y.saturating_add(point.y.saturating_mul(scale));
+        Some(Projected {
+            x: x.round().saturating_to_num(),
+            y: y.round().saturating_to_num(),
+            parallax: self.parallax_at_scale(scale),
+        })
+    }
+
+    /// The parallax of anything `depth` pixels from the camera.
+    pub fn parallax(&self, depth: I16F16) -> i16 {
+        self.parallax_at_scale(self.scale(depth.max(self.near)))
+    }
+
+    /// How much bigger things at this depth look than things on the screen plane.
+    fn scale(&self, depth: I16F16) -> I16F16 {
+        self.focal_length.saturating_mul(recip(depth))
+    }
+
+    fn parallax_at_scale(&self, scale: I16F16) -> i16 {
+        let disparity = self
+            .eye_separation
+            .saturating_mul(I16F16::ONE.saturating_sub(scale));
+        (disparity / 2).round().saturating_to_num()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECTION: Projection = Projection::new(I16F16::lit("128"), I16F16::lit("16"));
+
+    #[test]
+    fn the_screen_plane_has_no_parallax() {
+        assert_eq!(PROJECTION.parallax(I16F16::from_num(128)), 0);
+        let projected = PROJECTION.project(Vec3::from_ints(10, -20, 128)).unwrap();
+        assert_eq!(
+            projected,
+            Projected {
+                x: 202,
+                y: 92,
+                parallax: 0
+            }
+        );
+    }
+
+    #[test]
+    fn parallax_approaches_half_the_eye_separation() {
+        let mut previous = 0;
+        for depth in [256, 512, 1024, 4096, 16384, 32767] {
+            let parallax = PROJECTION.parallax(I16F16::from_num(depth));
+            assert!(parallax >= previous, "parallax shrank at depth {depth}");
+            assert!(parallax <= 8);
+            previous = parallax;
+        }
+        assert_eq!(PROJECTION.parallax(I16F16::from_num(256)), 4);
+        assert_eq!(previous, 8);
+    }
+
+    #[test]
+    fn closer_points_pop_out_of_the_screen() {
+        assert!(PROJECTION.parallax(I16F16::from_num(64)) < 0);
+    }
+
+    #[test]
+    fn points_behind_the_near_plane_arent_projected() {
+        assert_eq!(PROJECTION.project(Vec3::from_ints(0, 0, 0)), None);
+    }
+}
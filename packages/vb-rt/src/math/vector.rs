@@ -0,0 +1,155 @@
+use core::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use super::{Angle, I16F16, atan2, cos, recip, sin, sqrt, widen};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Vec2 {
+    pub x: I16F16,
+    pub y: I16F16,
+}
+
+impl Vec2 {
+    pub const ZERO: Self = Self::new(I16F16::ZERO, I16F16::ZERO);
+
+    pub const fn new(x: I16F16, y: I16F16) -> Self {
+        Self { x, y }
+    }
+
+    pub const fn from_ints(x: i16, y: i16) -> Self {
+        Self::new(
+            I16F16::from_bits((x as i32) << 16),
+            I16F16::from_bits((y as i32) << 16),
+        )
+    }
+
+    /// A vector `length` long, pointing in the direction of `angle`.
+    pub fn from_angle(angle: Angle, length: I16F16) -> Self {
+        Self::new(widen(cos(angle)) * length, widen(sin(angle)) * length)
+    }
+
+    pub fn dot(self, rhs: Self) -> I16F16 {
+        self.x * rhs.x + self.y * rhs.y
+    }
+
+    pub fn length(self) -> I16F16 {
+        sqrt(self.dot(self))
+    }
+
+    /// A vector pointing the same way, one unit long. The zero vector stays zero.
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        if length == I16F16::ZERO {
+            return self;
+        }
+        self * recip(length)
+    }
+
+    /// The angle from the positive x axis to this vector.
+    pub const fn angle(self) -> Angle {
+        atan2(self.y, self.x)
+    }
+
+    pub fn rotate(self, angle: Angle) -> Self {
+        let (sin, cos) = (widen(sin(angle)), widen(cos(angle)));
+        Self::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Vec3 {
+    pub x: I16F16,
+    pub y: I16F16,
+    pub z: I16F16,
+}
+
+impl Vec3 {
+    pub const ZERO: Self = Self::new(I16F16::ZERO, I16F16::ZERO, I16F16::ZERO);
+
+    pub const fn new(x: I16F16, y: I16F16, z: I16F16) -> Self {
+        Self { x, y, z }
+    }
+
+    pub const fn from_ints(x: i16, y: i16, z: i16) -> Self {
+        Self::new(
+            I16F16::from_bits((x as i32) << 16),
+            I16F16::from_bits((y as i32) << 16),
+            I16F16::from_bits((z as i32) << 16),
+        )
+    }
+
+    pub const fn xy(self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    pub fn dot(self, rhs: Self) -> I16F16 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    pub fn cross(self, rhs: Self) -> Self {
+        Self::new(
+            self.y * rhs.z - self.z * rhs.y,
+            self.z * rhs.x - self.x * rhs.z,
+            self.x * rhs.y - self.y * rhs.x,
+        )
+    }
+
+    pub fn length(self) -> I16F16 {
+        sqrt(self.dot(self))
+    }
+
+    /// A vector pointing the same way, one unit long. The zero vector stays zero.
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        if length == I16F16::ZERO {
+            return self;
+        }
+        self * recip(length)
+    }
+}
+
+macro_rules! impl_vector_ops {
+    ($type:ident { $($field:ident),* }) => {
+        impl Add for $type {
+            type Output = Self;
+            fn add(self, rhs: Self) -> Self {
+                Self { $($field: self.$field + rhs.$field),* }
+            }
+        }
+
+        impl AddAssign for $type {
+            fn add_assign(&mut self, rhs: Self) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl Sub for $type {
+            type Output = Self;
+            fn sub(self, rhs: Self) -> Self {
+                Self { $($field: self.$field - rhs.$field),* }
+            }
+        }
+
+        impl SubAssign for $type {
+            fn sub_assign(&mut self, rhs: Self) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl Neg for $type {
+            type Output = Self;
+            fn neg(self) -> Self {
+                Self { $($field: -self.$field),* }
+            }
+        }
+
+        impl Mul<I16F16> for $type {
+            type Output = Self;
+            fn mul(self, rhs: I16F16) -> Self {
+                Self { $($field: self.$field * rhs),* }
+            }
+        }
+    };
+}
+
+impl_vector_ops!(Vec2 { x, y });
+impl_vector_ops!(Vec3 { x, y, z });
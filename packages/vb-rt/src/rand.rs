@@ -0,0 +1,570 @@
+//! Pseudo-random numbers, for games which want them to be unpredictable but reproducible.
+//!
+//! [`Rng`] is xoshiro128**, which only needs 32-bit shifts, rotates and multiplies.
+//! Seed it from [`Entropy`] gathered from the hardware and the player,
+//! or from a fixed seed for replays and tests.
+//! ```ignore
+//! let mut entropy = Entropy::new();
+//! entropy.add_sram(4096..8192);
+//! loop {
+//!     input.update();
+//!     if entropy.wait_for_start(&input) {
+//!         break;
+//!     }
+//!     frame.wait_for_new_frame();
+//! }
+//! let mut rng = entropy.rng();
+//! let damage = rng.range(5..=10);
+//! ```
+
+use core::ops::{Bound, Range, RangeBounds};
+
+use fixed::types::I16F16;
+
+use crate::{
+    input::{Button, InputState},
+    save::{Reader, SaveField, Writer},
+    sys::sram,
+    timer,
+};
+
+/// A fast, small pseudo-random number generator.
+/// The same seed always produces the same sequence, on hardware and on the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: [u32; 4],
+}
+
+impl Rng {
+    /// A generator whose sequence is determined by `seed`. Every seed is fine, including zero.
+    pub const fn from_seed(seed: u32) -> Self {
+        // lowbias32 is a bijection, so the four words can't all be zero.
+        let mut state = [0; 4];
+        let mut index = 0;
+        while index < 4 {
+            state[index] = mix(seed.wrapping_add(GOLDEN_RATIO.wrapping_mul(index as u32)));
+            index += 1;
+        }
+        Self { state }
+    }
+
+    /// Restore a generator from [`Rng::state`], so it carries on where it left off.
+    /// Panics if the state is all zeroes, since the generator would never leave it.
+    pub const fn from_state(state: [u32; 4]) -> Self {
+        assert!(
+            state[0] | state[1] | state[2] | state[3] != 0,
+            "An Rng's state can't be all zeroes"
+        );
+        Self { state }
+    }
+
+    /// Everything needed to reproduce the rest of this generator's sequence.
+    pub const fn state(&self) -> [u32; 4] {
+        self.state
+    }
+
+    pub const fn next_u32(&mut self) -> u32 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 9;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(11);
+        result
+    }
+
+    /// A number from 0 up to (but not including) `bound`, with every number equally likely.
+    /// Panics if `bound` is zero.
+    pub const fn below(&mut self, bound: u32) -> u32 {
+        assert!(bound != 0, "Can't choose a number below zero");
+        // Reject the few values which would make smaller results more likely than larger ones.
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let value = self.next_u32();
+            if value >= threshold {
+                return value % bound;
+            }
+        }
+    }
+
+    /// A number in `range`, with every number equally likely.
+    /// Panics if the range is empty.
+    pub fn range(&mut self, range: impl RangeBounds<i32>) -> i32 {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).expect("Empty range"),
+            Bound::Unbounded => i32::MIN,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end,
+            Bound::Excluded(&end) => end.checked_sub(1).expect("Empty range"),
+            Bound::Unbounded => i32::MAX,
+        };
+        assert!(start <= end, "Empty range");
+        let span = (end as u32).wrapping_sub(start as u32).wrapping_add(1);
+        if span == 0 {
+            // The range covers every i32.
+            return self.next_u32() as i32;
+        }
+        start.wrapping_add(self.below(span) as i32)
+    }
+
+    /// A number from 0 up to (but not including) 1.
+    pub const fn fraction(&mut self) -> I16F16 {
+        I16F16::from_bits((self.next_u32() >> 16) as i32)
+    }
+
+    pub const fn bool(&mut self) -> bool {
+        self.next_u32() & 0x8000_0000 != 0
+    }
+
+    /// Returns true `numerator` times out of every `denominator`.
+    pub const fn chance(&mut self, numerator: u32, denominator: u32) -> bool {
+        self.below(denominator) < numerator
+    }
+
+    /// One of the items, or `None` if there aren't any.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len() as u32) as usize)
+    }
+
+    /// The index of one of the weights, chosen in proportion to its weight.
+    /// Returns `None` if every weight is zero. Panics if the weights add up to more than `u32::MAX`.
+    /// ```ignore
+    /// // Common, uncommon, rare
+    /// let rarity = rng.choose_weighted(&[70, 25, 5]);
+    /// ```
+    pub fn choose_weighted(&mut self, weights: &[u32]) -> Option<usize> {
+        let total = weights
+            .iter()
+            .try_fold(0u32, |total, &weight| total.checked_add(weight));
+        let total = total.expect("Weights add up to more than u32::MAX");
+        if total == 0 {
+            return None;
+        }
+        let mut roll = self.below(total);
+        for (index, &weight) in weights.iter().enumerate() {
+            if roll < weight {
+                return Some(index);
+            }
+            roll -= weight;
+        }
+        unreachable!()
+    }
+
+    /// Put the items in a random order, with every order equally likely.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for index in (1..items.len()).rev() {
+            let other = self.below(index as u32 + 1) as usize;
+            items.swap(index, other);
+        }
+    }
+}
+
+impl SaveField for Rng {
+    fn save(&self, writer: &mut Writer<'_>) {
+        writer.put(&self.state);
+    }
+
+    fn load(reader: &mut Reader<'_>) -> Self {
+        let state: [u32; 4] = reader.get();
+        if state == [0; 4] {
+            // Corrupt or never written. Carry on with something usable.
+            return Self::from_seed(0);
+        }
+        Self::from_state(state)
+    }
+}
+
+/// Gathers unpredictable values from the hardware and the player, to seed an [`Rng`].
+#[derive(Debug, Clone)]
+pub struct Entropy {
+    hash: u32,
+    frames: u32,
+    seen_input: bool,
+}
+
+impl Entropy {
+    pub const fn new() -> Self {
+        Self {
+            hash: 0,
+            frames: 0,
+            seen_input: false,
+        }
+    }
+
+    /// Mix in any value.
+    pub const fn add(&mut self, value: u32) {
+        self.hash = mix(self.hash.rotate_left(5) ^ value);
+    }
+
+    /// Mix in the timer's counter. Only useful while the timer is running.
+    pub fn add_timer(&mut self) {
+        self.add(timer::counter() as u32);
+    }
+
+    /// Mix in the contents of part of SRAM. Unused SRAM holds noise until something writes to it,
+    /// so pick a range your save data doesn't use.
+    pub fn add_sram(&mut self, range: Range<usize>) {
+        let mut word = 0u32;
+        let mut bytes = 0;
+        for offset in range {
+            word = (word << 8) | sram::SRAM.index(offset).read() as u32;
+            bytes += 1;
+            if bytes == 4 {
+                self.add(word);
+                (word, bytes) = (0, 0);
+            }
+        }
+        if bytes != 0 {
+            self.add(word);
+        }
+    }
+
+    /// Call once per frame while waiting for the player to press START, e.g. on a title screen.
+    /// Mixes in the timer when the first button goes down, and how many frames passed before START.
+    /// Returns true once START has been pressed.
+    pub fn wait_for_start(&mut self, input: &InputState) -> bool {
+        self.frames = self.frames.wrapping_add(1);
+        if !self.seen_input && input.raw().into_bits() != 0 {
+            self.seen_input = true;
+            self.add_timer();
+        }
+        if input.pressed(Button::Start) {
+            self.add(self.frames);
+            self.add_timer();
+            return true;
+        }
+        false
+    }
+
+    /// A generator seeded with everything gathered so far.
+    pub const fn rng(&self) -> Rng {
+        Rng::from_seed(self.hash)
+    }
+}
+
+impl Default for Entropy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const GOLDEN_RATIO: u32 = 0x9e37_79b9;
+
+/// lowbias32, by Chris Wellons. Every bit of the input affects every bit of the output.
+const fn mix(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sys::{hardware::GamePadData, mock};
+
+    const DRAWS: u32 = 100_000;
+
+    /// Panics unless every count is within 5% of `expected`, which is several standard deviations at this many draws.
+    fn assert_close(counts: &[u32], expected: &[u32]) {
+        for (index, (&count, &expected)) in counts.iter().zip(expected).enumerate() {
+            let tolerance = expected / 20;
+            assert!(
+                count.abs_diff(expected) <= tolerance,
+                "bucket {index} got {count}, expected {expected} ± {tolerance}: {counts:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_generator_is_xoshiro128_star_star() {
+        // The first outputs of the reference implementation, starting from this state
+        let mut rng = Rng::from_state([1, 2, 3, 4]);
+        let outputs: [u32; 6] = core::array::from_fn(|_| rng.next_u32());
+        assert_eq!(
+            outputs,
+            [11520, 0, 5927040, 70819200, 2031721883, 1637235492]
+        );
+    }
+
+    #[test]
+    fn seeds_produce_known_sequences() {
+        let cases = [
+            (
+                0,
+                [0x0000_0000, 0x01fc_e552, 0x04f8_d29e, 0x0f8c_1dbd],
+                [0xba27_b524, 0xdee7_6e6c, 0x3753_201f, 0x7e29_b864],
+            ),
+            (
+                1,
+                [0x6889_90c0, 0x9f50_5634, 0x43c5_316a, 0xf2c6_0681],
+                [0x8f93_927e, 0x8bc3_6552, 0x09d8_b72b, 0x5027_b604],
+            ),
+            (
+                0xdead_beef,
+                [0xe628_c683, 0x3f1e_e9cc, 0x78d3_66d1, 0xedc5_c939],
+                [0x378c_6f05, 0xa6f8_63b4, 0x8055_d93f, 0x97c5_b372],
+            ),
+        ];
+        for (seed, state, outputs) in cases {
+            let mut rng = Rng::from_seed(seed);
+            assert_eq!(rng.state(), state, "seed {seed:#x}");
+            let actual: [u32; 4] = core::array::from_fn(|_| rng.next_u32());
+            assert_eq!(actual, outputs, "seed {seed:#x}");
+        }
+    }
+
+    #[test]
+    fn restoring_the_state_carries_on_the_sequence() {
+        let mut rng = Rng::from_seed(42);
+        rng.next_u32();
+        let mut restored = Rng::from_state(rng.state());
+        assert_eq!(restored.next_u32(), rng.next_u32());
+    }
+
+    #[test]
+    #[should_panic]
+    fn the_all_zero_state_is_rejected() {
+        Rng::from_state([0; 4]);
+    }
+
+    #[test]
+    fn below_is_uniform() {
+        let mut rng = Rng::from_seed(1);
+        let mut counts = [0; 10];
+        for _ in 0..DRAWS {
+            counts[rng.below(10) as usize] += 1;
+        }
+        assert_close(&counts, &[DRAWS / 10; 10]);
+    }
+
+    #[test]
+    fn below_handles_awkward_bounds() {
+        let mut rng = Rng::from_seed(2);
+        for _ in 0..1000 {
+            assert_eq!(rng.below(1), 0);
+            assert!(rng.below(3) < 3);
+            // Just over half of the u32s, so nearly half of them get rejected
+            assert!(rng.below(0x8000_0001) <= 0x8000_0000);
+        }
+    }
+
+    #[test]
+    fn range_is_uniform() {
+        let mut rng = Rng::from_seed(3);
+        let mut counts = [0; 7];
+        for _ in 0..DRAWS {
+            let value = rng.range(-3..=3);
+            counts[(value + 3) as usize] += 1;
+        }
+        assert_close(&counts, &[DRAWS / 7; 7]);
+    }
+
+    #[test]
+    fn range_handles_every_kind_of_bound() {
+        let mut rng = Rng::from_seed(4);
+        for _ in 0..1000 {
+            assert_eq!(rng.range(5..6), 5);
+            assert_eq!(rng.range(i32::MAX..), i32::MAX);
+            assert_eq!(rng.range(..=i32::MIN), i32::MIN);
+            assert!((-10..10).contains(&rng.range(-10..10)));
+            assert!(rng.range(..0) < 0);
+            rng.range(..);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_ranges_are_rejected() {
+        Rng::from_seed(5).range(3..3);
+    }
+
+    #[test]
+    fn shuffle_produces_a_permutation() {
+        let mut rng = Rng::from_seed(6);
+        let mut items: [u8; 50] = core::array::from_fn(|index| index as u8);
+        rng.shuffle(&mut items);
+        assert_ne!(items, core::array::from_fn(|index| index as u8));
+        items.sort_unstable();
+        assert_eq!(items, core::array::from_fn(|index| index as u8));
+
+        rng.shuffle(&mut [] as &mut [u8]);
+        let mut one = [7];
+        rng.shuffle(&mut one);
+        assert_eq!(one, [7]);
+    }
+
+    #[test]
+    fn every_order_is_equally_likely() {
+        let mut rng = Rng::from_seed(7);
+        let mut counts = [0; 6];
+        for _ in 0..DRAWS {
+            let mut items = [0, 1, 2];
+            rng.shuffle(&mut items);
+            let order = match items {
+                [0, 1, 2] => 0,
+                [0, 2, 1] => 1,
+                [1, 0, 2] => 2,
+                [1, 2, 0] => 3,
+                [2, 0, 1] => 4,
+                [2, 1, 0] => 5,
+                _ => panic!("{items:?} isn't a permutation"),
+            };
+            counts[order] += 1;
+        }
+        assert_close(&counts, &[DRAWS / 6; 6]);
+    }
+
+    #[test]
+    fn choose_weighted_picks_in_proportion() {
+        let mut rng = Rng::from_seed(8);
+        let weights = [70, 25, 5, 0];
+        let mut counts = [0; 4];
+        for _ in 0..DRAWS {
+            counts[rng.choose_weighted(&weights).unwrap()] += 1;
+        }
+        assert_close(&counts, &weights.map(|weight| DRAWS / 100 * weight));
+        assert_eq!(counts[3], 0);
+    }
+
+    #[test]
+    fn choose_weighted_needs_some_weight() {
+        let mut rng = Rng::from_seed(9);
+        assert_eq!(rng.choose_weighted(&[]), None);
+        assert_eq!(rng.choose_weighted(&[0, 0]), None);
+        assert_eq!(rng.choose_weighted(&[0, 1, 0]), Some(1));
+    }
+
+    #[test]
+    fn choose_picks_from_the_items() {
+        let mut rng = Rng::from_seed(10);
+        assert_eq!(rng.choose::<u8>(&[]), None);
+        let mut counts = [0; 4];
+        for _ in 0..DRAWS {
+            counts[*rng.choose(&[0, 1, 2, 3]).unwrap()] += 1;
+        }
+        assert_close(&counts, &[DRAWS / 4; 4]);
+    }
+
+    #[test]
+    fn generators_survive_a_save() {
+        let mut storage = [0u8; 64];
+        let slot: crate::save::SaveSlot<SavedRng, 16> = crate::save::SaveSlot::new(0, *b"RAND");
+        let mut rng = Rng::from_seed(11);
+        rng.next_u32();
+        slot.save(&mut storage, &SavedRng(rng.clone())).unwrap();
+        let SavedRng(mut loaded) = slot.load(&storage).unwrap();
+        assert_eq!(loaded.next_u32(), rng.next_u32());
+    }
+
+    struct SavedRng(Rng);
+
+    impl crate::save::SaveData for SavedRng {
+        const VERSION: u16 = 1;
+
+        fn save(&self, writer: &mut Writer<'_>) {
+            writer.put(&self.0);
+        }
+
+        fn load(_version: u16, reader: &mut Reader<'_>) -> Option<Self> {
+            Some(Self(reader.get()))
+        }
+    }
+
+    fn fill_sram(bytes: &[u8]) {
+        for (offset, &byte) in bytes.iter().enumerate() {
+            sram::SRAM.index(offset).write(byte);
+        }
+    }
+
+    fn seed_from_sram(bytes: &[u8], range: Range<usize>) -> u32 {
+        mock::reset();
+        fill_sram(bytes);
+        let mut entropy = Entropy::new();
+        entropy.add_sram(range);
+        entropy.hash
+    }
+
+    #[test]
+    fn different_sram_gives_different_seeds() {
+        let a = seed_from_sram(&[1, 2, 3, 4, 5, 6, 7, 8], 0..8);
+        let b = seed_from_sram(&[1, 2, 3, 4, 5, 6, 7, 9], 0..8);
+        let c = seed_from_sram(&[2, 1, 3, 4, 5, 6, 7, 8], 0..8);
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(b, c);
+        assert_eq!(a, seed_from_sram(&[1, 2, 3, 4, 5, 6, 7, 8], 0..8));
+    }
+
+    #[test]
+    fn sram_is_mixed_in_a_word_at_a_time() {
+        let mut expected = Entropy::new();
+        expected.add(0x0102_0304);
+        expected.add(0x0506_0708);
+        assert_eq!(
+            seed_from_sram(&[1, 2, 3, 4, 5, 6, 7, 8], 0..8),
+            expected.hash
+        );
+    }
+
+    #[test]
+    fn trailing_sram_bytes_are_mixed_in() {
+        let whole = seed_from_sram(&[1, 2, 3, 4, 5, 6], 0..4);
+        let partial = seed_from_sram(&[1, 2, 3, 4, 5, 6], 0..6);
+        assert_ne!(whole, partial);
+        assert_ne!(partial, seed_from_sram(&[1, 2, 3, 4, 5, 7], 0..6));
+
+        let mut expected = Entropy::new();
+        expected.add(0x0102_0304);
+        expected.add(0x0506);
+        assert_eq!(partial, expected.hash);
+    }
+
+    const NOTHING: GamePadData = GamePadData::new().with_sgn(true);
+    const A: GamePadData = NOTHING.with_a(true);
+    const START: GamePadData = NOTHING.with_sta(true);
+
+    /// Feeds one frame of input per entry, and returns what `wait_for_start` said each frame.
+    fn wait<const N: usize>(entropy: &mut Entropy, frames: [GamePadData; N]) -> [bool; N] {
+        let mut input = InputState::new();
+        frames.map(|data| {
+            input.update_with(data);
+            entropy.wait_for_start(&input)
+        })
+    }
+
+    #[test]
+    fn wait_for_start_returns_true_on_the_frame_start_is_pressed() {
+        mock::reset();
+        let mut entropy = Entropy::new();
+        let results = wait(&mut entropy, [NOTHING, A, A, START, START, NOTHING]);
+        assert_eq!(results, [false, false, false, true, false, false]);
+    }
+
+    #[test]
+    fn wait_for_start_mixes_in_the_frame_count() {
+        mock::reset();
+        let mut early = Entropy::new();
+        wait(&mut early, [NOTHING, START]);
+        let mut late = Entropy::new();
+        wait(&mut late, [NOTHING, NOTHING, START]);
+        assert_ne!(early.hash, late.hash);
+
+        // The timer isn't running, so it reads 0 both times it's mixed in
+        let mut expected = Entropy::new();
+        expected.add(0);
+        expected.add(3);
+        expected.add(0);
+        assert_eq!(late.hash, expected.hash);
+    }
+}
@@ -1,45 +1,14 @@
 use core::arch::naked_asm;
 
-#[repr(C, align(4))]
-struct ColumnTableContents([u16; 256]);
+use crate::{stack, sys::vip::ColumnTable};
 
-#[rustfmt::skip]
-static COLUMN_TABLE_CONTENTS: ColumnTableContents = ColumnTableContents([
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00e0, 0x00bc,
-    0x00a6, 0x0096, 0x008a, 0x0082, 0x007a, 0x0074, 0x006e, 0x006a,
-    0x0066, 0x0062, 0x0060, 0x005c, 0x005a, 0x0058, 0x0056, 0x0054,
-    0x0052, 0x0050, 0x0050, 0x004e, 0x004c, 0x004c, 0x004a, 0x004a,
-    0x0048, 0x0048, 0x0046, 0x0046, 0x0046, 0x0044, 0x0044, 0x0044,
-    0x0042, 0x0042, 0x0042, 0x0040, 0x0040, 0x0040, 0x0040, 0x0040,
-    0x003e, 0x003e, 0x003e, 0x003e, 0x003e, 0x003e, 0x003e, 0x003c,
-    0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c,
-    0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c,
-    0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c,
-    0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c, 0x003c,
-    0x003c, 0x003e, 0x003e, 0x003e, 0x003e, 0x003e, 0x003e, 0x003e,
-    0x0040, 0x0040, 0x0040, 0x0040, 0x0040, 0x0042, 0x0042, 0x0042,
-    0x0044, 0x0044, 0x0044, 0x0046, 0x0046, 0x0046, 0x0048, 0x0048,
-    0x004a, 0x004a, 0x004c, 0x004c, 0x004e, 0x0050, 0x0050, 0x0052,
-    0x0054, 0x0056, 0x0058, 0x005a, 0x005c, 0x0060, 0x0062, 0x0066,
-    0x006a, 0x006e, 0x0074, 0x007a, 0x0082, 0x008a, 0x0096, 0x00a6,
-    0x00bc, 0x00e0, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-    0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe, 0x00fe,
-]);
+/// Loaded into both column tables at reset, unless the game supplies its own with `column_table!`.
+#[unsafe(export_name = "_vb_rt_default_column_table")]
+#[used]
+static DEFAULT_COLUMN_TABLE: [ColumnTable; 2] = [ColumnTable::DEFAULT; 2];
 
 const LEFT_CTA: usize = 0x0003dc00;
+const WCR: usize = 0x02000024;
 
 #[unsafe(no_mangle)]
 #[unsafe(naked)]
@@ -49,6 +18,12 @@ pub unsafe extern "C" fn _vb_rt_reset() {
     }
 
     naked_asm!("
+    /* configure ROM and expansion wait states */
+        movhi   hi({WCR}), r0, r4
+        movea   lo({WCR}), r4, r4
+        movea   lo(_vb_rt_wcr), r0, r5
+        st.b    r5, 0[r4]
+
         movhi   hi(_data_lma), r0, r4
         movea   lo(_data_lma), r4, r4
         movhi   hi(_data_end), r0, r5
@@ -66,9 +41,30 @@ pub unsafe extern "C" fn _vb_rt_reset() {
     3:
         cmp r5,r6
         blt 2b
-    
-    /* zero out the rest of RAM */
-        movhi   0x0501, r0, r4
+
+        movhi   hi(_ramtext_lma), r0, r4
+        movea   lo(_ramtext_lma), r4, r4
+        movhi   hi(_ramtext_end), r0, r5
+        movea   lo(_ramtext_end), r5, r5
+        movhi   hi(_ramtext_start), r0, r6
+        movea   lo(_ramtext_start), r6, r6
+
+    /* copy code which runs from RAM */
+        jr  11f
+    10:
+        ld.b    0[r4], r7
+        st.b    r7, 0[r6]
+        add 1,r4
+        add 1,r6
+    11:
+        cmp r5,r6
+        blt 10b
+
+    /* zero out .bss and the heap */
+        movhi   hi(_bss_start), r0, r5
+        movea   lo(_bss_start), r5, r5
+        movhi   hi(_stack_bottom), r0, r4
+        movea   lo(_stack_bottom), r4, r4
         jr  5f
     4:
         st.b    r0, 0[r5]
@@ -77,17 +73,30 @@ pub unsafe extern "C" fn _vb_rt_reset() {
         cmp r4, r5
         blt 4b
 
+    /* paint the rest of RAM, so we can measure how much stack gets used */
+        movhi   hi(_stack_top), r0, r4
+        movea   lo(_stack_top), r4, r4
+        movhi   hi({STACK_PAINT}), r0, r6
+        movea   lo({STACK_PAINT}), r6, r6
+        jr  9f
+    8:
+        st.w    r6, 0[r5]
+        add 4, r5
+    9:
+        cmp r4, r5
+        blt 8b
+
     /* initialize the stack */
         movhi   hi(__gp), r0, sp
     /* and global reg */
         movea   lo(__gp), sp, gp
 
-    /* init left column table */
-        movhi   hi({COLUMN_TABLE_CONTENTS}), r0, r6
-        movea   lo({COLUMN_TABLE_CONTENTS}), r6, r6
+    /* init both column tables, which sit next to each other */
+        movhi   hi(_vb_rt_column_table), r0, r6
+        movea   lo(_vb_rt_column_table), r6, r6
         movhi   hi({LEFT_CTA}), r0, r7
         movea   lo({LEFT_CTA}), r7, r7
-        movea   0x0100, r0, r8
+        movea   0x0200, r0, r8
     6:
         ld.h    0[r6], r9
         st.h    r9, 0[r7]
@@ -95,18 +104,6 @@ pub unsafe extern "C" fn _vb_rt_reset() {
         add 2, r7
         add -1, r8
         bnz 6b
-    
-    /* init right column table */
-        movhi   hi({COLUMN_TABLE_CONTENTS}), r0, r6
-        movea   lo({COLUMN_TABLE_CONTENTS}), r6, r6
-        movea   0x0100, r0, r8
-    7:
-        ld.h    0[r6], r9
-        st.h    r9, 0[r7]
-        add 2, r6
-        add 2, r7
-        add -1, r8
-        bnz 7b
 
     /* we're done, load up main */
         movhi   hi({main}), r0, r1
@@ -114,6 +111,7 @@ pub unsafe extern "C" fn _vb_rt_reset() {
         jmp [r1]
     ",
     main = sym _vb_rt_main,
-    COLUMN_TABLE_CONTENTS = sym COLUMN_TABLE_CONTENTS,
-    LEFT_CTA = const LEFT_CTA)
+    LEFT_CTA = const LEFT_CTA,
+    WCR = const WCR,
+    STACK_PAINT = const stack::PAINT)
 }
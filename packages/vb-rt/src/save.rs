@@ -0,0 +1,629 @@
+use core::marker::PhantomData;
+
+use crate::sys::sram;
+
+/// Magic, version, length, sequence number, and CRC.
+const HEADER_SIZE: usize = 16;
+
+/// Somewhere to keep save data.
+/// [`Sram`] is the cartridge's battery-backed SRAM, but plain byte arrays work too.
+pub trait Storage {
+    /// The number of bytes available.
+    fn capacity(&self) -> usize;
+    fn read(&self, offset: usize, buf: &mut [u8]);
+    fn write(&mut self, offset: usize, data: &[u8]);
+}
+
+/// Cartridge SRAM.
+pub struct Sram;
+
+impl Storage for Sram {
+    fn capacity(&self) -> usize {
+        8192
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) {
+        sram::SRAM.read_slice(buf, offset);
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) {
+        sram::SRAM.write_slice(data, offset);
+    }
+}
+
+impl<const N: usize> Storage for [u8; N] {
+    fn capacity(&self) -> usize {
+        N
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) {
+        buf.copy_from_slice(&self[offset..offset + buf.len()]);
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) {
+        self[offset..offset + data.len()].copy_from_slice(data);
+    }
+}
+
+/// Data which can be written to a [`SaveSlot`].
+pub trait SaveData: Sized {
+    /// The current version of the format. Stored alongside the data.
+    const VERSION: u16;
+
+    /// Write this data in the current format.
+    fn save(&self, writer: &mut Writer<'_>);
+
+    /// Read data which was saved with the given version of the format.
+    /// Handle older versions here to migrate them forward.
+    /// Return `None` if the version is unsupported.
+    fn load(version: u16, reader: &mut Reader<'_>) -> Option<Self>;
+}
+
+/// A value which can be written field-by-field with [`Writer::put`] and read with [`Reader::get`].
+pub trait SaveField: Sized {
+    fn save(&self, writer: &mut Writer<'_>);
+    fn load(reader: &mut Reader<'_>) -> Self;
+}
+
+macro_rules! impl_save_field {
+    ($($typ:ty),*) => {
+        $(
+            impl SaveField for $typ {
+                fn save(&self, writer: &mut Writer<'_>) {
+                    writer.write_bytes(&self.to_le_bytes());
+                }
+
+                fn load(reader: &mut Reader<'_>) -> Self {
+                    let mut bytes = [0; size_of::<$typ>()];
+                    reader.read_bytes(&mut bytes);
+                    Self::from_le_bytes(bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_save_field!(u8, i8, u16, i16, u32, i32, u64, i64);
+
+impl SaveField for bool {
+    fn save(&self, writer: &mut Writer<'_>) {
+        writer.write_bytes(&[*self as u8]);
+    }
+
+    fn load(reader: &mut Reader<'_>) -> Self {
+        reader.get::<u8>() != 0
+    }
+}
+
+impl<T: SaveField, const N: usize> SaveField for [T; N] {
+    fn save(&self, writer: &mut Writer<'_>) {
+        for value in self {
+            value.save(writer);
+        }
+    }
+
+    fn load(reader: &mut Reader<'_>) -> Self {
+        core::array::from_fn(|_| T::load(reader))
+    }
+}
+
+/// Writes little-endian values into a save slot's buffer.
+pub struct Writer<'a> {
+    buffer: &'a mut [u8],
+    pos: usize,
+    overflowed: bool,
+}
+
+impl Writer<'_> {
+    pub fn put<T: SaveField>(&mut self, value: &T) {
+        value.save(self);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        let end = self.pos + bytes.len();
+        if end > self.buffer.len() {
+            self.overflowed = true;
+            return;
+        }
+        self.buffer[self.pos..end].copy_from_slice(bytes);
+        self.pos = end;
+    }
+}
+
+/// Reads little-endian values out of a save slot's buffer.
+/// Reading past the end of the saved data yields zeroes,
+/// so fields appended to the end of a format read as zero from older saves.
+pub struct Reader<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    pub fn get<T: SaveField>(&mut self) -> T {
+        T::load(self)
+    }
+
+    pub fn read_bytes(&mut self, bytes: &mut [u8]) {
+        for dst in bytes.iter_mut() {
+            *dst = self.buffer.get(self.pos).copied().unwrap_or(0);
+            self.pos += 1;
+        }
+    }
+
+    /// The number of saved bytes which haven't been read yet.
+    pub fn remaining(&self) -> usize {
+        self.buffer.len().saturating_sub(self.pos)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    /// Nothing has been saved in this slot.
+    Empty,
+    /// Every copy of the data in this slot is damaged.
+    Corrupt,
+    /// The saved data uses a version of the format which [`SaveData::load`] doesn't support.
+    UnsupportedVersion(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveError {
+    /// The data doesn't fit in the slot.
+    TooLarge,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    version: u16,
+    length: u16,
+    sequence: u32,
+}
+
+enum CopyStatus {
+    Missing,
+    Corrupt,
+    Valid(Header),
+}
+
+/// A region of storage holding one `T`, taking up at most `N` bytes once saved.
+///
+/// Each slot keeps two copies of its data, and every save overwrites the older copy.
+/// If the power goes out partway through a save, the other copy is still intact,
+/// and a checksum lets us tell which copy is damaged.
+pub struct SaveSlot<T, const N: usize> {
+    offset: usize,
+    magic: [u8; 4],
+    _data: PhantomData<fn() -> T>,
+}
+
+impl<T: SaveData, const N: usize> SaveSlot<T, N> {
+    /// The number of bytes of storage the slot takes up.
+    pub const SIZE: usize = 2 * (HEADER_SIZE + N);
+
+    /// Define a slot starting at `offset`. The magic number identifies data in this slot,
+    /// so use a different one for each kind of data.
+    pub const fn new(offset: usize, magic: [u8; 4]) -> Self {
+        assert!(
+            N <= u16::MAX as usize,
+            "save slots can hold at most 65535 bytes"
+        );
+        Self {
+            offset,
+            magic,
+            _data: PhantomData,
+        }
+    }
+
+    /// Load the newest undamaged copy of the data.
+    pub fn load<S: Storage>(&self, storage: &S) -> Result<T, LoadError> {
+        self.check_capacity(storage);
+        let (copy, header) = self.newest(storage)?;
+        let mut buffer = [0u8; N];
+        let data = &mut buffer[..header.length as usize];
+        storage.read(self.copy_offset(copy) + HEADER_SIZE, data);
+        let mut reader = Reader {
+            buffer: data,
+            pos: 0,
+        };
+        T::load(header.version, &mut reader).ok_or(LoadError::UnsupportedVersion(header.version))
+    }
+
+    /// Save the data, replacing the older of the two copies.
+    pub fn save<S: Storage>(&self, storage: &mut S, data: &T) -> Result<(), SaveError> {
+        self.check_capacity(storage);
+        let mut buffer = [0u8; N];
+        let mut writer = Writer {
+            buffer: &mut buffer,
+            pos: 0,
+            overflowed: false,
+        };
+        data.save(&mut writer);
+        if writer.overflowed {
+            return Err(SaveError::TooLarge);
+        }
+        let length = writer.pos;
+
+        let (copy, sequence) = match self.newest(storage) {
+            Ok((copy, header)) => (1 - copy, header.sequence.wrapping_add(1)),
+            Err(_) => (0, 0),
+        };
+        let header = self.encode_header(
+            Header {
+                version: T::VERSION,
+                length: length as u16,
+                sequence,
+            },
+            &buffer[..length],
+        );
+        let offset = self.copy_offset(copy);
+        storage.write(offset + HEADER_SIZE, &buffer[..length]);
+        storage.write(offset, &header);
+        Ok(())
+    }
+
+    /// Erase both copies of the data.
+    pub fn erase<S: Storage>(&self, storage: &mut S) {
+        self.check_capacity(storage);
+        for copy in 0..2 {
+            storage.write(self.copy_offset(copy), &[0; HEADER_SIZE]);
+        }
+    }
+
+    fn newest<S: Storage>(&self, storage: &S) -> Result<(usize, Header), LoadError> {
+        let mut newest: Option<(usize, Header)> = None;
+        let mut corrupt = false;
+        for copy in 0..2 {
+            match self.read_copy(storage, copy) {
+                CopyStatus::Missing => {}
+                CopyStatus::Corrupt => corrupt = true,
+                CopyStatus::Valid(header) => {
+                    let is_newer = newest.is_none_or(|(_, other)| {
+                        header.sequence.wrapping_sub(other.sequence) as i32 > 0
+                    });
+                    if is_newer {
+                        newest = Some((copy, header));
+                    }
+                }
+            }
+        }
+        match newest {
+            Some(newest) => Ok(newest),
+            None if corrupt => Err(LoadError::Corrupt),
+            None => Err(LoadError::Empty),
+        }
+    }
+
+    fn read_copy<S: Storage>(&self, storage: &S, copy: usize) -> CopyStatus {
+        let offset = self.copy_offset(copy);
+        let mut raw = [0u8; HEADER_SIZE];
+        storage.read(offset, &mut raw);
+        if raw[0..4] != self.magic {
+            return CopyStatus::Missing;
+        }
+        let header = Header {
+            version: u16::from_le_bytes([raw[4], raw[5]]),
+            length: u16::from_le_bytes([raw[6], raw[7]]),
+            sequence: u32::from_le_bytes([raw[8], raw[9], raw[10], raw[11]]),
+        };
+        let stored_crc = u32::from_le_bytes([raw[12], raw[13], raw[14], raw[15]]);
+        if header.length as usize > N {
+            return CopyStatus::Corrupt;
+        }
+
+        let mut crc = crc32_update(!0, &raw[0..12]);
+        let mut chunk = [0u8; 16];
+        let mut pos = 0;
+        while pos < header.length as usize {
+            let len = chunk.len().min(header.length as usize - pos);
+            storage.read(offset + HEADER_SIZE + pos, &mut chunk[..len]);
+            crc = crc32_update(crc, &chunk[..len]);
+            pos += len;
+        }
+        if !crc != stored_crc {
+            return CopyStatus::Corrupt;
+        }
+        CopyStatus::Valid(header)
+    }
+
+    fn encode_header(&self, header: Header, data: &[u8]) -> [u8; HEADER_SIZE] {
+        let mut raw = [0u8; HEADER_SIZE];
+        raw[0..4].copy_from_slice(&self.magic);
+        raw[4..6].copy_from_slice(&header.version.to_le_bytes());
+        raw[6..8].copy_from_slice(&header.length.to_le_bytes());
+        raw[8..12].copy_from_slice(&header.sequence.to_le_bytes());
+        let crc = !crc32_update(crc32_update(!0, &raw[0..12]), data);
+        raw[12..16].copy_from_slice(&crc.to_le_bytes());
+        raw
+    }
+
+    const fn copy_offset(&self, copy: usize) -> usize {
+        self.offset + copy * (HEADER_SIZE + N)
+    }
+
+    fn check_capacity<S: Storage>(&self, storage: &S) {
+        assert!(
+            self.offset + Self::SIZE <= storage.capacity(),
+            "save slot does not fit in storage"
+        );
+    }
+}
+
+const CRC_TABLE: [u32; 16] = {
+    let mut table = [0; 16];
+    let mut index = 0;
+    while index < 16 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 4 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+/// CRC-32, a nibble at a time to keep the table small.
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc = (crc >> 4) ^ CRC_TABLE[((crc ^ *byte as u32) & 0x0f) as usize];
+        crc = (crc >> 4) ^ CRC_TABLE[((crc ^ (*byte as u32 >> 4)) & 0x0f) as usize];
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Progress {
+        level: u8,
+        score: u32,
+    }
+
+    impl SaveData for Progress {
+        const VERSION: u16 = 2;
+
+        fn save(&self, writer: &mut Writer<'_>) {
+            writer.put(&self.level);
+            writer.put(&self.score);
+        }
+
+        fn load(version: u16, reader: &mut Reader<'_>) -> Option<Self> {
+            match version {
+                // Version 1 only had a 16-bit score, and everyone started on level 1.
+                1 => Some(Self {
+                    level: 1,
+                    score: reader.get::<u16>() as u32,
+                }),
+                2 => Some(Self {
+                    level: reader.get(),
+                    score: reader.get(),
+                }),
+                _ => None,
+            }
+        }
+    }
+
+    /// How `Progress` was saved before it had levels.
+    struct ProgressV1 {
+        score: u16,
+    }
+
+    impl SaveData for ProgressV1 {
+        const VERSION: u16 = 1;
+
+        fn save(&self, writer: &mut Writer<'_>) {
+            writer.put(&self.score);
+        }
+
+        fn load(_version: u16, _reader: &mut Reader<'_>) -> Option<Self> {
+            unreachable!()
+        }
+    }
+
+    const SLOT: SaveSlot<Progress, 8> = SaveSlot::new(4, *b"TEST");
+    const COPY_B: usize = 4 + HEADER_SIZE + 8;
+
+    fn progress(score: u32) -> Progress {
+        Progress { level: 3, score }
+    }
+
+    fn sequence(storage: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(storage[offset + 8..offset + 12].try_into().unwrap())
+    }
+
+    #[test]
+    fn empty_storage_has_nothing_to_load() {
+        let storage = [0u8; 64];
+        assert_eq!(SLOT.load(&storage), Err(LoadError::Empty));
+    }
+
+    #[test]
+    fn saved_data_loads_back() {
+        let mut storage = [0u8; 64];
+        SLOT.save(&mut storage, &progress(1234)).unwrap();
+        assert_eq!(SLOT.load(&storage), Ok(progress(1234)));
+    }
+
+    #[test]
+    fn saves_alternate_between_the_two_copies() {
+        let mut storage = [0u8; 64];
+        SLOT.save(&mut storage, &progress(1)).unwrap();
+        assert_eq!(&storage[4..8], b"TEST");
+        assert_eq!(&storage[COPY_B..COPY_B + 4], &[0; 4]);
+
+        SLOT.save(&mut storage, &progress(2)).unwrap();
+        assert_eq!(&storage[COPY_B..COPY_B + 4], b"TEST");
+        assert_eq!((sequence(&storage, 4), sequence(&storage, COPY_B)), (0, 1));
+        assert_eq!(SLOT.load(&storage), Ok(progress(2)));
+
+        SLOT.save(&mut storage, &progress(3)).unwrap();
+        assert_eq!((sequence(&storage, 4), sequence(&storage, COPY_B)), (2, 1));
+        assert_eq!(SLOT.load(&storage), Ok(progress(3)));
+    }
+
+    #[test]
+    fn the_slot_stays_inside_its_bounds() {
+        let mut storage = [0xffu8; 64];
+        storage[4..4 + SaveSlot::<Progress, 8>::SIZE].fill(0);
+        SLOT.save(&mut storage, &progress(1)).unwrap();
+        SLOT.save(&mut storage, &progress(2)).unwrap();
+        assert_eq!(&storage[..4], &[0xff; 4]);
+        assert!(
+            storage[4 + SaveSlot::<Progress, 8>::SIZE..]
+                .iter()
+                .all(|b| *b == 0xff)
+        );
+    }
+
+    #[test]
+    fn sequence_numbers_wrap_around() {
+        let mut storage = [0u8; 64];
+        SLOT.save(&mut storage, &progress(1)).unwrap();
+        SLOT.save(&mut storage, &progress(2)).unwrap();
+        // Pretend copy B was saved with the highest sequence number, and fix up its CRC.
+        let header = SLOT.encode_header(
+            Header {
+                version: 2,
+                length: 5,
+                sequence: u32::MAX,
+            },
+            &storage[COPY_B + HEADER_SIZE..COPY_B + HEADER_SIZE + 5],
+        );
+        storage[COPY_B..COPY_B + HEADER_SIZE].copy_from_slice(&header);
+
+        SLOT.save(&mut storage, &progress(3)).unwrap();
+        assert_eq!(sequence(&storage, 4), 0);
+        assert_eq!(SLOT.load(&storage), Ok(progress(3)));
+    }
+
+    #[test]
+    fn a_corrupt_copy_falls_back_to_the_other() {
+        let mut storage = [0u8; 64];
+        SLOT.save(&mut storage, &progress(1)).unwrap();
+        SLOT.save(&mut storage, &progress(2)).unwrap();
+        // The power went out partway through writing copy B.
+        storage[COPY_B + HEADER_SIZE + 1] ^= 0x40;
+        assert_eq!(SLOT.load(&storage), Ok(progress(1)));
+
+        // The next save replaces the damaged copy, not the good one.
+        SLOT.save(&mut storage, &progress(3)).unwrap();
+        assert_eq!(sequence(&storage, 4), 0);
+        assert_eq!(SLOT.load(&storage), Ok(progress(3)));
+    }
+
+    #[test]
+    fn a_corrupt_header_falls_back_to_the_other_copy() {
+        let mut storage = [0u8; 64];
+        SLOT.save(&mut storage, &progress(1)).unwrap();
+        SLOT.save(&mut storage, &progress(2)).unwrap();
+        storage[4 + 6] = 0xff;
+        assert_eq!(SLOT.load(&storage), Ok(progress(2)));
+    }
+
+    #[test]
+    fn two_corrupt_copies_fail_to_load() {
+        let mut storage = [0u8; 64];
+        SLOT.save(&mut storage, &progress(1)).unwrap();
+        SLOT.save(&mut storage, &progress(2)).unwrap();
+        storage[4 + HEADER_SIZE] ^= 1;
+        storage[COPY_B + 15] ^= 1;
+        assert_eq!(SLOT.load(&storage), Err(LoadError::Corrupt));
+    }
+
+    #[test]
+    fn old_versions_are_migrated_on_load() {
+        let mut storage = [0u8; 64];
+        let old: SaveSlot<ProgressV1, 8> = SaveSlot::new(4, *b"TEST");
+        old.save(&mut storage, &ProgressV1 { score: 500 }).unwrap();
+        assert_eq!(
+            SLOT.load(&storage),
+            Ok(Progress {
+                level: 1,
+                score: 500
+            })
+        );
+
+        // Saving again writes the current version, and keeps the old copy as a fallback.
+        SLOT.save(&mut storage, &progress(600)).unwrap();
+        assert_eq!(
+            u16::from_le_bytes([storage[COPY_B + 4], storage[COPY_B + 5]]),
+            2
+        );
+        assert_eq!(SLOT.load(&storage), Ok(progress(600)));
+    }
+
+    #[test]
+    fn unknown_versions_are_reported() {
+        let mut storage = [0u8; 64];
+        struct FromTheFuture;
+        impl SaveData for FromTheFuture {
+            const VERSION: u16 = 7;
+            fn save(&self, _writer: &mut Writer<'_>) {}
+            fn load(_version: u16, _reader: &mut Reader<'_>) -> Option<Self> {
+                unreachable!()
+            }
+        }
+        let future: SaveSlot<FromTheFuture, 8> = SaveSlot::new(4, *b"TEST");
+        future.save(&mut storage, &FromTheFuture).unwrap();
+        assert_eq!(SLOT.load(&storage), Err(LoadError::UnsupportedVersion(7)));
+    }
+
+    #[test]
+    fn other_magic_numbers_are_ignored() {
+        let mut storage = [0u8; 64];
+        SLOT.save(&mut storage, &progress(1)).unwrap();
+        let other: SaveSlot<Progress, 8> = SaveSlot::new(4, *b"ELSE");
+        assert_eq!(other.load(&storage), Err(LoadError::Empty));
+    }
+
+    #[test]
+    fn data_which_doesnt_fit_is_not_saved() {
+        let mut storage = [0u8; 64];
+        let small: SaveSlot<Progress, 4> = SaveSlot::new(4, *b"TEST");
+        assert_eq!(
+            small.save(&mut storage, &progress(1)),
+            Err(SaveError::TooLarge)
+        );
+        assert_eq!(storage, [0; 64]);
+    }
+
+    #[test]
+    fn erasing_empties_the_slot() {
+        let mut storage = [0u8; 64];
+        SLOT.save(&mut storage, &progress(1)).unwrap();
+        SLOT.save(&mut storage, &progress(2)).unwrap();
+        SLOT.erase(&mut storage);
+        assert_eq!(SLOT.load(&storage), Err(LoadError::Empty));
+    }
+
+    #[test]
+    fn fields_missing_from_short_saves_read_as_zero() {
+        let mut reader = Reader {
+            buffer: &[0x34, 0x12],
+            pos: 0,
+        };
+        assert_eq!(reader.get::<u32>(), 0x1234);
+        assert_eq!(reader.remaining(), 0);
+        assert!(!reader.get::<bool>());
+    }
+
+    #[test]
+    fn crc_matches_crc32() {
+        assert_eq!(!crc32_update(!0, b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn the_sram_slot_round_trips_through_cartridge_sram() {
+        crate::sys::mock::reset();
+        SLOT.save(&mut Sram, &progress(77)).unwrap();
+        assert_eq!(SLOT.load(&Sram), Ok(progress(77)));
+    }
+}
@@ -0,0 +1,87 @@
+//! Keep an eye on the stack.
+//!
+//! The stack starts at the top of RAM and grows down towards .bss (or the heap, if there is one).
+//! At reset, the whole region is painted with a known pattern,
+//! so we can tell how deep it has ever grown by looking for the first unpainted word.
+
+/// The pattern painted over the stack at reset.
+pub(crate) const PAINT: u32 = 0xdead_beef;
+
+/// The number of words at the bottom of the stack which are checked for overflow.
+const CANARY_WORDS: usize = 4;
+
+unsafe extern "C" {
+    static _stack_bottom: u32;
+    static _stack_top: u32;
+}
+
+unsafe extern "Rust" {
+    unsafe fn _vb_rt_stack_overflow_handler();
+}
+
+fn bounds() -> (*const u32, *const u32) {
+    (&raw const _stack_bottom, &raw const _stack_top)
+}
+
+pub(crate) fn pointer() -> usize {
+    let sp: usize;
+    unsafe { core::arch::asm!("mov sp, {}", out(reg) sp, options(nomem, nostack)) };
+    sp
+}
+
+/// The number of bytes reserved for the stack.
+pub fn size() -> usize {
+    let (bottom, top) = bounds();
+    top.addr() - bottom.addr()
+}
+
+/// The number of bytes of stack in use right now.
+pub fn used() -> usize {
+    let (_, top) = bounds();
+    top.addr().saturating_sub(pointer())
+}
+
+/// The most bytes of stack which have ever been in use at once.
+/// This scans the stack for painted memory, so avoid calling it every frame.
+/// If the stack has overflowed, this is the whole stack.
+pub fn high_water_mark() -> usize {
+    let (bottom, top) = bounds();
+    let mut word = bottom;
+    // SAFETY: everything between the bounds is stack
+    while word < top && unsafe { word.read_volatile() } == PAINT {
+        word = word.wrapping_add(1);
+    }
+    top.addr() - word.addr()
+}
+
+/// Returns false if anything has written to the bottom few words of the stack.
+pub fn canary_intact() -> bool {
+    let (bottom, top) = bounds();
+    (0..CANARY_WORDS)
+        .map(|index| bottom.wrapping_add(index))
+        .take_while(|word| *word < top)
+        // SAFETY: everything between the bounds is stack
+        .all(|word| unsafe { word.read_volatile() } == PAINT)
+}
+
+/// Run the stack overflow handler if the bottom of the stack has been clobbered.
+/// The handler panics by default, override it with `stack_overflow_handler!`.
+///
+/// With the `stack-canary` feature, this runs at the start of every VIP interrupt.
+pub fn check_canary() {
+    if !canary_intact() {
+        unsafe { _vb_rt_stack_overflow_handler() };
+    }
+}
+
+#[doc(hidden)]
+#[inline(always)]
+pub fn on_vip_interrupt() {
+    #[cfg(feature = "stack-canary")]
+    check_canary();
+}
+
+#[unsafe(no_mangle)]
+pub fn default_stack_overflow_handler() {
+    panic!("stack overflow");
+}
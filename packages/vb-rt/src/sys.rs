@@ -1,4 +1,6 @@
 pub mod hardware;
+#[cfg(feature = "host-mock")]
+pub mod mock;
 pub mod sram;
 pub mod vip;
 mod volatile;
@@ -6,4 +8,9 @@ pub mod vsu;
 
 pub use volatile::{OveralignedVolatilePointer, VolatilePointer};
 
+#[cfg(target_arch = "v810")]
 pub use core::arch::v810::*;
+
+/// Stand-in for the HALT instruction on the host. Returns immediately, as if an interrupt had fired.
+#[cfg(not(target_arch = "v810"))]
+pub fn halt() {}
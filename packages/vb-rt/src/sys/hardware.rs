@@ -1,6 +1,58 @@
 use bitfield_struct::bitfield;
 
-use super::volatile::mmio;
+use super::volatile::{bool_enum, mmio};
+
+bool_enum! {
+    /// Controls which console drives the clock during link port transfers.
+    pub CommClock(
+        /// Wait for the other console's clock signal
+        External,
+        /// Provide the clock signal to the other console
+        Internal
+    )
+}
+
+#[bitfield(u8)]
+pub struct CommControlData {
+    _padding0: bool,
+    /// Set while a transfer is in progress
+    pub c_stat: bool,
+    /// Set to begin a transfer
+    pub c_start: bool,
+    _padding1: bool,
+    /// Specifies which console drives the clock
+    #[bits(1)]
+    pub c_clk_sel: CommClock,
+    #[bits(2)]
+    _padding2: u8,
+    /// When set, communication interrupts will not fire. Set to acknowledge an interrupt.
+    pub c_int_inh: bool,
+}
+
+#[bitfield(u8)]
+pub struct CommCountData {
+    /// The current level of the COMCNT line
+    pub cc_rd: bool,
+    /// When clear, this console pulls the COMCNT line low
+    pub cc_wr: bool,
+    /// The COMCNT level to compare against
+    pub cc_smp: bool,
+    /// When set, an interrupt fires when the COMCNT line matches cc_smp
+    pub cc_sig: bool,
+    #[bits(3)]
+    _padding: u8,
+    /// When set, COMCNT interrupts will not fire
+    pub cc_int_inh: bool,
+}
+
+mmio! {
+    pub const CCR: CommControlData = 0x02000000;
+    pub const CCSR: CommCountData = 0x02000004;
+    /// The byte to send during the next link port transfer.
+    pub const CDTR: u8 = 0x02000008;
+    /// The byte received during the last link port transfer.
+    pub const CDRR: u8 = 0x0200000c;
+}
 
 /// The lower 8 bits of serial (controller) data
 #[bitfield(u8)]
@@ -49,6 +101,54 @@ mmio! {
     pub const SDHR: SerialDataHigh = 0x02000014;
 }
 
+bool_enum! {
+    /// Controls how often the timer counter decrements.
+    pub TimerResolution(
+        /// Tick every 100µs
+        Micros100,
+        /// Tick every 20µs
+        Micros20
+    )
+}
+
+#[bitfield(u8)]
+pub struct TimerControlData {
+    /// When set, the timer is enabled.
+    pub t_enb: bool,
+    /// Set when the timer counter has reached zero.
+    pub z_stat: bool,
+    /// When set, clears the zero status flag.
+    pub z_stat_clr: bool,
+    /// When set, an interrupt fires when the timer counter reaches zero.
+    pub tim_z_int: bool,
+    /// Specifies how often the timer counter decrements.
+    #[bits(1)]
+    pub t_clk_sel: TimerResolution,
+    #[bits(3)]
+    _padding: u8,
+}
+
+mmio! {
+    /// The lower 8 bits of the timer counter. Writes set the reload value.
+    pub const TLR: u8 = 0x02000018;
+    /// The upper 8 bits of the timer counter. Writes set the reload value.
+    pub const THR: u8 = 0x0200001c;
+    pub const TCR: TimerControlData = 0x02000020;
+}
+
+#[bitfield(u8)]
+pub struct WaitControlData {
+    /// When set, cartridge ROM is accessed with 1 wait state instead of 2
+    pub rom1w: bool,
+    /// When set, the expansion area is accessed with 1 wait state instead of 2
+    pub exp1w: bool,
+    #[bits(6)]
+    _padding: u8,
+}
+mmio! {
+    pub const WCR: WaitControlData = 0x02000024;
+}
+
 #[bitfield(u8)]
 pub struct SerialControlData {
     /// When set, aborts hardware reads
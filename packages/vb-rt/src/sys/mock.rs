@@ -0,0 +1,123 @@
+//! A simulated address space, so that code which touches hardware can run on the host.
+//!
+//! With the `host-mock` feature, every [`VolatilePointer`](super::VolatilePointer) reads and writes
+//! this memory instead of real hardware. It covers the VIP, the VSU, the hardware control registers
+//! and SRAM. Touching any other address panics.
+//!
+//! Each thread has its own memory, starting out zeroed, so tests can run in parallel.
+//! ```ignore
+//! vb_rt::sys::mock::reset();
+//! vb_graphics::set_bkcol(2);
+//! assert_eq!(vb_rt::sys::vip::BKCOL.read(), 2);
+//! ```
+
+extern crate std;
+
+use std::{cell::RefCell, string::String, vec, vec::Vec};
+
+/// The name, start address and size of every simulated region.
+const REGIONS: [(&str, usize, usize); 4] = [
+    ("VIP", 0x0000_0000, 0x0008_0000),
+    ("VSU", 0x0100_0000, 0x0000_0800),
+    ("hardware", 0x0200_0000, 0x0000_0040),
+    ("SRAM", 0x0600_0000, 0x0000_4000),
+];
+
+/// The VIP mirrors its four blocks of character data here, as one contiguous block.
+const CHARACTER_MIRROR_START: usize = 0x0007_8000;
+const CHARACTER_MIRROR_END: usize = 0x0008_0000;
+
+const STDOUT: usize = 0x0200_0030;
+
+/// NP is set at reset.
+const RESET_PSW: u32 = 0x0000_8000;
+
+struct Memory {
+    regions: Vec<Vec<u8>>,
+    stdout: Vec<u8>,
+    psw: u32,
+}
+
+impl Memory {
+    fn new() -> Self {
+        Self {
+            regions: REGIONS.iter().map(|(_, _, size)| vec![0; *size]).collect(),
+            stdout: vec![],
+            psw: RESET_PSW,
+        }
+    }
+
+    fn bytes(&mut self, address: usize, len: usize) -> &mut [u8] {
+        let address = unmirror(address);
+        for ((_, start, size), region) in REGIONS.iter().zip(&mut self.regions) {
+            if address >= *start && address + len <= start + size {
+                let offset = address - start;
+                return &mut region[offset..offset + len];
+            }
+        }
+        panic!("address {address:#010x} is not simulated");
+    }
+}
+
+std::thread_local! {
+    static MEMORY: RefCell<Memory> = RefCell::new(Memory::new());
+}
+
+fn unmirror(address: usize) -> usize {
+    if (CHARACTER_MIRROR_START..CHARACTER_MIRROR_END).contains(&address) {
+        let offset = address - CHARACTER_MIRROR_START;
+        0x6000 + (offset / 0x2000) * 0x8000 + (offset % 0x2000)
+    } else {
+        address
+    }
+}
+
+pub(crate) fn load(address: usize, buf: &mut [u8]) {
+    MEMORY.with_borrow_mut(|memory| buf.copy_from_slice(memory.bytes(address, buf.len())));
+}
+
+pub(crate) fn store(address: usize, bytes: &[u8]) {
+    MEMORY.with_borrow_mut(|memory| {
+        if address == STDOUT {
+            memory.stdout.extend_from_slice(bytes);
+        }
+        memory.bytes(address, bytes.len()).copy_from_slice(bytes);
+    });
+}
+
+pub(crate) fn psw() -> u32 {
+    MEMORY.with_borrow(|memory| memory.psw)
+}
+
+pub(crate) fn set_psw(psw: u32) {
+    MEMORY.with_borrow_mut(|memory| memory.psw = psw);
+}
+
+/// Zero this thread's simulated memory, and forget anything written to STDOUT.
+pub fn reset() {
+    MEMORY.set(Memory::new());
+}
+
+/// Copy simulated memory into `buf`, starting at `address`.
+pub fn read_bytes(address: usize, buf: &mut [u8]) {
+    load(address, buf);
+}
+
+/// Copy `bytes` into simulated memory, starting at `address`.
+/// Unlike writing through a pointer, this doesn't count as output.
+pub fn write_bytes(address: usize, bytes: &[u8]) {
+    MEMORY.with_borrow_mut(|memory| memory.bytes(address, bytes.len()).copy_from_slice(bytes));
+}
+
+/// Get `len` bytes of simulated memory, starting at `address`.
+pub fn bytes(address: usize, len: usize) -> Vec<u8> {
+    let mut result = vec![0; len];
+    load(address, &mut result);
+    result
+}
+
+/// Everything written to STDOUT since the last call, or since the last [`reset`].
+pub fn take_stdout() -> String {
+    let stdout = MEMORY.with_borrow_mut(|memory| core::mem::take(&mut memory.stdout));
+    String::from_utf8_lossy(&stdout).into_owned()
+}
@@ -2,9 +2,27 @@ use bitfield_struct::bitfield;
 
 use super::volatile::{mmio, mmstruct};
 
+/// One column of a frame buffer: 256 rows of 2-bit pixels, 16 rows to a word.
+/// The top row of each word is in its lowest bits. Only the top 224 rows are displayed.
+#[repr(C, align(4))]
+#[derive(Clone, Copy)]
+pub struct FrameBufferColumn(pub [u32; 16]);
+const _: () = assert!(size_of::<FrameBufferColumn>() == 0x40);
+
+/// A whole frame buffer, stored column by column from the left edge of the screen.
+pub type FrameBuffer = [FrameBufferColumn; 384];
+
+mmio! {
+    pub const LEFT_FRAME_BUFFER_0: FrameBuffer = 0x00000000, size = 0x6000;
+    pub const LEFT_FRAME_BUFFER_1: FrameBuffer = 0x00008000, size = 0x6000;
+    pub const RIGHT_FRAME_BUFFER_0: FrameBuffer = 0x00010000, size = 0x6000;
+    pub const RIGHT_FRAME_BUFFER_1: FrameBuffer = 0x00018000, size = 0x6000;
+}
+
 #[repr(C, align(4))]
 #[derive(Clone, Copy)]
 pub struct Character(pub [u16; 8]);
+const _: () = assert!(size_of::<Character>() == 0x10);
 
 mmio! {
     pub const CHARACTERS: [Character; 2048] = 0x00078000;
@@ -44,6 +62,7 @@ mmstruct! {
         pub hofstr: i16,
     }
 }
+const _: () = assert!(size_of::<HBiasElement>() == 0x4);
 
 mmstruct! {
     #[repr(C)]
@@ -57,6 +76,26 @@ mmstruct! {
         _padding: [i16; 3],
     }
 }
+const _: () = assert!(size_of::<AffineElement>() == 0x10);
+
+impl AffineElement {
+    pub const fn new(
+        mx: fixed::types::I13F3,
+        mp: i16,
+        my: fixed::types::I13F3,
+        dx: fixed::types::I7F9,
+        dy: fixed::types::I7F9,
+    ) -> Self {
+        Self {
+            mx,
+            mp,
+            my,
+            dx,
+            dy,
+            _padding: [0; 3],
+        }
+    }
+}
 
 mmio! {
     pub const BG_CELLS: [Cell; 64 * 64 * 16] = 0x00020000, size = 0x20000;
@@ -90,6 +129,7 @@ impl WorldMode {
 }
 
 #[bitfield(u16)]
+#[derive(PartialEq, Eq)]
 pub struct WorldHeader {
     /// The index of the first background map in the world's background.
     #[bits(4)]
@@ -117,7 +157,7 @@ pub struct WorldHeader {
 
 mmstruct! {
     #[repr(C, align(4))]
-    #[derive(Clone, Copy)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     pub struct World {
         /// Describes the world.
         pub header: WorldHeader,
@@ -144,11 +184,112 @@ mmstruct! {
         _pad: [u16; 5],
     }
 }
+const _: () = assert!(size_of::<World>() == 0x20);
+
+impl World {
+    /// A world with this header, and everything else zeroed.
+    pub const fn new(header: WorldHeader) -> Self {
+        Self {
+            header,
+            gx: 0,
+            gp: 0,
+            gy: 0,
+            mx: 0,
+            mp: 0,
+            my: 0,
+            w: 0,
+            h: 0,
+            param_base: 0,
+            overplane_character: 0,
+            _pad: [0; 5],
+        }
+    }
+}
 
 mmio! {
     pub const WORLDS: [World; 32] = 0x0003d800;
 }
 
+/// One entry in a column table.
+#[bitfield(u16)]
+pub struct ColumnTableEntry {
+    /// How long the display spends on this column.
+    pub time: u8,
+    /// Each column is lit `repeat + 1` times, so higher values are brighter.
+    #[bits(4)]
+    pub repeat: u8,
+    #[bits(4)]
+    _pad: u8,
+}
+
+/// The contents of one eye's column table.
+#[repr(C, align(4))]
+#[derive(Clone, Copy)]
+pub struct ColumnTable(pub [ColumnTableEntry; 256]);
+const _: () = assert!(size_of::<ColumnTable>() == 0x200);
+
+impl ColumnTable {
+    /// The table which the runtime loads at reset, unless the game supplies its own with `vb_rt::column_table!`.
+    /// Columns near the edges of the mirrors' sweep take longer, to make up for the mirrors moving unevenly.
+    pub const DEFAULT: Self = Self::from_times(&DEFAULT_COLUMN_TIMES);
+
+    /// A table with the given times, and a repeat of 0.
+    pub const fn from_times(times: &[u8; 256]) -> Self {
+        let mut entries = [ColumnTableEntry::new(); 256];
+        let mut index = 0;
+        while index < 256 {
+            entries[index] = ColumnTableEntry::new().with_time(times[index]);
+            index += 1;
+        }
+        Self(entries)
+    }
+
+    /// The same table, with every entry's repeat set to `repeat`.
+    pub const fn with_repeat(self, repeat: u8) -> Self {
+        self.with_repeats(&[repeat; 256])
+    }
+
+    /// The same table, with a different repeat for each entry.
+    pub const fn with_repeats(mut self, repeats: &[u8; 256]) -> Self {
+        let mut index = 0;
+        while index < 256 {
+            assert!(
+                repeats[index] < 16,
+                "Column table repeats must be less than 16"
+            );
+            self.0[index] = self.0[index].with_repeat(repeats[index]);
+            index += 1;
+        }
+        self
+    }
+}
+
+#[rustfmt::skip]
+const DEFAULT_COLUMN_TIMES: [u8; 256] = [
+    0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe,
+    0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe,
+    0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe,
+    0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xe0, 0xbc,
+    0xa6, 0x96, 0x8a, 0x82, 0x7a, 0x74, 0x6e, 0x6a, 0x66, 0x62, 0x60, 0x5c, 0x5a, 0x58, 0x56, 0x54,
+    0x52, 0x50, 0x50, 0x4e, 0x4c, 0x4c, 0x4a, 0x4a, 0x48, 0x48, 0x46, 0x46, 0x46, 0x44, 0x44, 0x44,
+    0x42, 0x42, 0x42, 0x40, 0x40, 0x40, 0x40, 0x40, 0x3e, 0x3e, 0x3e, 0x3e, 0x3e, 0x3e, 0x3e, 0x3c,
+    0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c,
+    0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c, 0x3c,
+    0x3c, 0x3e, 0x3e, 0x3e, 0x3e, 0x3e, 0x3e, 0x3e, 0x40, 0x40, 0x40, 0x40, 0x40, 0x42, 0x42, 0x42,
+    0x44, 0x44, 0x44, 0x46, 0x46, 0x46, 0x48, 0x48, 0x4a, 0x4a, 0x4c, 0x4c, 0x4e, 0x50, 0x50, 0x52,
+    0x54, 0x56, 0x58, 0x5a, 0x5c, 0x60, 0x62, 0x66, 0x6a, 0x6e, 0x74, 0x7a, 0x82, 0x8a, 0x96, 0xa6,
+    0xbc, 0xe0, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe,
+    0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe,
+    0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe,
+    0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe,
+];
+
+mmio! {
+    pub const COLUMN_TABLES: [[ColumnTableEntry; 256]; 2] = 0x0003dc00, size = 0x400;
+    pub const LEFT_COLUMN_TABLE: [ColumnTableEntry; 256] = 0x0003dc00;
+    pub const RIGHT_COLUMN_TABLE: [ColumnTableEntry; 256] = 0x0003de00;
+}
+
 #[bitfield(u16)]
 pub struct ObjectStereo {
     /// The signed parallax offset applied to the horizontal coordinate.
@@ -176,6 +317,7 @@ mmstruct! {
         pub cell: Cell,
     }
 }
+const _: () = assert!(size_of::<Object>() == 0x8);
 
 mmio! {
     pub const OBJS: [Object; 1024] = 0x0003e000;
@@ -245,6 +387,20 @@ mmio! {
     pub const BRTB: u16 = 0x0005f826;
     pub const BRTC: u16 = 0x0005f828;
     pub const REST: u16 = 0x0005f82a;
+    /// How many display frames each game frame lasts, minus one. Set to 1 for 25 fps, or 2 for about 17 fps.
+    pub const FRMCYC: u16 = 0x0005f82e;
+}
+
+#[bitfield(u16)]
+pub struct ColumnTableAddress {
+    /// The index of the left column table entry the display is reading.
+    pub cta_l: u8,
+    /// The index of the right column table entry the display is reading.
+    pub cta_r: u8,
+}
+
+mmio! {
+    pub const CTA: ColumnTableAddress = 0x0005f830;
 }
 
 #[bitfield(u16)]
@@ -275,6 +431,8 @@ pub struct DrawingFlags {
 mmio! {
     pub const XPSTTS: DrawingFlags = 0x0005f840;
     pub const XPCTRL: DrawingFlags = 0x0005f842;
+    /// The VIP's version number.
+    pub const VER: u16 = 0x0005f844;
 
     pub const SPT: [u16; 4] = 0x0005f848;
     pub const SPT0: u16 = 0x0005f848;
@@ -22,26 +22,52 @@ impl<T: Copy> VolatilePointer<T> {
     /// # Safety
     ///
     /// The given offset must point to a field of the given type.
+    #[inline(always)]
     pub const unsafe fn field<U: Copy>(self, offset: usize) -> VolatilePointer<U> {
         assert!(offset < size_of::<T>());
         let inner = unsafe { self.0.cast::<u8>().add(offset) }.cast::<U>();
         VolatilePointer(inner)
     }
 
+    #[inline(always)]
     pub const fn cast<U: Copy>(self) -> VolatilePointer<U> {
         assert!(size_of::<T>() == size_of::<U>());
         VolatilePointer(self.0.cast())
     }
 
+    #[cfg(not(feature = "host-mock"))]
+    #[inline(always)]
     pub fn read(self) -> T {
         // SAFETY: constructor guarantees that address is valid and aligned
         unsafe { self.0.read_volatile() }
     }
 
+    #[cfg(not(feature = "host-mock"))]
+    #[inline(always)]
     pub fn write(self, val: T) {
         // SAFETY: constructor guarantees that address is valid and aligned
         unsafe { self.0.write_volatile(val) }
     }
+
+    #[cfg(feature = "host-mock")]
+    pub fn read(self) -> T {
+        let mut value = core::mem::MaybeUninit::<T>::zeroed();
+        // SAFETY: the value is zeroed, so every byte of it is initialized
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(value.as_mut_ptr().cast::<u8>(), size_of::<T>())
+        };
+        super::mock::load(self.0.addr(), bytes);
+        // SAFETY: the simulated memory holds whatever was last written here, just like hardware
+        unsafe { value.assume_init() }
+    }
+
+    #[cfg(feature = "host-mock")]
+    pub fn write(self, val: T) {
+        // SAFETY: T is Copy, so it's just bytes
+        let bytes =
+            unsafe { core::slice::from_raw_parts((&raw const val).cast::<u8>(), size_of::<T>()) };
+        super::mock::store(self.0.addr(), bytes);
+    }
 }
 
 /// SAFETY: we always use volatile semantics, and never mutate the address we contain,
@@ -62,7 +88,7 @@ impl<T: Copy, const N: usize> VolatilePointer<[T; N]> {
         assert!(start + slice.len() <= N);
         let offsets = start..start + slice.len();
         for (dst, offset) in slice.iter_mut().zip(offsets) {
-            *dst = unsafe { self.0.cast::<T>().add(offset).read_volatile() };
+            *dst = unsafe { self.element(offset) }.read();
         }
     }
 
@@ -71,12 +97,21 @@ impl<T: Copy, const N: usize> VolatilePointer<[T; N]> {
         let mut array = [core::mem::MaybeUninit::uninit(); M];
         let offsets = start..start + N;
         for (dst, offset) in array.iter_mut().zip(offsets) {
-            dst.write(unsafe { self.0.cast::<T>().add(offset).read_volatile() });
+            dst.write(unsafe { self.element(offset) }.read());
         }
         // SAFETY: we loaded every value
         unsafe { core::mem::transmute_copy(&array) }
     }
 
+    #[cfg(feature = "host-mock")]
+    pub fn write_slice(self, slice: &[T], start: usize) {
+        assert!(start + slice.len() <= N);
+        for (src, offset) in slice.iter().zip(start..start + slice.len()) {
+            unsafe { self.element(offset) }.write(*src);
+        }
+    }
+
+    #[cfg(not(feature = "host-mock"))]
     pub fn write_slice(self, slice: &[T], start: usize) {
         assert!(start + slice.len() <= N);
         let src = slice.as_ptr();
@@ -99,13 +134,20 @@ impl<T: Copy, const N: usize> VolatilePointer<[T; N]> {
         }
     }
 
+    #[inline(always)]
     pub const fn index(self, index: usize) -> VolatilePointer<T> {
         assert!(index < N);
         // unsafe { VolatilePointer(self.0.cast::<T>().add(index)) }
         unsafe { self.offset(index * size_of::<T>()) }
     }
 
+    /// SAFETY: the index must be in bounds.
+    const unsafe fn element(self, index: usize) -> VolatilePointer<T> {
+        VolatilePointer(self.0.cast::<T>().wrapping_add(index))
+    }
+
     /// SAFETY: the offset must be within this data's allocation.
+    #[inline(always)]
     const unsafe fn offset(self, offset: usize) -> VolatilePointer<T> {
         // below wacky antics avoid pointer provenance bugs for compile-time indexing
         let address: usize = unsafe { core::mem::transmute(self) };
@@ -113,6 +155,7 @@ impl<T: Copy, const N: usize> VolatilePointer<[T; N]> {
     }
 }
 
+#[cfg(not(feature = "host-mock"))]
 #[inline(always)]
 fn can_wordaligned_memcpy<T>(dst: *mut T, src: *const T, bytes: usize) -> bool {
     if core::mem::align_of::<T>() >= 4 {
@@ -149,7 +192,7 @@ macro_rules! impl_overaligned_volatile_pointer {
             }
 
             pub fn read_slice(self, slice: &mut [$typ], start: usize) {
-                assert!(start + slice.len() < N);
+                assert!(start + slice.len() <= N);
                 let indices = start..start + slice.len();
                 for (dst, index) in slice.iter_mut().zip(indices) {
                     let src: VolatilePointer<$typ> = unsafe { self.index_unchecked(index) };
@@ -302,3 +345,33 @@ macro_rules! mmstruct {
     };
 }
 pub(crate) use mmstruct;
+
+macro_rules! bool_enum {
+    (
+        $(#[$enum_attr:meta])*
+        $enum_vis:vis $name:ident($(#[$false_attr:meta])* $false:ident, $(#[$true_attr:meta])* $true:ident)
+    ) => {
+            $(#[$enum_attr])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            #[repr(u8)]
+            pub enum $name {
+                $(#[$false_attr])*
+                $false = 0,
+                $(#[$true_attr])*
+                $true = 1,
+            }
+            impl $name {
+                const fn into_bits(self) -> u8 {
+                    self as _
+                }
+
+                const fn from_bits(value: u8) -> Self {
+                    match value {
+                        0 => Self::$false,
+                        _ => Self::$true,
+                    }
+                }
+            }
+    };
+}
+pub(crate) use bool_enum;
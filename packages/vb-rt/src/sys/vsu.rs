@@ -2,7 +2,7 @@ use bitfield_struct::bitfield;
 
 use crate::sys::{
     VolatilePointer,
-    volatile::{mmio, mmstruct},
+    volatile::{bool_enum, mmio, mmstruct},
 };
 
 /** A waveform, made out of 32 6-bit unsigned integers. */
@@ -41,35 +41,6 @@ pub struct LevelData {
     pub left: u8,
 }
 
-macro_rules! bool_enum {
-    (
-        $(#[$enum_attr:meta])*
-        $enum_vis:vis $name:ident($(#[$false_attr:meta])* $false:ident, $(#[$true_attr:meta])* $true:ident)
-    ) => {
-            $(#[$enum_attr])*
-            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-            #[repr(u8)]
-            pub enum $name {
-                $(#[$false_attr])*
-                $false = 0,
-                $(#[$true_attr])*
-                $true = 1,
-            }
-            impl $name {
-                const fn into_bits(self) -> u8 {
-                    self as _
-                }
-
-                const fn from_bits(value: u8) -> Self {
-                    match value {
-                        0 => Self::$false,
-                        _ => Self::$true,
-                    }
-                }
-            }
-    };
-}
-
 bool_enum! {
     pub Direction(Shrink, Grow)
 }
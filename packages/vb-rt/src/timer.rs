@@ -0,0 +1,109 @@
+use crate::sys::hardware::{self, TimerControlData, TimerResolution};
+
+/// Stop the timer, and configure it to count down from `reload` to zero.
+/// The counter decrements once per `resolution`. When it reaches zero, it fires
+/// (if interrupts are enabled) and starts over, so it fires every `(reload + 1) * resolution`.
+pub fn configure(resolution: TimerResolution, reload: u16) {
+    // The timer must be disabled while its resolution or reload value changes.
+    hardware::TCR.write(
+        TimerControlData::new()
+            .with_t_clk_sel(resolution)
+            .with_z_stat_clr(true),
+    );
+    hardware::TLR.write(reload as u8);
+    hardware::THR.write((reload >> 8) as u8);
+}
+
+/// Stop the timer, and configure it to fire every `micros` microseconds.
+/// Uses the finer 20µs resolution whenever it can reach the interval, and 100µs for longer ones.
+/// Intervals are rounded down to the nearest tick, and clamped to the range the timer supports.
+/// Returns the interval it actually fires at, in microseconds.
+pub fn configure_interval(micros: u32) -> u32 {
+    let (resolution, tick_micros) = if micros <= 20 * 0x10000 {
+        (TimerResolution::Micros20, 20)
+    } else {
+        (TimerResolution::Micros100, 100)
+    };
+    let ticks = (micros / tick_micros).clamp(1, 0x10000);
+    configure(resolution, (ticks - 1) as u16);
+    ticks * tick_micros
+}
+
+/// Start the timer counting down.
+pub fn start() {
+    write_control(read_control().with_t_enb(true));
+}
+
+/// Stop the timer. The counter keeps its current value.
+pub fn stop() {
+    write_control(read_control().with_t_enb(false));
+}
+
+/// Fire a timer interrupt whenever the counter reaches zero.
+/// Interrupts must also be enabled in the PSW for the handler to run.
+pub fn enable_interrupts() {
+    write_control(read_control().with_tim_z_int(true));
+}
+
+pub fn disable_interrupts() {
+    write_control(read_control().with_tim_z_int(false));
+}
+
+/// Clear the zero status flag. Call this from your timer interrupt handler,
+/// or the interrupt will keep firing.
+pub fn acknowledge_interrupts() {
+    write_control(read_control().with_z_stat_clr(true));
+}
+
+/// Returns true if the counter has reached zero since the last acknowledgement.
+pub fn has_fired() -> bool {
+    hardware::TCR.read().z_stat()
+}
+
+/// Read the current value of the counter.
+pub fn counter() -> u16 {
+    let lo = hardware::TLR.read() as u16;
+    let hi = hardware::THR.read() as u16;
+    lo | (hi << 8)
+}
+
+fn read_control() -> TimerControlData {
+    hardware::TCR
+        .read()
+        .with_z_stat(false)
+        .with_z_stat_clr(false)
+}
+
+fn write_control(value: TimerControlData) {
+    hardware::TCR.write(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sys::mock;
+
+    #[test]
+    fn configure_interval_returns_the_programmed_interval() {
+        mock::reset();
+        assert_eq!(configure_interval(1000), 1000);
+        assert_eq!(counter(), 49);
+        assert_eq!(hardware::TCR.read().t_clk_sel(), TimerResolution::Micros20);
+
+        // Not a multiple of 20µs, so it's rounded down to the nearest 20µs tick
+        assert_eq!(configure_interval(1050), 1040);
+        assert_eq!(counter(), 51);
+        assert_eq!(hardware::TCR.read().t_clk_sel(), TimerResolution::Micros20);
+
+        // Too long for 20µs ticks, so it falls back to 100µs
+        assert_eq!(configure_interval(2_000_000), 2_000_000);
+        assert_eq!(counter(), 19_999);
+        assert_eq!(hardware::TCR.read().t_clk_sel(), TimerResolution::Micros100);
+
+        // Clamped to the shortest and longest intervals the timer supports
+        assert_eq!(configure_interval(5), 20);
+        assert_eq!(counter(), 0);
+        assert_eq!(configure_interval(u32::MAX), 100 * 0x10000);
+        assert_eq!(counter(), 0xffff);
+    }
+}
@@ -1,18 +1,19 @@
 #![no_std]
 #![allow(clippy::manual_dangling_ptr)]
-#![cfg(target_arch = "v810")]
+#![cfg(any(target_arch = "v810", feature = "host-mock"))]
 
 mod assets;
 
 use core::{
-    cell::UnsafeCell,
-    ops::{Deref, DerefMut},
-    ptr::NonNull,
-    sync::atomic::{AtomicBool, AtomicPtr, Ordering::Relaxed},
+    cell::RefCell,
+    sync::atomic::{AtomicPtr, Ordering::Relaxed},
 };
 
 pub use assets::WaveformData;
-use vb_rt::sys::{VolatilePointer, vsu};
+use vb_rt::{
+    interrupt::{self, Mutex},
+    sys::{VolatilePointer, vsu},
+};
 
 pub static WAVEFORMS: WaveformControl = WaveformControl(AtomicPtr::new(core::ptr::null_mut()));
 pub static CHANNELS: [SoundChannel; 6] = [const { SoundChannel::new() }; 6];
@@ -54,6 +55,7 @@ impl Controller {
         self.store(0x00000003 as *mut u32);
     }
 
+    #[inline(always)]
     pub fn set_status(&self, priority: Option<Priority>) {
         let status = match priority {
             Some(p) => (((p as u8) as u32) << 28 | 0x08000000) as *mut u32,
@@ -75,6 +77,7 @@ impl Controller {
         }
     }
 
+    #[inline(always)]
     pub fn command(&self) -> Option<Command> {
         let value = self.load();
         match value.addr() & 0x03 {
@@ -89,10 +92,12 @@ impl Controller {
         }
     }
 
+    #[inline(always)]
     fn load(&self) -> *mut u32 {
         self.0.load(Relaxed)
     }
 
+    #[inline(always)]
     fn store(&self, value: *mut u32) {
         self.0.store(value, Relaxed);
     }
@@ -105,6 +110,7 @@ pub enum Priority {
     High,
 }
 impl From<u8> for Priority {
+    #[inline(always)]
     fn from(value: u8) -> Self {
         match value {
             2 => Self::High,
@@ -173,27 +179,32 @@ impl SoundChannel {
     }
 }
 
-pub struct SoundPlayer(SyncRefCell<[ChannelState; 6]>);
+pub struct SoundPlayer(Mutex<RefCell<[ChannelState; 6]>>);
 impl SoundPlayer {
     pub const fn new() -> Self {
-        Self(SyncRefCell::new([
+        Self(Mutex::new(RefCell::new([
             ChannelState::new(0),
             ChannelState::new(1),
             ChannelState::new(2),
             ChannelState::new(3),
             ChannelState::new(4),
             ChannelState::new(5),
-        ]))
+        ])))
     }
 
-    pub fn tick(&self) {
-        self.load_waveforms();
-        let mut state = self.0.borrow_mut();
-        for channel in state.iter_mut() {
-            channel.tick();
+    vb_rt::ram_fn! {
+        pub fn tick(&self) {
+            self.load_waveforms();
+            interrupt::free(|cs| {
+                let mut state = self.0.borrow_ref_mut(cs);
+                for channel in state.iter_mut() {
+                    channel.tick();
+                }
+            });
         }
     }
 
+    #[inline(always)]
     fn load_waveforms(&self) {
         let waveform_set = WAVEFORMS.0.load(Relaxed).cast_const();
         if waveform_set.is_null() {
@@ -227,6 +238,8 @@ impl ChannelState {
         }
     }
 
+    // Everything `SoundPlayer::tick` calls per frame is inlined, so it all runs from WRAM too
+    #[inline(always)]
     fn tick(&mut self) {
         let controls = &CHANNELS[self.channel];
         let channel = vsu::CHANNELS.index(self.channel);
@@ -252,6 +265,9 @@ impl ChannelState {
     }
 }
 
+// SAFETY: the only pointer in here points into sound data, which lives forever and never changes.
+unsafe impl Send for SubChannelState {}
+
 #[derive(Debug)]
 struct SubChannelState {
     playing: *const u32,
@@ -272,6 +288,7 @@ impl SubChannelState {
         }
     }
 
+    #[inline(always)]
     fn handle_command(
         &mut self,
         cmd: Command,
@@ -307,10 +324,12 @@ impl SubChannelState {
         }
     }
 
+    #[inline(always)]
     fn is_playing(&self) -> bool {
         !self.playing.is_null() && !self.paused
     }
 
+    #[inline(always)]
     fn resume(&self, channel: VolatilePointer<vsu::Channel>) {
         for (offset, value) in self.shadowed.into_iter().enumerate() {
             let field = unsafe { channel.field::<u8>(offset << 2) };
@@ -318,6 +337,7 @@ impl SubChannelState {
         }
     }
 
+    #[inline(always)]
     fn tick(&mut self, channel: VolatilePointer<vsu::Channel>, silent: bool) -> Option<Priority> {
         if self.paused || self.playing.is_null() {
             // Not playing audio right now.
@@ -369,6 +389,7 @@ enum ChannelEvent {
     Jump { offset: isize },
 }
 impl ChannelEvent {
+    #[inline(always)]
     fn decode(value: u32) -> Self {
         let [b0, b1, b2, b3] = value.to_le_bytes();
         match b0 {
@@ -396,50 +417,158 @@ impl ChannelEvent {
     }
 }
 
-struct SyncRefCell<T> {
-    value: UnsafeCell<T>,
-    taken: AtomicBool,
-}
-impl<T> SyncRefCell<T> {
-    const fn new(value: T) -> Self {
-        Self {
-            value: UnsafeCell::new(value),
-            taken: AtomicBool::new(false),
-        }
+#[cfg(test)]
+mod tests {
+    use vb_rt::sys::mock;
+
+    use super::*;
+
+    const CHANNEL_0: usize = 0x01000400;
+
+    fn write(offset: u8, value: u8) -> u32 {
+        u32::from_le_bytes([1, 0, offset, value])
     }
-    fn borrow_mut(&self) -> RefMut<'_, T> {
-        if self.taken.load(Relaxed) {
-            panic!("borrowed twice");
-        }
-        self.taken.store(true, Relaxed);
-        // SAFETY: UnsafeCell's content can't be null
-        let value = unsafe { NonNull::new_unchecked(self.value.get()) };
-        RefMut {
-            value,
-            taken: &self.taken,
-        }
+
+    fn wait(frames: u32) -> u32 {
+        (frames + 1) << 8
     }
-}
-unsafe impl<T> Sync for SyncRefCell<T> {}
 
-struct RefMut<'a, T> {
-    value: NonNull<T>,
-    taken: &'a AtomicBool,
-}
+    fn jump(offset: i32) -> u32 {
+        (offset as u32) << 8 | 2
+    }
+
+    const DONE: u32 = 0;
 
-impl<T> Deref for RefMut<'_, T> {
-    type Target = T;
-    fn deref(&self) -> &Self::Target {
-        unsafe { self.value.as_ref() }
+    /// The first byte of each of the channel's registers, which are 4 bytes apart.
+    fn registers() -> [u8; 7] {
+        let bytes = mock::bytes(CHANNEL_0, 28);
+        core::array::from_fn(|index| bytes[index * 4])
     }
-}
-impl<T> DerefMut for RefMut<'_, T> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { self.value.as_mut() }
+
+    fn playing(data: &[u32]) -> SubChannelState {
+        let mut state = SubChannelState::new();
+        state.handle_command(
+            Command::Play(data.as_ptr().cast_mut(), Priority::High),
+            vsu::CHANNELS.index(0),
+            true,
+        );
+        state
     }
-}
-impl<T> Drop for RefMut<'_, T> {
-    fn drop(&mut self) {
-        self.taken.store(false, Relaxed);
+
+    #[test]
+    fn events_are_decoded() {
+        assert!(matches!(ChannelEvent::decode(DONE), ChannelEvent::Done));
+        assert!(matches!(
+            ChannelEvent::decode(wait(3)),
+            ChannelEvent::Wait { frames: 3 }
+        ));
+        assert!(matches!(
+            ChannelEvent::decode(write(0x14, 0xab)),
+            ChannelEvent::Write {
+                offset: 0x14,
+                value: 0xab
+            }
+        ));
+        assert!(matches!(
+            ChannelEvent::decode(jump(-3)),
+            ChannelEvent::Jump { offset: -3 }
+        ));
+    }
+
+    #[test]
+    fn writes_go_to_the_channel_until_a_wait() {
+        mock::reset();
+        let data = [
+            write(0x04, 0xff),
+            write(0x08, 0x12),
+            wait(0),
+            write(0x0c, 0x34),
+            DONE,
+        ];
+        let mut state = playing(&data);
+        let channel = vsu::CHANNELS.index(0);
+
+        assert_eq!(state.tick(channel, false), Some(Priority::High));
+        assert_eq!(registers(), [0, 0xff, 0x12, 0, 0, 0, 0]);
+
+        assert_eq!(state.tick(channel, false), None);
+        assert_eq!(registers(), [0, 0xff, 0x12, 0x34, 0, 0, 0]);
+        assert!(!state.is_playing());
+    }
+
+    #[test]
+    fn waits_last_for_whole_frames() {
+        mock::reset();
+        let data = [wait(2), write(0x04, 0xff), DONE];
+        let mut state = playing(&data);
+        let channel = vsu::CHANNELS.index(0);
+        for _ in 0..3 {
+            assert_eq!(state.tick(channel, false), Some(Priority::High));
+            assert_eq!(registers()[1], 0);
+        }
+        assert_eq!(state.tick(channel, false), None);
+        assert_eq!(registers()[1], 0xff);
+    }
+
+    #[test]
+    fn finishing_disables_the_channel() {
+        mock::reset();
+        mock::write_bytes(CHANNEL_0, &[0x9f]);
+        let data = [DONE];
+        let mut state = playing(&data);
+        assert_eq!(state.tick(vsu::CHANNELS.index(0), false), None);
+        assert_eq!(registers()[0], 0);
+        assert_eq!(state.tick(vsu::CHANNELS.index(0), false), None);
+    }
+
+    #[test]
+    fn jumps_loop_back() {
+        mock::reset();
+        let data = [
+            write(0x04, 0x11),
+            wait(0),
+            write(0x04, 0x22),
+            wait(0),
+            jump(-4),
+        ];
+        let mut state = playing(&data);
+        let channel = vsu::CHANNELS.index(0);
+        for expected in [0x11, 0x22, 0x11, 0x22, 0x11] {
+            assert_eq!(state.tick(channel, false), Some(Priority::High));
+            assert_eq!(registers()[1], expected);
+        }
+    }
+
+    #[test]
+    fn silent_channels_remember_what_they_would_have_written() {
+        mock::reset();
+        let data = [write(0x04, 0xff), write(0x10, 0x80), wait(0), DONE];
+        let mut state = playing(&data);
+        let channel = vsu::CHANNELS.index(0);
+        assert_eq!(state.tick(channel, true), Some(Priority::High));
+        assert_eq!(registers(), [0; 7]);
+
+        // Once whatever was in the way is done, the channel picks up where it would have been
+        state.resume(channel);
+        assert_eq!(registers(), [0, 0xff, 0, 0, 0x80, 0, 0]);
+    }
+
+    #[test]
+    fn paused_channels_dont_advance() {
+        mock::reset();
+        let data = [write(0x10, 0xf0), wait(0), write(0x04, 0xff), DONE];
+        let mut state = playing(&data);
+        let channel = vsu::CHANNELS.index(0);
+        state.tick(channel, false);
+
+        state.handle_command(Command::Pause, channel, false);
+        assert_eq!(registers()[4], 0);
+        assert_eq!(state.tick(channel, false), None);
+        assert_eq!(registers()[1], 0);
+
+        state.handle_command(Command::Resume, channel, false);
+        assert_eq!(registers()[4], 0xf0);
+        assert_eq!(state.tick(channel, false), None);
+        assert_eq!(registers()[1], 0xff);
     }
 }